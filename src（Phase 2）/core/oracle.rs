@@ -6,17 +6,53 @@ use rug::{Integer, Complete};
 use std::sync::Arc;
 use std::collections::HashSet;
 
+/// 单次 `suggest_candidates` 调用中试除次数的默认上限
+const DEFAULT_MAX_TRIAL_DIVISIONS: usize = 100_000;
+
+/// 单次空间查询最多枚举的坐标数，防止高维大半径下的组合爆炸
+/// 中心单元与回绕产生的重复坐标同样计入预算。
+pub const MAX_PROBED_CELLS: usize = 10_000;
+
+#[cfg(test)]
+thread_local! {
+    /// 测试用: 统计 `query_spatial_neighbors` 枚举到的坐标数 (含重复)
+    static BALL_VISITS: std::cell::Cell<usize> = std::cell::Cell::new(0);
+}
+
 /// 🔮 HTPOracle: 代数预言机
 /// 它的职责是利用张量的拓扑结构，直接“预知”合法的候选集，
 /// 从而避免暴力的词表遍历。
 pub struct HTPOracle {
     /// 绑定的宿主神经元（提供内存和权重）
     neuron: Arc<HTPNeuron>,
+
+    /// [Optional]: 已知的 Token 素数 (来自词表)，用于分解被聚合的 P 因子
+    token_primes: Vec<Integer>,
+
+    /// 试除预算，防止大词表上的分解失控
+    max_trial_divisions: usize,
 }
 
 impl HTPOracle {
     pub fn new(neuron: Arc<HTPNeuron>) -> Self {
-        HTPOracle { neuron }
+        HTPOracle {
+            neuron,
+            token_primes: Vec::new(),
+            max_trial_divisions: DEFAULT_MAX_TRIAL_DIVISIONS,
+        }
+    }
+
+    /// 登记词表中的 Token 素数，开启递归分解回退
+    pub fn set_token_primes<I: IntoIterator<Item = Integer>>(&mut self, primes: I) {
+        let mut primes: Vec<Integer> = primes.into_iter().filter(|p| *p > 1).collect();
+        primes.sort();
+        primes.dedup();
+        self.token_primes = primes;
+    }
+
+    /// 设置单次查询的试除上限
+    pub fn set_max_trial_divisions(&mut self, limit: usize) {
+        self.max_trial_divisions = limit;
     }
 
     /// 🔍 Core Function: 快速提取“合法邻居” (Candidate Extraction)
@@ -27,6 +63,7 @@ impl HTPOracle {
         let weight = &self.neuron.p_weight;
 
         let mut candidates = HashSet::new();
+        let mut budget = self.max_trial_divisions;
 
         // [Direct Access]: 直接遍历稀疏张量的活跃节点
         // 这里我们利用了 HyperTensor 的 "Sparse" 特性。
@@ -40,14 +77,21 @@ impl HTPOracle {
             // (防止噪声干扰)
             let (quotient, rem) = tuple.p_factor.div_rem_ref(weight).into();
 
-            if rem == Integer::from(0) {
+            let exact = rem == Integer::from(0)
+                && (self.token_primes.is_empty() || self.token_primes.binary_search(&quotient).is_ok());
+
+            if exact {
                 // 找到了！quotient 就是原始的 Token Prime
                 candidates.insert(quotient);
-            } else {
-                // 如果不能整除，说明这个节点可能被聚合了或者是噪声，
-                // 或者是其他神经元留下的痕迹。
-                // 在更复杂的实现中，我们可能需要递归分解 (Recursive Factorization)。
+            } else if !self.token_primes.is_empty() {
+                // 节点可能被聚合了 (P = P_tok1 * W * P_tok2 * W ...)，
+                // 用已知 Token 素数做有界的试除分解 (Recursive Factorization)。
+                self.factor_with_known_primes(&tuple.p_factor, weight, &mut candidates, &mut budget);
+            } else if rem == Integer::from(0) {
+                // 未登记词表时保持旧行为
+                candidates.insert(quotient);
             }
+            // 否则：噪声或其他神经元留下的痕迹
         }
 
         // 返回候选集。
@@ -56,33 +100,177 @@ impl HTPOracle {
         Ok(candidates)
     }
 
+    /// 剥离权重因子后，用已知 Token 素数试除，每找到一个因子就加入候选集
+    fn factor_with_known_primes(
+        &self,
+        p_factor: &Integer,
+        weight: &Integer,
+        candidates: &mut HashSet<Integer>,
+        budget: &mut usize,
+    ) {
+        let mut remaining = p_factor.clone();
+        if *weight > 1 {
+            while remaining.is_divisible(weight) {
+                remaining /= weight;
+            }
+        }
+
+        for prime in &self.token_primes {
+            if remaining == 1 || *budget == 0 {
+                break;
+            }
+            *budget -= 1;
+
+            if remaining.is_divisible(prime) {
+                candidates.insert(prime.clone());
+                while remaining.is_divisible(prime) {
+                    remaining /= prime;
+                }
+            }
+        }
+    }
+
     /// 🧭 Spatial Query: 空间邻近查询 (高级功能)
     /// 如果我们假设坐标 (Coordinate) 蕴含了语义（如 Phase 2 所述），
     /// 我们还可以查询“当前关注点”附近的坐标。
-    pub fn query_spatial_neighbors(&self, active_coords: &[Vec<usize>]) -> Result<Vec<AffineTuple>, String> {
+    /// 枚举曼哈顿距离 `1..=radius` 内的所有坐标 (环面回绕)，去重并跳过空单元。
+    /// 最多枚举 `MAX_PROBED_CELLS` 个坐标 (含中心与重复坐标)。
+    pub fn query_spatial_neighbors(&self, active_coords: &[Vec<usize>], radius: usize) -> Result<Vec<AffineTuple>, String> {
         let memory_guard = self.neuron.memory.read().map_err(|_| "Lock poisoned")?;
+        let side = memory_guard.side_length;
         let mut neighbors = Vec::new();
+        if side == 0 {
+            return Ok(neighbors);
+        }
+
+        let centers: HashSet<&Vec<usize>> = active_coords.iter().collect();
+        let mut visited: HashSet<Vec<usize>> = HashSet::new();
+        let mut probed = 0usize;
 
         for coord in active_coords {
-            // 简单的“曼哈顿距离”邻居搜索 demo
-            // 尝试在每个维度 +/- 1
-            for dim in 0..coord.len() {
-                let mut next_coord = coord.clone();
-                // +1 Neighbor
-                next_coord[dim] = (next_coord[dim] + 1) % memory_guard.side_length;
-                if let Some(tuple) = memory_guard.data.get(&next_coord) {
-                    neighbors.push(tuple.clone());
+            if probed >= MAX_PROBED_CELLS {
+                break;
+            }
+            let mut current = coord.clone();
+            Self::enumerate_ball(coord, 0, radius, side, &mut current, &mut |candidate| {
+                if probed >= MAX_PROBED_CELLS {
+                    return false;
+                }
+                // 每次枚举都计入预算，而不只是首次访问的坐标
+                probed += 1;
+                #[cfg(test)]
+                BALL_VISITS.with(|c| c.set(c.get() + 1));
+                if centers.contains(candidate) || !visited.insert(candidate.clone()) {
+                    return true;
                 }
-                
-                // -1 Neighbor
-                let mut prev_coord = coord.clone();
-                prev_coord[dim] = if prev_coord[dim] == 0 { memory_guard.side_length - 1 } else { prev_coord[dim] - 1 };
-                if let Some(tuple) = memory_guard.data.get(&prev_coord) {
+                if let Some(tuple) = memory_guard.data.get(candidate) {
                     neighbors.push(tuple.clone());
                 }
-            }
+                true
+            });
         }
         
         Ok(neighbors)
     }
+
+    /// 深度优先枚举：在第 `dim` 维上分配剩余的曼哈顿预算。
+    /// `visit` 返回 false 时终止枚举。
+    fn enumerate_ball<F: FnMut(&Vec<usize>) -> bool>(
+        center: &[usize],
+        dim: usize,
+        remaining: usize,
+        side: usize,
+        current: &mut Vec<usize>,
+        visit: &mut F,
+    ) -> bool {
+        if dim == center.len() {
+            return visit(current);
+        }
+
+        // 回绕后不同偏移可能落在同一坐标，去重交给调用方
+        let max_offset = remaining.min(side.saturating_sub(1)) as i64;
+        for offset in -max_offset..=max_offset {
+            let shifted = (center[dim] as i64 + offset).rem_euclid(side as i64) as usize;
+            current[dim] = shifted;
+            let used = offset.unsigned_abs() as usize;
+            if !Self::enumerate_ball(center, dim + 1, remaining - used, side, current, visit) {
+                current[dim] = center[dim];
+                return false;
+            }
+        }
+        current[dim] = center[dim];
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::algebra::ClassGroupElement;
+
+    fn neuron_with_node(p_factor: Integer) -> Arc<HTPNeuron> {
+        let d = Integer::from(-23);
+        let neuron = HTPNeuron::new(Integer::from(7), 2, 4, d.clone());
+        neuron.memory.write().unwrap().data.insert(
+            vec![0, 0],
+            AffineTuple { p_factor, q_shift: ClassGroupElement::identity(&d) },
+        );
+        Arc::new(neuron)
+    }
+
+    #[test]
+    fn test_neighbor_two_cells_away_needs_radius_two() {
+        let d = Integer::from(-23);
+        let neuron = HTPNeuron::new(Integer::from(7), 2, 8, d.clone());
+        {
+            let mut memory = neuron.memory.write().unwrap();
+            let planted = AffineTuple { p_factor: Integer::from(11), q_shift: ClassGroupElement::identity(&d) };
+            memory.data.insert(vec![5, 3], planted.clone());
+            // 中心单元自身不算邻居
+            memory.data.insert(vec![3, 3], planted);
+        }
+        let oracle = HTPOracle::new(Arc::new(neuron));
+        let center = vec![vec![3, 3]];
+
+        assert!(oracle.query_spatial_neighbors(&center, 1).unwrap().is_empty());
+        let found = oracle.query_spatial_neighbors(&center, 2).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].p_factor, 11);
+        // 更大半径不会重复返回同一单元
+        assert_eq!(oracle.query_spatial_neighbors(&center, 6).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_probe_budget_counts_duplicate_visits() {
+        let d = Integer::from(-23);
+        // 边长 2 的 12 维环面：半径 12 的球几乎全是回绕重复，唯一坐标只有 4096 个
+        let neuron = HTPNeuron::new(Integer::from(7), 12, 2, d);
+        let oracle = HTPOracle::new(Arc::new(neuron));
+        let centers: Vec<Vec<usize>> = (0..4).map(|i| vec![i % 2; 12]).collect();
+
+        BALL_VISITS.with(|c| c.set(0));
+        assert!(oracle.query_spatial_neighbors(&centers, 12).unwrap().is_empty());
+        let visits = BALL_VISITS.with(|c| c.get());
+        assert_eq!(visits, MAX_PROBED_CELLS);
+    }
+
+    #[test]
+    fn test_aggregated_node_is_factored() {
+        // 两个 Token 被聚合进同一节点: (11 * 7) * (13 * 7)
+        let neuron = neuron_with_node(Integer::from(11 * 7 * 13 * 7));
+
+        let plain = HTPOracle::new(neuron.clone());
+        let before = plain.suggest_candidates().unwrap();
+        assert!(!before.contains(&Integer::from(11)));
+
+        let mut oracle = HTPOracle::new(neuron);
+        oracle.set_token_primes([11, 13, 17].iter().map(|&p| Integer::from(p)));
+        let found = oracle.suggest_candidates().unwrap();
+        let expected: HashSet<Integer> = [11, 13].iter().map(|&p| Integer::from(p)).collect();
+        assert_eq!(found, expected);
+
+        // 预算耗尽时停止分解
+        oracle.set_max_trial_divisions(1);
+        assert_eq!(oracle.suggest_candidates().unwrap().len(), 1);
+    }
 }
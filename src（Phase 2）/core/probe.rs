@@ -21,6 +21,21 @@ pub struct HTPProbe {
     /// 阈值灵敏度：决定多少概率的 Attention 值得被转化为“硬逻辑”
     /// 范围 [0.0, 1.0]，默认 0.1
     attention_threshold: f32,
+
+    /// 逻辑惩罚系数。越大则 HTP 对幻觉的容忍度越低。默认 5.0
+    alpha: f32,
+
+    /// 命中 Oracle 候选集时的逻辑分数，默认 1.0
+    hit_score: f32,
+
+    /// 未命中时的逻辑分数，默认 0.01
+    miss_score: f32,
+
+    /// 可选的高置信度奖励系数。None 表示不奖励
+    beta: Option<f32>,
+
+    /// 注意力权重映射到的最大幂次: power = round(weight * max_attention_power)，至少为 1。默认 4
+    max_attention_power: u32,
 }
 
 impl HTPProbe {
@@ -30,37 +45,80 @@ impl HTPProbe {
             neuron,
             oracle,
             attention_threshold: threshold,
+            alpha: 5.0,
+            hit_score: 1.0,
+            miss_score: 0.01,
+            beta: None,
+            max_attention_power: 4,
         }
     }
 
+    /// 设置惩罚系数 alpha
+    pub fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+
+    /// 设置命中 / 未命中分数
+    pub fn set_scores(&mut self, hit_score: f32, miss_score: f32) {
+        self.hit_score = hit_score;
+        self.miss_score = miss_score;
+    }
+
+    /// 设置高置信度奖励系数 beta (None 关闭奖励)
+    pub fn set_beta(&mut self, beta: Option<f32>) {
+        self.beta = beta;
+    }
+
+    /// 设置注意力阈值 (无需重建探针与 Oracle)
+    pub fn set_attention_threshold(&mut self, threshold: f32) {
+        self.attention_threshold = threshold;
+    }
+
+    /// 设置注意力权重的最大幂次
+    pub fn set_max_attention_power(&mut self, max_power: u32) {
+        self.max_attention_power = max_power;
+    }
+
     /// 🔄 1. Attention-to-Prime Converter
     /// 将 Transformer 的注意力分布转化为代数输入流
     pub fn quantize_attention(
         &self, 
         token_ids: &[u32], 
         attention_weights: &[f32]
+    ) -> Result<Vec<AffineTuple>, String> {
+        self.quantize_attention_with(token_ids, attention_weights, self.attention_threshold)
+    }
+
+    /// 🔄 1b. 与 `quantize_attention` 相同，但本次调用使用给定的 `threshold`
+    /// 便于在同一会话中扫描阈值，不修改探针自身的配置。
+    pub fn quantize_attention_with(
+        &self,
+        token_ids: &[u32],
+        attention_weights: &[f32],
+        threshold: f32
     ) -> Result<Vec<AffineTuple>, String> {
         if token_ids.len() != attention_weights.len() {
             return Err("Dimension mismatch between tokens and weights".into());
         }
 
         let mut algebraic_stream = Vec::new();
+        // 假设 Q 为 Generator (代表标准语义方向)
+        let generator = ClassGroupElement::generator(&self.neuron.discriminant);
 
         for (i, &weight) in attention_weights.iter().enumerate() {
             // [Filter]: 只有权重超过阈值的 Token 才有资格参与逻辑演化
             // 这是一个 "Soft-to-Hard" 的关键转换点
-            if weight > self.attention_threshold {
+            if weight > threshold {
                 let token_id_str = format!("tok_{}", token_ids[i]);
                 
                 // [Mapping]: Token ID -> Prime (P)
                 let p = hash_to_prime(&token_id_str, 64).map_err(|e| e.to_string())?;
                 
-                // [Mapping]: Weight -> Power (Optional)
-                // 我们可以让权重影响演化的深度，或者简单地作为开关。
-                // 这里为了简化，只要通过阈值，就视为有效算子。
-                
-                // 构造对应的 AffineTuple，假设 Q 为 Generator (代表标准语义方向)
-                let q = ClassGroupElement::generator(&self.neuron.discriminant);
+                // [Mapping]: Weight -> Power
+                // 权重越大，代数位移越深: Q = G ^ round(weight * max_power)
+                // 通过阈值的算子至少为一次幂，保证不会退化为单位元。
+                let power = ((weight * self.max_attention_power as f32).round() as u32).max(1);
+                let q = generator.pow(&Integer::from(power), &self.neuron.discriminant)?;
                 
                 algebraic_stream.push(AffineTuple {
                     p_factor: p,
@@ -80,6 +138,18 @@ impl HTPProbe {
         context_stream: Vec<AffineTuple>,
         next_token_id: u32
     ) -> Result<f32, String> {
+        let scores = self.score_batch(context_stream, &[next_token_id])?;
+        Ok(scores[0])
+    }
+
+    /// 📦 2b. Batch Logic Validator
+    /// 对整个候选 Token 集合打分：神经元只激活一次，Oracle 候选集只提取一次。
+    /// 返回的分数与 `candidate_token_ids` 一一对应，可直接交给 `apply_veto`。
+    pub fn score_batch(
+        &self,
+        context_stream: Vec<AffineTuple>,
+        candidate_token_ids: &[u32]
+    ) -> Result<Vec<f32>, String> {
         // Step A: 运行 HTP 神经元的演化，激活内部记忆张量
         // 这会更新 Neuron 内部的 Tensor 状态
         let (_expected_state, _proof) = self.neuron.activate(context_stream, 1)?;
@@ -88,20 +158,24 @@ impl HTPProbe {
         // 这是 O(Active_Memory) 的操作，远快于遍历词表
         let candidates = self.oracle.suggest_candidates()?;
         
-        // Step C: 将 Transformer 预测的 Token 转化为素数
-        let token_str = format!("tok_{}", next_token_id);
-        let candidate_p = hash_to_prime(&token_str, 64).map_err(|e| e.to_string())?;
-        
-        // Step D: O(1) 集合查询
-        if candidates.contains(&candidate_p) {
-            // 命中！绝对合法的代数后继
-            Ok(1.0)
-        } else {
-            // 未命中。
-            // 可能是幻觉，也可能是该概念从未在上下文中出现过（Out-of-Distribution）。
-            // 我们给予严厉的惩罚。
-            Ok(0.01)
+        let mut scores = Vec::with_capacity(candidate_token_ids.len());
+        for &token_id in candidate_token_ids {
+            // Step C: 将 Transformer 预测的 Token 转化为素数
+            let token_str = format!("tok_{}", token_id);
+            let candidate_p = hash_to_prime(&token_str, 64).map_err(|e| e.to_string())?;
+            
+            // Step D: O(1) 集合查询
+            if candidates.contains(&candidate_p) {
+                // 命中！绝对合法的代数后继
+                scores.push(self.hit_score);
+            } else {
+                // 未命中。
+                // 可能是幻觉，也可能是该概念从未在上下文中出现过（Out-of-Distribution）。
+                // 我们给予严厉的惩罚。
+                scores.push(self.miss_score);
+            }
         }
+        Ok(scores)
     }
 
     /// 🚫 3. The Veto Mechanism (阻断机制)
@@ -112,16 +186,82 @@ impl HTPProbe {
         token_ids: &[u32],
         logic_scores: &[f32]
     ) {
-        // alpha: 逻辑惩罚系数。越大则 HTP 对幻觉的容忍度越低。
-        let alpha = 5.0; 
-
         for (i, &score) in logic_scores.iter().enumerate() {
             if score < 0.5 {
                 // 如果逻辑置信度低，大幅降低 Logit
                 // Logit = Logit - alpha * (1 - score)
-                original_logits[i] -= alpha * (1.0 - score);
+                original_logits[i] -= self.alpha * (1.0 - score);
+            } else if let Some(beta) = self.beta {
+                // 如果逻辑置信度高，给予微弱奖励
+                // Logit = Logit + beta * (score - 0.5)
+                original_logits[i] += beta * (score - 0.5);
             }
-            // 如果逻辑置信度高，保持不变（或者微弱奖励）
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe() -> HTPProbe {
+        let neuron = HTPNeuron::new(Integer::from(7), 2, 4, Integer::from(-23));
+        HTPProbe::new(Arc::new(neuron), 0.1)
+    }
+
+    #[test]
+    fn test_attention_weight_scales_operator() {
+        let probe = probe();
+        let tokens = [5, 5, 5];
+        // 0.05 低于阈值被过滤；0.3 -> G^1，0.6 -> G^2 (Delta = -23 的类数为 3)
+        let stream = probe.quantize_attention(&tokens, &[0.05, 0.3, 0.6]).unwrap();
+
+        assert_eq!(stream.len(), 2);
+        assert_eq!(stream[0].p_factor, stream[1].p_factor);
+        assert_ne!(stream[0].q_shift, stream[1].q_shift);
+    }
+
+    #[test]
+    fn test_threshold_sweep_changes_stream_length() {
+        let mut probe = probe();
+        let tokens = [1, 2, 3, 4];
+        let weights = [0.05, 0.2, 0.4, 0.8];
+
+        let lengths: Vec<usize> = [0.0, 0.1, 0.3, 0.5, 0.9]
+            .iter()
+            .map(|&t| probe.quantize_attention_with(&tokens, &weights, t).unwrap().len())
+            .collect();
+        assert_eq!(lengths, vec![4, 3, 2, 1, 0]);
+
+        // 覆盖参数不影响探针自身的阈值 (0.1)
+        assert_eq!(probe.quantize_attention(&tokens, &weights).unwrap().len(), 3);
+
+        probe.set_attention_threshold(0.5);
+        assert_eq!(probe.quantize_attention(&tokens, &weights).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_larger_alpha_penalizes_harder() {
+        let tokens = [1, 2];
+        let scores = [0.01, 1.0];
+
+        let mut mild = probe();
+        mild.set_alpha(1.0);
+        let mut mild_logits = [0.0f32, 0.0];
+        mild.apply_veto(&mut mild_logits, &tokens, &scores);
+
+        let mut strict = probe();
+        strict.set_alpha(10.0);
+        let mut strict_logits = [0.0f32, 0.0];
+        strict.apply_veto(&mut strict_logits, &tokens, &scores);
+
+        assert!(strict_logits[0] < mild_logits[0]);
+        // 高置信度 Token 默认不受影响
+        assert_eq!(strict_logits[1], 0.0);
+
+        // 开启 beta 后获得奖励
+        strict.set_beta(Some(2.0));
+        strict.apply_veto(&mut strict_logits, &tokens, &scores);
+        assert!(strict_logits[1] > 0.0);
+    }
+}
@@ -1,6 +1,8 @@
 use rand::prelude::*;
 use rand_distr::{Distribution, Normal};
 use rand::rngs::StdRng;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
 use crate::dsl::schema::{ProofAction, ProofBundle};
 // [Fix] 移除了不存在的 EnergyProfile 引用
 use crate::dsl::stp_bridge::STPContext; 
@@ -15,27 +17,32 @@ use crate::dsl::stp_bridge::STPContext;
 /// the search trajectory.
 /// -------------------------------------------------------------------
 
-const BIAS_DIM: usize = 16;
-const EMBEDDING_DIM: usize = 128; 
+/// Default control-space dimension (size of the bias vector).
+pub const DEFAULT_BIAS_DIM: usize = 16;
+/// Default semantic-space dimension (number of logits the bias is projected onto).
+pub const DEFAULT_EMBEDDING_DIM: usize = 128;
+
+/// Default number of steps between basis rotations.
+pub const DEFAULT_ROTATION_PERIOD: usize = 15;
 
 /// The algebraic control signal.
 #[derive(Clone, Debug)]
 pub struct BiasVector {
-    pub components: [f64; BIAS_DIM],
+    pub components: Vec<f64>,
 }
 
 impl BiasVector {
-    pub fn new_zero() -> Self {
-        BiasVector { components: [0.0; BIAS_DIM] }
+    pub fn new_zero(dim: usize) -> Self {
+        BiasVector { components: vec![0.0; dim] }
     }
 
     /// Perturbs the vector locally using a deterministic RNG.
     pub fn perturb(&self, rng: &mut StdRng, intensity: f64) -> Self {
         let normal = Normal::new(0.0, 1.0).unwrap();
-        let mut new_comps = self.components;
+        let mut new_comps = self.components.clone();
         
         // Select a random dimension to tweak
-        let idx = rng.gen_range(0..BIAS_DIM);
+        let idx = rng.gen_range(0..new_comps.len());
         new_comps[idx] += normal.sample(rng) * intensity;
         
         // Tanh activation (soft-clipping)
@@ -43,44 +50,108 @@ impl BiasVector {
 
         BiasVector { components: new_comps }
     }
+
+    /// Annealed multi-dimension perturbation.
+    /// Touches `k` distinct dimensions where `k` shrinks with `temperature`:
+    /// hot searches move many components per step to escape broad basins,
+    /// cold searches degrade to the single-dimension `perturb`.
+    /// The choice of `k` and of the indices is fully determined by the seeded RNG.
+    pub fn perturb_annealed(&self, rng: &mut StdRng, intensity: f64, temperature: f64) -> Self {
+        let dim = self.components.len();
+        let k = Self::annealed_count(dim, temperature);
+
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let mut new_comps = self.components.clone();
+
+        for idx in rand::seq::index::sample(rng, dim, k).into_iter() {
+            new_comps[idx] += normal.sample(rng) * intensity;
+            // Tanh activation (soft-clipping), per touched dimension
+            new_comps[idx] = new_comps[idx].tanh();
+        }
+
+        BiasVector { components: new_comps }
+    }
+
+    /// Number of dimensions touched at a given temperature: round(T * dim / 4), clamped to [1, dim].
+    fn annealed_count(dim: usize, temperature: f64) -> usize {
+        let k = (temperature.max(0.0) * dim as f64 / 4.0).round() as usize;
+        k.clamp(1, dim.max(1))
+    }
+}
+
+/// Upper bound on cached projectors. The cache is flushed when it is exceeded.
+const PROJECTION_CACHE_CAPACITY: usize = 64;
+
+type ProjectionKey = (u64, usize, usize);
+
+/// Process-wide projector cache, keyed by (seed, embedding_dim, bias_dim).
+/// Generation is fully deterministic, so sharing results is safe.
+fn projection_cache() -> &'static Mutex<HashMap<ProjectionKey, ProjectionMatrix>> {
+    static CACHE: OnceLock<Mutex<HashMap<ProjectionKey, ProjectionMatrix>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Test-only: counts cache misses in `ProjectionMatrix::new_from_seed` on this thread.
+    static PROJECTION_MISSES: std::cell::Cell<usize> = std::cell::Cell::new(0);
 }
 
 /// The Projector bridging Control Space -> Semantic Space.
 /// MUST be deterministic based on the seed.
+/// Weights are shared behind an `Arc`, so clones are cheap.
 #[derive(Clone)]
 struct ProjectionMatrix {
-    weights: Vec<Vec<f64>>,
+    weights: Arc<Vec<Vec<f64>>>,
 }
 
 impl ProjectionMatrix {
-    /// Initialize matrix using a specific seed.
+    /// Initialize matrix using a specific seed (cached).
     /// This ensures W_proj is identical on Server and Verifier.
-    fn new_from_seed(seed: u64) -> Self {
+    fn new_from_seed(seed: u64, embedding_dim: usize, bias_dim: usize) -> Self {
+        let key = (seed, embedding_dim, bias_dim);
+
+        if let Ok(cache) = projection_cache().lock() {
+            if let Some(hit) = cache.get(&key) {
+                return hit.clone();
+            }
+        }
+
+        #[cfg(test)]
+        PROJECTION_MISSES.with(|c| c.set(c.get() + 1));
+        let matrix = Self::generate(seed, embedding_dim, bias_dim);
+
+        if let Ok(mut cache) = projection_cache().lock() {
+            if cache.len() >= PROJECTION_CACHE_CAPACITY {
+                cache.clear();
+            }
+            cache.insert(key, matrix.clone());
+        }
+        matrix
+    }
+
+    /// Uncached generation of W_proj.
+    fn generate(seed: u64, embedding_dim: usize, bias_dim: usize) -> Self {
         // We use the seed + a constant to separate Matrix gen from other randomness
-        let mut rng = StdRng::seed_from_u64(seed + 0xDEADBEEF); 
-        let normal = Normal::new(0.0, (1.0 / BIAS_DIM as f64).sqrt()).unwrap();
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(0xDEADBEEF)); 
+        let normal = Normal::new(0.0, (1.0 / bias_dim as f64).sqrt()).unwrap();
 
-        let weights = (0..EMBEDDING_DIM)
+        let weights = (0..embedding_dim)
             .map(|_| {
-                (0..BIAS_DIM)
+                (0..bias_dim)
                     .map(|_| normal.sample(&mut rng))
                     .collect()
             })
             .collect();
 
-        ProjectionMatrix { weights }
+        ProjectionMatrix { weights: Arc::new(weights) }
     }
 
     fn project(&self, bias: &BiasVector) -> Vec<f64> {
-        let mut output = vec![0.0; EMBEDDING_DIM];
-        for i in 0..EMBEDDING_DIM {
-            let mut sum = 0.0;
-            for j in 0..BIAS_DIM {
-                sum += self.weights[i][j] * bias.components[j];
-            }
-            output[i] = sum;
-        }
-        output
+        self.weights
+            .iter()
+            .map(|row| row.iter().zip(bias.components.iter()).map(|(w, b)| w * b).sum())
+            .collect()
     }
 }
 
@@ -88,6 +159,41 @@ pub struct VapoConfig {
     pub max_iterations: usize,
     pub initial_temperature: f64,
     pub valuation_decay: f64,
+    /// Control-space dimension (length of `BiasVector::components`).
+    pub bias_dim: usize,
+    /// Semantic-space dimension. Should match the Generator's logits length.
+    pub embedding_dim: usize,
+    /// Echo progress to stdout. Telemetry is always recorded in `OptimizeStats`.
+    pub verbose: bool,
+    /// Rotate the projection basis every `rotation_period` non-converged steps. `0` disables rotation.
+    pub rotation_period: usize,
+}
+
+impl Default for VapoConfig {
+    fn default() -> Self {
+        VapoConfig {
+            max_iterations: 50,
+            initial_temperature: 1.0,
+            valuation_decay: 0.95,
+            bias_dim: DEFAULT_BIAS_DIM,
+            embedding_dim: DEFAULT_EMBEDDING_DIM,
+            verbose: false,
+            rotation_period: DEFAULT_ROTATION_PERIOD,
+        }
+    }
+}
+
+/// Structured telemetry of a single `optimize` run.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OptimizeStats {
+    /// Loop iterations actually executed.
+    pub iterations: usize,
+    /// Whether the best energy reached zero (`<= 1e-6`).
+    pub converged: bool,
+    /// Number of basis rotations performed.
+    pub rotations: usize,
+    /// Energy of the candidate evaluated at each iteration.
+    pub energy_history: Vec<f64>,
 }
 
 /// The main controller that runs VAPO.
@@ -98,11 +204,7 @@ pub struct BiasController {
 impl BiasController {
     pub fn new(config: Option<VapoConfig>) -> Self {
         BiasController {
-            config: config.unwrap_or(VapoConfig {
-                max_iterations: 50,
-                initial_temperature: 1.0,
-                valuation_decay: 0.95,
-            }),
+            config: config.unwrap_or_default(),
         }
     }
 
@@ -118,15 +220,55 @@ impl BiasController {
     where
         F: Fn(&[f64]) -> ProofAction,
     {
-        println!("🛡️ [VAPO] Init: Seed={}, ContextHash={:x}", seed, md5::compute(context_str));
+        self.optimize_with_stats(context_str, seed, raw_logits, stp_ctx, decode_fn).0
+    }
+
+    /// `optimize` with the standard argmax decode built in.
+    /// Logit `i` selects `action_table[i]`; logits past the end of the table are ignored.
+    /// Use the closure version when the decode is anything other than a plain table lookup.
+    ///
+    /// Panics if `action_table` is empty.
+    pub fn optimize_logits(
+        &self,
+        context_str: &str,
+        seed: u64,
+        raw_logits: &[f64],
+        stp_ctx: &mut STPContext,
+        action_table: &[ProofAction]
+    ) -> ProofBundle {
+        assert!(!action_table.is_empty(), "optimize_logits requires a non-empty action table");
+        self.optimize(context_str, seed, raw_logits, stp_ctx, |logits| {
+            let scope = &logits[..logits.len().min(action_table.len())];
+            action_table[argmax(scope)].clone()
+        })
+    }
+
+    /// Same as `optimize`, additionally returning the run's `OptimizeStats`.
+    pub fn optimize_with_stats<F>(
+        &self,
+        context_str: &str,
+        seed: u64,
+        raw_logits: &[f64],
+        stp_ctx: &mut STPContext,
+        decode_fn: F
+    ) -> (ProofBundle, OptimizeStats)
+    where
+        F: Fn(&[f64]) -> ProofAction,
+    {
+        let verbose = self.config.verbose;
+        let mut stats = OptimizeStats::default();
+        if verbose {
+            println!("🛡️ [VAPO] Init: Seed={}, ContextHash={}", seed, ProofBundle::hash_context(context_str));
+        }
 
         // 1. Deterministic Initialization
         let mut rng = StdRng::seed_from_u64(seed);
-        let mut projector = ProjectionMatrix::new_from_seed(seed);
-        let mut current_bias = BiasVector::new_zero();
+        let mut projector = ProjectionMatrix::new_from_seed(seed, self.config.embedding_dim, self.config.bias_dim);
+        let mut current_bias = BiasVector::new_zero(self.config.bias_dim);
         
         let mut temperature = self.config.initial_temperature;
         let mut best_energy = f64::MAX;
+        let mut current_energy = f64::MAX;
         let mut best_bias = current_bias.clone();
         
         // Initial guess evaluation
@@ -134,16 +276,16 @@ impl BiasController {
         // Note: In a real scenario, we calculate energy of the initial guess here too, 
         // but often the raw_logits alone are high energy.
         let mut best_action = initial_action;
+        let baseline = stp_ctx.snapshot();
 
         // 2. Optimization Loop
         for step in 0..self.config.max_iterations {
             // Generate Candidate
-            let candidate_bias = current_bias.perturb(&mut rng, 0.5 * temperature);
+            let candidate_bias = current_bias.perturb_annealed(&mut rng, 0.5 * temperature, temperature);
             let bias_logits = projector.project(&candidate_bias);
             
             // Combine: Logits_Final = Logits_Raw + Logits_Bias
-            // Note: In real world, dimensions match. Here we assume logic handles mapping.
-            // For mock: we just look at the bias effect or assume raw_logits is adaptable.
+            // `embedding_dim` should match the logits length; any excess is ignored.
             let mut mixed_logits = raw_logits.to_vec();
             for i in 0..mixed_logits.len().min(bias_logits.len()) {
                 mixed_logits[i] += bias_logits[i]; 
@@ -153,18 +295,30 @@ impl BiasController {
             let candidate_action = decode_fn(&mixed_logits);
             
             // Critical: Check energy using the STP Context
-            // This implicitly assumes the action is valid in current state context
+            // Evaluation may mutate the context (e.g. `Define`), so roll back afterwards
+            // to keep candidates from contaminating each other.
             let energy = stp_ctx.calculate_energy(&candidate_action);
+            stp_ctx.restore(&baseline);
+            stats.iterations = step + 1;
+            stats.energy_history.push(energy);
+
+            // Selection Logic (Metropolis)
+            // The walker may step uphill; the global best is tracked separately.
+            if metropolis_accept(&mut rng, current_energy, energy, temperature) {
+                current_energy = energy;
+                current_bias = candidate_bias;
+            }
 
-            // Selection Logic (Greedy + Temperature for simplicity in this demo)
             if energy < best_energy {
                 best_energy = energy;
-                current_bias = candidate_bias.clone();
                 best_bias = current_bias.clone();
                 best_action = candidate_action.clone();
 
                 if best_energy <= 1e-6 {
-                    println!("✨ [VAPO] Convergence at Step {}. Energy=0.0", step);
+                    stats.converged = true;
+                    if verbose {
+                        println!("✨ [VAPO] Convergence at Step {}. Energy=0.0", step);
+                    }
                     break;
                 }
             } 
@@ -172,24 +326,411 @@ impl BiasController {
             // Blind Spot Rotation Logic (Simulated)
             // If stuck, we can rotate the projector. 
             // Crucial: The rotation must ALSO be deterministic based on the RNG state!
-            if step % 15 == 14 && best_energy > 0.1 {
-                println!("🔄 [VAPO] Rotating Basis (Deterministic)...");
+            let period = self.config.rotation_period;
+            if period > 0 && step % period == period - 1 && best_energy > 0.1 {
+                stats.rotations += 1;
+                if verbose {
+                    println!("🔄 [VAPO] Rotating Basis (Deterministic)...");
+                }
                 // Re-init projector with current RNG state (which flows from seed)
                 let new_sub_seed = rng.next_u64(); 
-                projector = ProjectionMatrix::new_from_seed(new_sub_seed);
-                current_bias = BiasVector::new_zero(); // Reset bias
+                projector = ProjectionMatrix::new_from_seed(new_sub_seed, self.config.embedding_dim, self.config.bias_dim);
+                // Restart the walk from the best bias found so far instead of from zero.
+                // Its energy under the new basis is unknown, so the next candidate is always accepted.
+                current_bias = best_bias.clone();
+                current_energy = f64::MAX;
             }
 
             temperature *= self.config.valuation_decay;
         }
 
         // 3. Construct the Proof Bundle
-        ProofBundle {
-            bias_vector: best_bias.components.to_vec(),
+        let bundle = ProofBundle {
+            bias_vector: best_bias.components,
             action: best_action,
             energy_signature: best_energy,
-            context_hash: format!("{:x}", md5::compute(context_str)),
+            context_hash: ProofBundle::hash_context(context_str),
             generator_seed: seed,
+        };
+        (bundle, stats)
+    }
+
+    /// Verifier-side replay of a `ProofBundle`.
+    /// Re-seeds the RNG and the Projection Matrix from `bundle.generator_seed`,
+    /// re-runs the exact same search and checks that it lands on the claimed
+    /// bias / action / energy. Any tampering with the bundle breaks the replay.
+    ///
+    /// `stp_ctx` must be in the same state the prover started from.
+    pub fn verify_bundle<F>(
+        &self,
+        context_str: &str,
+        bundle: &ProofBundle,
+        raw_logits: &[f64],
+        stp_ctx: &mut STPContext,
+        decode_fn: F
+    ) -> bool
+    where
+        F: Fn(&[f64]) -> ProofAction,
+    {
+        // Layer 0: Context Binding
+        if !bundle.verify_binding(context_str) {
+            return false;
+        }
+
+        // Layer 1: Replay the "Chaos"
+        let replay = self.optimize(context_str, bundle.generator_seed, raw_logits, stp_ctx, decode_fn);
+
+        if replay.bias_vector != bundle.bias_vector {
+            println!("❌ [Verify] Bias vector does not match the replayed search.");
+            return false;
+        }
+        if replay.action != bundle.action {
+            println!("❌ [Verify] Action does not match the replayed search.");
+            return false;
+        }
+        if replay.energy_signature != bundle.energy_signature {
+            println!("❌ [Verify] Energy signature does not match the replayed search.");
+            return false;
+        }
+        true
+    }
+}
+
+/// Index of the largest logit (the first one wins on ties; NaN never wins).
+/// Returns 0 for an empty slice.
+fn argmax(logits: &[f64]) -> usize {
+    logits.iter().enumerate()
+        .fold(None, |best: Option<(usize, f64)>, (i, &v)| match best {
+            Some((_, b)) if !(v > b || b.is_nan()) => best,
+            _ => Some((i, v)),
+        })
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Metropolis acceptance rule.
+/// Improvements are always taken; a worse candidate is taken with
+/// probability `exp(-(candidate - current) / temperature)`.
+/// The uniform draw comes from the seeded RNG, so a replay makes the same choices.
+fn metropolis_accept(rng: &mut StdRng, current_energy: f64, candidate_energy: f64, temperature: f64) -> bool {
+    if candidate_energy < current_energy {
+        return true;
+    }
+    if temperature <= 0.0 {
+        return false;
+    }
+    let p = (-(candidate_energy - current_energy) / temperature).exp();
+    rng.gen::<f64>() < p
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn odd_context() -> STPContext {
+        let mut ctx = STPContext::new();
+        for symbol in ["n", "m"] {
+            ctx.calculate_energy(&ProofAction::Define {
+                symbol: symbol.to_string(),
+                hierarchy_path: vec!["Odd".to_string()],
+            });
         }
+        ctx
+    }
+
+    fn decode(logits: &[f64]) -> ProofAction {
+        let max_idx = logits.iter().enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        let leaf = if max_idx == 0 { "Odd" } else { "Even" };
+        ProofAction::Define {
+            symbol: "sum_truth".to_string(),
+            hierarchy_path: vec![leaf.to_string()],
+        }
+    }
+
+    #[test]
+    fn test_verify_bundle_replays_and_rejects_tampering() {
+        let controller = BiasController::new(None);
+        let context = "Prove that the sum of two Odd numbers is Even";
+        let mut raw_logits = vec![0.0; 64];
+        raw_logits[0] = 1.0;
+
+        let bundle = controller.optimize(context, 42, &raw_logits, &mut odd_context(), decode);
+        assert!(controller.verify_bundle(context, &bundle, &raw_logits, &mut odd_context(), decode));
+
+        let mut tampered = bundle.clone();
+        tampered.bias_vector[0] += 0.25;
+        assert!(!controller.verify_bundle(context, &tampered, &raw_logits, &mut odd_context(), decode));
+
+        assert!(!controller.verify_bundle("Another prompt", &bundle, &raw_logits, &mut odd_context(), decode));
+    }
+
+    #[test]
+    fn test_candidates_do_not_leak_state() {
+        let controller = BiasController::new(None);
+        let mut raw_logits = vec![0.0; 64];
+        raw_logits[0] = 1.0;
+
+        let mut ctx = odd_context();
+        controller.optimize("ctx", 7, &raw_logits, &mut ctx, decode);
+        assert!(!ctx.is_defined("sum_truth"));
+        assert!(ctx.is_defined("n") && ctx.is_defined("m"));
+
+        // 第二次运行从同一状态出发，结果与全新上下文一致
+        let again = controller.optimize("ctx", 7, &raw_logits, &mut ctx, decode);
+        let fresh = controller.optimize("ctx", 7, &raw_logits, &mut odd_context(), decode);
+        assert_eq!(again.bias_vector, fresh.bias_vector);
+    }
+
+    #[test]
+    fn test_independent_controllers_optimize_in_parallel() {
+        use std::sync::Barrier;
+        use std::time::Instant;
+
+        // 永远无法收敛的解码器，保证每次运行都跑满迭代
+        fn stubborn(_: &[f64]) -> ProofAction {
+            ProofAction::Assert {
+                subject: "n".to_string(),
+                relation: "is".to_string(),
+                object: "Even".to_string(),
+            }
+        }
+
+        let barrier = Barrier::new(2);
+        let raw_logits = vec![0.0; 64];
+
+        // 两个线程上的独立控制器不共享任何锁，运行区间应当重叠。
+        // 这是 `align` 释放 GIL 的 Rust 侧前提；本测试不涉及 GIL 本身。
+        let spans: Vec<(Instant, Instant)> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..2u64)
+                .map(|i| {
+                    let (barrier, raw_logits) = (&barrier, &raw_logits);
+                    scope.spawn(move || {
+                        let config = VapoConfig { max_iterations: 400, ..VapoConfig::default() };
+                        let controller = BiasController::new(Some(config));
+                        let mut ctx = odd_context();
+                        barrier.wait();
+                        let start = Instant::now();
+                        controller.optimize("concurrent", i, raw_logits, &mut ctx, stubborn);
+                        (start, Instant::now())
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let (a, b) = (spans[0], spans[1]);
+        assert!(a.0 < b.1 && b.0 < a.1, "optimize calls were serialized");
+    }
+
+    #[test]
+    fn test_optimize_logits_matches_closure_decode() {
+        // main.rs 场景: n, m 为 Odd，Generator 倾向于把 sum_truth 定义为 Odd
+        let controller = BiasController::new(None);
+        let context = "Prove that the sum of two Odd numbers is Even";
+        let mut raw_logits = vec![0.0; 1024];
+        raw_logits[0] = 5.0;
+        raw_logits[1] = -2.0;
+
+        let table: Vec<ProofAction> = ["Odd", "Even"].iter()
+            .map(|leaf| ProofAction::Define {
+                symbol: "sum_truth".to_string(),
+                hierarchy_path: vec![leaf.to_string()],
+            })
+            .collect();
+
+        let bundle = controller.optimize_logits(context, 123456789, &raw_logits, &mut odd_context(), &table);
+        assert!(table.contains(&bundle.action));
+        assert!(bundle.verify_binding(context));
+
+        // 等价的手写闭包得到同一个 bundle
+        let decode_table = |logits: &[f64]| -> ProofAction {
+            let idx = if logits[1] > logits[0] { 1 } else { 0 };
+            table[idx].clone()
+        };
+        let reference = controller.optimize(context, 123456789, &raw_logits, &mut odd_context(), decode_table);
+        assert_eq!(bundle.bias_vector, reference.bias_vector);
+        assert_eq!(bundle.action, reference.action);
+        assert_eq!(bundle.energy_signature, reference.energy_signature);
+        assert!(controller.verify_bundle(context, &bundle, &raw_logits, &mut odd_context(), decode_table));
+    }
+
+    #[test]
+    fn test_argmax_prefers_first_on_ties() {
+        assert_eq!(argmax(&[1.0, 3.0, 3.0]), 1);
+        assert_eq!(argmax(&[f64::NAN, 2.0, f64::NAN]), 1);
+        assert_eq!(argmax(&[]), 0);
+    }
+
+    #[test]
+    fn test_optimize_reports_stats() {
+        let controller = BiasController::new(None);
+        let mut raw_logits = vec![0.0; 64];
+        raw_logits[0] = 1.0;
+
+        let (bundle, stats) = controller.optimize_with_stats("stats", 42, &raw_logits, &mut odd_context(), decode);
+        assert!(stats.iterations >= 1 && stats.iterations <= 50);
+        assert_eq!(stats.energy_history.len(), stats.iterations);
+        assert_eq!(stats.converged, bundle.energy_signature <= 1e-6);
+        assert!(stats.rotations <= stats.iterations / 15);
+
+        // Telemetry is part of the deterministic replay as well
+        let (_, replay) = controller.optimize_with_stats("stats", 42, &raw_logits, &mut odd_context(), decode);
+        assert_eq!(replay, stats);
+    }
+
+    /// Decoder that stays stuck for the first `stuck_calls` calls, then proposes the correct action.
+    /// Call 0 is the initial decode, call `k + 1` belongs to step `k`.
+    fn converges_after(stuck_calls: usize) -> impl Fn(&[f64]) -> ProofAction {
+        let calls = std::cell::Cell::new(0usize);
+        move |_: &[f64]| {
+            let n = calls.get();
+            calls.set(n + 1);
+            if n < stuck_calls {
+                // n 为 Odd，断言其为偶数的能量为 2.0
+                ProofAction::Assert {
+                    subject: "n".to_string(),
+                    relation: "IsEven".to_string(),
+                    object: "True".to_string(),
+                }
+            } else {
+                ProofAction::Define {
+                    symbol: "sum_truth".to_string(),
+                    hierarchy_path: vec!["Even".to_string()],
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_converges_after_rotation_and_replays() {
+        let controller = BiasController::new(Some(VapoConfig {
+            rotation_period: 5,
+            ..VapoConfig::default()
+        }));
+        let raw_logits = vec![0.0; 64];
+
+        // Stuck through steps 0..=6, so the rotation at step 4 must happen before convergence
+        let (bundle, stats) = controller.optimize_with_stats("rotate", 17, &raw_logits, &mut odd_context(), converges_after(8));
+        assert!(stats.converged);
+        assert_eq!(stats.iterations, 8);
+        assert_eq!(stats.rotations, 1);
+        assert_eq!(bundle.energy_signature, 0.0);
+
+        let (replay, replay_stats) = controller.optimize_with_stats("rotate", 17, &raw_logits, &mut odd_context(), converges_after(8));
+        assert_eq!(replay.bias_vector, bundle.bias_vector);
+        assert_eq!(replay_stats, stats);
+        assert!(controller.verify_bundle("rotate", &bundle, &raw_logits, &mut odd_context(), converges_after(8)));
+    }
+
+    #[test]
+    fn test_rotation_period_zero_disables_rotation() {
+        let controller = BiasController::new(Some(VapoConfig {
+            rotation_period: 0,
+            ..VapoConfig::default()
+        }));
+        let raw_logits = vec![0.0; 64];
+
+        let (_, stats) = controller.optimize_with_stats("no-rotate", 17, &raw_logits, &mut odd_context(), converges_after(usize::MAX));
+        assert!(!stats.converged);
+        assert_eq!(stats.rotations, 0);
+    }
+
+    #[test]
+    fn test_metropolis_escapes_local_minimum() {
+        // 1-D landscape: local minimum at 0, barrier at 1, global minimum at 2.
+        let landscape = [1.0, 2.0, 0.0];
+
+        let walk = |temperature: f64| -> f64 {
+            let mut rng = StdRng::seed_from_u64(5);
+            let (mut pos, mut best) = (0usize, landscape[0]);
+            for _ in 0..200 {
+                let next = if rng.gen::<bool>() { (pos + 1).min(2) } else { pos.saturating_sub(1) };
+                if metropolis_accept(&mut rng, landscape[pos], landscape[next], temperature) {
+                    pos = next;
+                }
+                best = f64::min(best, landscape[pos]);
+            }
+            best
+        };
+
+        // Effectively greedy: never crosses the barrier
+        assert_eq!(walk(1e-9), 1.0);
+        // Hot enough: climbs the barrier and finds the global minimum
+        assert_eq!(walk(1.0), 0.0);
+    }
+
+    #[test]
+    fn test_annealed_perturbation_touches_many_dims_when_hot() {
+        let base = BiasVector::new_zero(DEFAULT_BIAS_DIM);
+
+        let mut rng = StdRng::seed_from_u64(11);
+        let hot = base.perturb_annealed(&mut rng, 1.0, 2.0);
+        let changed_hot = hot.components.iter().filter(|c| **c != 0.0).count();
+        assert!(changed_hot > 1, "hot step changed only {} components", changed_hot);
+
+        let mut rng = StdRng::seed_from_u64(11);
+        let cold = base.perturb_annealed(&mut rng, 1.0, 0.01);
+        let changed_cold = cold.components.iter().filter(|c| **c != 0.0).count();
+        assert_eq!(changed_cold, 1);
+
+        // Deterministic given the seed
+        let mut rng = StdRng::seed_from_u64(11);
+        assert_eq!(base.perturb_annealed(&mut rng, 1.0, 2.0).components, hot.components);
+    }
+
+    #[test]
+    fn test_projection_covers_configured_action_space() {
+        let config = VapoConfig {
+            bias_dim: 8,
+            embedding_dim: 256,
+            ..VapoConfig::default()
+        };
+
+        let projector = ProjectionMatrix::new_from_seed(9, config.embedding_dim, config.bias_dim);
+        let mut bias = BiasVector::new_zero(config.bias_dim);
+        bias.components[3] = 0.5;
+
+        let logits = projector.project(&bias);
+        assert_eq!(logits.len(), 256);
+        // The tail of the action space is reachable (no silent truncation at 128)
+        assert!(logits[128..].iter().any(|v| v.abs() > 0.0));
+    }
+
+    #[test]
+    fn test_projection_cache_hit_skips_generation() {
+        // A seed no other test uses
+        let (seed, embedding_dim, bias_dim) = (0xCAC4E, 512, 16);
+
+        PROJECTION_MISSES.with(|c| c.set(0));
+        let first = ProjectionMatrix::new_from_seed(seed, embedding_dim, bias_dim);
+        assert_eq!(PROJECTION_MISSES.with(|c| c.get()), 1);
+
+        let second = ProjectionMatrix::new_from_seed(seed, embedding_dim, bias_dim);
+        assert_eq!(PROJECTION_MISSES.with(|c| c.get()), 1);
+
+        assert!(Arc::ptr_eq(&first.weights, &second.weights));
+        assert_eq!(*first.weights, *ProjectionMatrix::generate(seed, embedding_dim, bias_dim).weights);
+    }
+
+    #[test]
+    fn test_optimize_over_256_logits() {
+        let controller = BiasController::new(Some(VapoConfig {
+            bias_dim: 8,
+            embedding_dim: 256,
+            ..VapoConfig::default()
+        }));
+        let raw_logits = vec![0.0; 256];
+
+        let decode_256 = |logits: &[f64]| -> ProofAction {
+            assert_eq!(logits.len(), 256);
+            decode(logits)
+        };
+
+        let bundle = controller.optimize("wide", 3, &raw_logits, &mut odd_context(), decode_256);
+        assert_eq!(bundle.bias_vector.len(), 8);
+        assert!(controller.verify_bundle("wide", &bundle, &raw_logits, &mut odd_context(), decode_256));
     }
 }
@@ -1,7 +1,10 @@
 // COPYRIGHT (C) 2025 M-Patek. ALL RIGHTS RESERVED.
 
 use super::algebra::ClassGroupElement;
+use super::error::AlgebraError;
 use rug::Integer;
+use blake3::Hasher;
+use std::fmt;
 
 /// ⚠️ [Safety Limit]: 局部算子 P 因子最大位宽
 /// 边界定义: 仿射因子溢出 (P-Factor Overflow)
@@ -10,7 +13,11 @@ use rug::Integer;
 /// [Theory]: 
 /// HTP 协议禁止将无限的历史压缩进单个 AffineTuple 的 P 因子中。
 /// 全局演化必须使用流式处理 (Streaming)，而 P 因子累积仅限于局部 Chunk。
-const MAX_CHUNK_P_BITS: u32 = 4096;
+/// 默认上限；需要其他阈值时使用 `compose_with_limit`。
+pub const MAX_CHUNK_P_BITS: u32 = 4096;
+
+/// 🔖 Merkle 叶子哈希的域标签 (生成端与验证端共用)
+pub const LEAF_HASH_TAG: &[u8] = b"HTP_LOG_ENTRY_V2";
 
 #[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct AffineTuple {
@@ -18,7 +25,18 @@ pub struct AffineTuple {
     pub q_shift: ClassGroupElement, 
 }
 
+impl fmt::Display for AffineTuple {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Affine(P=[{} bits], Q={})", self.p_factor.significant_bits(), self.q_shift)
+    }
+}
+
 impl AffineTuple {
+    /// 完整 (不截断) 表示
+    pub fn to_full_string(&self) -> String {
+        format!("Affine(P={}, Q={})", self.p_factor, self.q_shift.to_full_string())
+    }
+
     pub fn identity(discriminant: &Integer) -> Self {
         AffineTuple {
             p_factor: Integer::from(1),
@@ -26,16 +44,59 @@ impl AffineTuple {
         }
     }
 
+    /// 📍 Checkpoint 元组: P = 1, Q = state
+    pub fn checkpoint(state: ClassGroupElement) -> Self {
+        AffineTuple {
+            p_factor: Integer::from(1),
+            q_shift: state,
+        }
+    }
+
+    /// 🔖 规范 Merkle 叶子哈希 (唯一权威编码)
+    /// `LEAF_HASH_TAG` 之后依次写入 P, a, b, c，每个字段为
+    /// `[len: u64 LE][sign: u8][digits LSF]`，保证编码无歧义。
+    /// 神经元 (生成端) 与 `StateTransitionProof::verify` (验证端) 都必须调用此函数。
+    pub fn leaf_hash(&self) -> [u8; 32] {
+        let mut hasher = Hasher::new();
+        hasher.update(LEAF_HASH_TAG);
+        hash_integer_field(&mut hasher, &self.p_factor);
+        hash_integer_field(&mut hasher, &self.q_shift.a);
+        hash_integer_field(&mut hasher, &self.q_shift.b);
+        hash_integer_field(&mut hasher, &self.q_shift.c);
+        hasher.finalize().into()
+    }
+
     /// ⏳ [Time Operator]: Non-Commutative Composition (时间演化 - 非交换)
     /// 公式: (P1, Q1) ⊕ (P2, Q2) = (P1*P2, Q1^P2 * Q2)
-    pub fn compose(&self, other: &Self, discriminant: &Integer) -> Result<Self, String> {
-        // [FALSIFIABILITY CHECK]: P-Factor Overflow (P 因子溢出熔断)
-        // 这是 HTP 协议的物理边界：
-        // 如果算子规模超过安全阈值 (4096 bits)，视为非法操作或 DoS 攻击，立即熔断。
-        let p_bits_new = self.p_factor.significant_bits() + other.p_factor.significant_bits();
-        if p_bits_new > MAX_CHUNK_P_BITS { 
-             return Err(format!("❌ Falsified: Affine P-Factor overflow ({} bits > {}). Global accumulation is forbidden; use State Streaming instead.", p_bits_new, MAX_CHUNK_P_BITS));
+    pub fn compose(&self, other: &Self, discriminant: &Integer) -> Result<Self, AlgebraError> {
+        self.compose_with_limit(other, discriminant, MAX_CHUNK_P_BITS)
+    }
+
+    /// 是否为单位元 (P = 1 且 Q 为主形式)
+    pub fn is_identity(&self, discriminant: &Integer) -> bool {
+        self.p_factor == 1 && self.q_shift.is_identity(discriminant)
+    }
+
+    /// 🏃 [Variable-Time]: 单位元 (1, 1_Q) 的闭式短路
+    /// `identity ⊕ x = (P2, 1^P2 * Q2) = x`，`x ⊕ identity = (P1, Q1^1 * 1) = x`，
+    /// 跳过 Q1^P2 的幂运算；P 因子熔断检查照常执行，其余情形等同 `compose`。
+    /// 与 `ClassGroupElement::compose_vartime` 相同，只用于公开数据的折叠 (`compose_many`)。
+    pub fn compose_vartime(&self, other: &Self, discriminant: &Integer) -> Result<Self, AlgebraError> {
+        for (unit, rest) in [(self, other), (other, self)] {
+            if unit.is_identity(discriminant) {
+                Self::check_p_bits(self, other, MAX_CHUNK_P_BITS)?;
+                return Ok(AffineTuple {
+                    p_factor: rest.p_factor.clone(),
+                    q_shift: unit.q_shift.compose_vartime(&rest.q_shift, discriminant)?,
+                });
+            }
         }
+        self.compose(other, discriminant)
+    }
+
+    /// 与 `compose` 相同，但使用调用方指定的 P 因子位宽上限 `max_bits`
+    pub fn compose_with_limit(&self, other: &Self, discriminant: &Integer, max_bits: u32) -> Result<Self, AlgebraError> {
+        Self::check_p_bits(self, other, max_bits)?;
 
         let new_p = Integer::from(&self.p_factor * &other.p_factor);
 
@@ -50,9 +111,66 @@ impl AffineTuple {
         })
     }
 
+    /// [FALSIFIABILITY CHECK]: P-Factor Overflow (P 因子溢出熔断)
+    /// 这是 HTP 协议的物理边界：
+    /// 如果算子规模超过安全阈值 (默认 4096 bits)，视为非法操作或 DoS 攻击，立即熔断。
+    fn check_p_bits(left: &Self, right: &Self, max_bits: u32) -> Result<(), AlgebraError> {
+        let p_bits_new = left.p_factor.significant_bits() + right.p_factor.significant_bits();
+        if p_bits_new > max_bits {
+             return Err(AlgebraError::PFactorOverflow { bits: p_bits_new as u64, limit: max_bits });
+        }
+        Ok(())
+    }
+
+    /// 🧺 批量时间合成：左折叠 `identity ⊕ t0 ⊕ t1 ⊕ ...` (保持顺序，非交换)
+    /// 与 `ClassGroupElement::compose_many` 约定相同：空切片返回单位元，错误立即返回，
+    /// 单位元经 `compose_vartime` 短路。
+    pub fn compose_many(tuples: &[Self], discriminant: &Integer) -> Result<Self, AlgebraError> {
+        tuples.iter().try_fold(Self::identity(discriminant), |acc, t| acc.compose_vartime(t, discriminant))
+    }
+
+    /// 🔁 [Time Operator]: 自组合幂 tuple^n = tuple ⊕ tuple ⊕ ... ⊕ tuple (n 次)
+    /// 组合满足结合律，且同一元素的各次幂彼此可交换，因此用平方-乘法只需 O(log n) 次组合，
+    /// 每一步都经过 `compose` 的 P 因子熔断检查。n = 0 返回单位元。
+    pub fn pow(&self, n: u64, discriminant: &Integer) -> Result<Self, AlgebraError> {
+        // 快速熔断: P^n 至少有 n * (bits(P) - 1) + 1 位
+        let p_bits = self.p_factor.significant_bits() as u64;
+        let min_bits = n.saturating_mul(p_bits.saturating_sub(1)).saturating_add(1);
+        if n > 0 && p_bits > 1 && min_bits > MAX_CHUNK_P_BITS as u64 {
+            return Err(AlgebraError::PFactorOverflow { bits: min_bits, limit: MAX_CHUNK_P_BITS });
+        }
+
+        let mut acc = Self::identity(discriminant);
+        let mut base = self.clone();
+        let mut remaining = n;
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                acc = acc.compose(&base, discriminant)?;
+            }
+            remaining >>= 1;
+            // 最后一轮不再平方，避免无用的 (可能触发熔断的) 组合
+            if remaining > 0 {
+                base = base.compose(&base, discriminant)?;
+            }
+        }
+        Ok(acc)
+    }
+
     /// 🌌 [Space Operator]: Commutative Aggregation (空间聚合 - 交换)
     /// 公式: (P1, Q1) ⊗ (P2, Q2) = (P1*P2, Q1*Q2)
-    pub fn commutative_merge(&self, other: &Self, discriminant: &Integer) -> Result<Self, String> {
+    pub fn commutative_merge(&self, other: &Self, discriminant: &Integer) -> Result<Self, AlgebraError> {
+        self.commutative_merge_with_limit(other, discriminant, MAX_CHUNK_P_BITS)
+    }
+
+    /// 与 `commutative_merge` 相同，但使用调用方指定的 P 因子位宽上限 `max_bits`
+    pub fn commutative_merge_with_limit(&self, other: &Self, discriminant: &Integer, max_bits: u32) -> Result<Self, AlgebraError> {
+        // [FALSIFIABILITY CHECK]: 与 compose 对称的 P 因子溢出熔断，
+        // 防止空间折叠悄悄累积出巨大的 P 因子。
+        let p_bits_new = self.p_factor.significant_bits() + other.p_factor.significant_bits();
+        if p_bits_new > max_bits {
+             return Err(AlgebraError::SpatialOverflow { bits: p_bits_new as u64, limit: max_bits });
+        }
+
         // P_new = P1 * P2 (整数乘法，交换)
         let new_p = Integer::from(&self.p_factor * &other.p_factor);
 
@@ -66,3 +184,159 @@ impl AffineTuple {
         })
     }
 }
+
+/// 写入一个长度前缀、带符号的整数字段: [len: u64 LE][sign: u8][digits LSF]
+pub(crate) fn hash_integer_field(hasher: &mut Hasher, value: &Integer) {
+    let digits = value.to_digits::<u8>(rug::integer::Order::Lsf);
+    hasher.update(&(digits.len() as u64).to_le_bytes());
+    hasher.update(&[(value.cmp0() == std::cmp::Ordering::Less) as u8]);
+    hasher.update(&digits);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leaf_hash_is_stable() {
+        let d = Integer::from(-23);
+        let t = AffineTuple::checkpoint(ClassGroupElement::identity(&d));
+
+        assert_eq!(t.leaf_hash(), t.clone().leaf_hash());
+        // 手工复现编码: 标签 + 四个长度前缀字段
+        let mut hasher = Hasher::new();
+        hasher.update(b"HTP_LOG_ENTRY_V2");
+        for (digits, sign) in [(vec![1u8], 0u8), (vec![1], 0), (vec![1], 0), (vec![6], 0)] {
+            hasher.update(&(digits.len() as u64).to_le_bytes());
+            hasher.update(&[sign]);
+            hasher.update(&digits);
+        }
+        let expected: [u8; 32] = hasher.finalize().into();
+        assert_eq!(t.leaf_hash(), expected);
+
+        // P 因子参与编码
+        let mut shifted = t.clone();
+        shifted.p_factor = Integer::from(3);
+        assert_ne!(shifted.leaf_hash(), t.leaf_hash());
+    }
+
+    #[test]
+    fn test_identity_compose_closed_forms() {
+        // Delta = -(2^61 - 1)
+        let d = -((Integer::from(1) << 61) - 1u32);
+        let g = ClassGroupElement::generator(&d);
+        let x = AffineTuple { p_factor: Integer::from(7), q_shift: g.pow(&Integer::from(5), &d).unwrap() };
+        let id = AffineTuple::identity(&d);
+
+        assert!(id.is_identity(&d));
+        assert!(!x.is_identity(&d));
+        assert_eq!(id.compose_vartime(&x, &d).unwrap(), x);
+        assert_eq!(x.compose_vartime(&id, &d).unwrap(), x);
+        assert_eq!(id.compose_vartime(&id, &d).unwrap(), id);
+
+        // 与通用公式 (P1*P2, Q1^P2 * Q2) 的逐项计算一致
+        let general_left = AffineTuple {
+            p_factor: Integer::from(&id.p_factor * &x.p_factor),
+            q_shift: id.q_shift.pow(&x.p_factor, &d).unwrap().compose(&x.q_shift, &d).unwrap(),
+        };
+        let general_right = AffineTuple {
+            p_factor: Integer::from(&x.p_factor * &id.p_factor),
+            q_shift: x.q_shift.pow(&id.p_factor, &d).unwrap().compose(&id.q_shift, &d).unwrap(),
+        };
+        assert_eq!(general_left, x);
+        assert_eq!(general_right, x);
+        assert_eq!(id.compose(&x, &d).unwrap(), x);
+        assert_eq!(x.compose(&id, &d).unwrap(), x);
+
+        // P = 1 但 Q 非单位元 (Checkpoint) 不是单位元，必须走通用路径
+        let chk = AffineTuple::checkpoint(g.clone());
+        assert!(!chk.is_identity(&d));
+        assert_eq!(chk.compose_vartime(&x, &d).unwrap(), chk.compose(&x, &d).unwrap());
+        assert_eq!(chk.compose_vartime(&x, &d).unwrap().p_factor, 7);
+
+        // 熔断检查不被短路绕过
+        let huge = AffineTuple { p_factor: Integer::from(1) << 4096, q_shift: g };
+        assert!(id.compose_vartime(&huge, &d).is_err());
+    }
+
+    #[test]
+    fn test_pow_matches_repeated_compose() {
+        // Delta = -(2^61 - 1)
+        let d = -((Integer::from(1) << 61) - 1u32);
+        let t = AffineTuple {
+            p_factor: Integer::from(3),
+            q_shift: ClassGroupElement::generator(&d),
+        };
+
+        let mut manual = t.clone();
+        for _ in 1..5 {
+            manual = manual.compose(&t, &d).unwrap();
+        }
+        assert_eq!(t.pow(5, &d).unwrap(), manual);
+        assert_eq!(t.pow(5, &d).unwrap().p_factor, 243);
+        assert_eq!(t.pow(1, &d).unwrap(), t);
+        assert_eq!(t.pow(0, &d).unwrap(), AffineTuple::identity(&d));
+
+        let mut manual = AffineTuple::identity(&d);
+        for _ in 0..13 {
+            manual = manual.compose(&t, &d).unwrap();
+        }
+        assert_eq!(t.pow(13, &d).unwrap(), manual);
+
+        // P 因子溢出熔断
+        let big = AffineTuple { p_factor: Integer::from(1) << 1000, q_shift: t.q_shift.clone() };
+        assert!(big.pow(5, &d).is_err());
+    }
+
+    #[test]
+    fn test_pow_with_unit_p_factor_handles_huge_exponent() {
+        let d = -((Integer::from(1) << 61) - 1u32);
+        let g = ClassGroupElement::generator(&d);
+        // P = 1 时没有熔断预检，指数只受组合次数限制
+        let t = AffineTuple::checkpoint(g.clone());
+
+        let result = t.pow(u64::MAX, &d).unwrap();
+        assert_eq!(result.p_factor, 1);
+        assert_eq!(result.q_shift, g.pow(&Integer::from(u64::MAX), &d).unwrap());
+    }
+
+    #[test]
+    fn test_commutative_merge_overflow_fails_cleanly() {
+        let d = -((Integer::from(1) << 61) - 1u32);
+        let op = AffineTuple {
+            p_factor: Integer::from(1009), // 10 bits
+            q_shift: ClassGroupElement::identity(&d),
+        };
+
+        let mut acc = AffineTuple::identity(&d);
+        let mut failure = None;
+        for i in 0..1000 {
+            match acc.commutative_merge(&op, &d) {
+                Ok(next) => acc = next,
+                Err(e) => { failure = Some((i, e)); break; }
+            }
+        }
+
+        let (steps, err) = failure.expect("merge should hit the P-factor ceiling");
+        assert!(matches!(err, AlgebraError::SpatialOverflow { limit: MAX_CHUNK_P_BITS, .. }));
+        assert!(err.to_string().contains("Falsified"));
+        assert!(steps < 500);
+        assert!(acc.p_factor.significant_bits() <= MAX_CHUNK_P_BITS);
+    }
+
+    #[test]
+    fn test_compose_with_limit() {
+        let d = -((Integer::from(1) << 61) - 1u32);
+        let t = AffineTuple {
+            p_factor: Integer::from(1009), // 10 bits
+            q_shift: ClassGroupElement::identity(&d),
+        };
+
+        assert!(t.compose_with_limit(&t, &d, 20).is_ok());
+        let err = t.compose_with_limit(&t, &d, 19).unwrap_err();
+        assert_eq!(err, AlgebraError::PFactorOverflow { bits: 20, limit: 19 });
+        assert!(err.to_string().contains("20 bits > 19"));
+        // 默认路径不变
+        assert_eq!(t.compose(&t, &d), t.compose_with_limit(&t, &d, MAX_CHUNK_P_BITS));
+    }
+}
@@ -3,17 +3,165 @@
 use rug::{Integer, ops::Pow};
 use serde::{Serialize, Deserialize};
 use blake3::Hasher;
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt;
+use super::error::AlgebraError;
+
+/// 约化步数上限 = REDUCTION_STEPS_BASE + REDUCTION_STEPS_PER_BIT * bits(D)
+/// 合法约化的步数与判别式位长同阶，按位长缩放可同时避免大 D 误报和小 D 过度放行。
+pub const REDUCTION_STEPS_BASE: usize = 64;
+pub const REDUCTION_STEPS_PER_BIT: usize = 8;
+
+/// 判别式对应的默认约化步数上限
+pub fn default_reduction_step_limit(discriminant: &Integer) -> usize {
+    REDUCTION_STEPS_BASE + REDUCTION_STEPS_PER_BIT * discriminant.significant_bits() as usize
+}
+
+/// Display 时每个整数保留的首/尾十进制位数
+pub const DISPLAY_EDGE_DIGITS: usize = 4;
+
+/// 📏 截断显示大整数: `1234..6789 [512 bits]`
+/// 十进制位数不超过 `2 * DISPLAY_EDGE_DIGITS` 时原样输出。
+pub fn abbreviate_integer(n: &Integer) -> String {
+    let digits = n.to_string_radix(10);
+    let (sign, magnitude) = match digits.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", digits.as_str()),
+    };
+
+    if magnitude.len() <= 2 * DISPLAY_EDGE_DIGITS {
+        return digits.clone();
+    }
+
+    format!(
+        "{}{}..{} [{} bits]",
+        sign,
+        &magnitude[..DISPLAY_EDGE_DIGITS],
+        &magnitude[magnitude.len() - DISPLAY_EDGE_DIGITS..],
+        n.significant_bits()
+    )
+}
 
 /// 🏛️ ClassGroupElement: 虚二次域类群元素
 /// 表示形式为二元二次型 (a, b, c)，满足 b^2 - 4ac = Delta
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// `Ord` 为约化后 `(a, b, c)` 的字典序 (按字段顺序派生)，用于确定性的并列裁决。
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct ClassGroupElement {
     pub a: Integer,
     pub b: Integer,
     pub c: Integer,
 }
 
+#[cfg(test)]
+thread_local! {
+    /// 测试用: 统计约化循环体的执行次数
+    static REDUCTION_STEPS: std::cell::Cell<usize> = std::cell::Cell::new(0);
+    /// 测试用: 统计 `square` 的调用次数
+    static SQUARE_CALLS: std::cell::Cell<usize> = std::cell::Cell::new(0);
+    /// 测试用: 统计 `compose` 的调用次数
+    static COMPOSE_CALLS: std::cell::Cell<usize> = std::cell::Cell::new(0);
+}
+
+/// 幂缓存容量上限，满后整体清空
+pub const POW_CACHE_CAPACITY: usize = 1024;
+
+/// 🗃️ PowCache: `(base, exp) -> base^exp` 的小型备忘录
+/// 面向重复出现的小指数 (例如时空噪声 g^(t+1))，命中时完全跳过平方阶梯。
+/// 元素的判别式由 (a, b, c) 唯一确定，因此键中无需再带判别式。
+#[derive(Debug, Default, Clone)]
+pub struct PowCache {
+    entries: HashMap<(ClassGroupElement, Integer), ClassGroupElement>,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl PowCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 带缓存的 `base.pow(exp)`
+    pub fn pow(&mut self, base: &ClassGroupElement, exp: &Integer, discriminant: &Integer) -> Result<ClassGroupElement, AlgebraError> {
+        let key = (base.clone(), exp.clone());
+        if let Some(hit) = self.entries.get(&key) {
+            self.hits += 1;
+            return Ok(hit.clone());
+        }
+
+        self.misses += 1;
+        let result = base.pow(exp, discriminant)?;
+        if self.entries.len() >= POW_CACHE_CAPACITY {
+            self.entries.clear();
+        }
+        self.entries.insert(key, result.clone());
+        Ok(result)
+    }
+}
+
+/// 🛡️ 判别式合法性检查
+/// 类群运算 (例如 `identity` 中的 `(1 - D) / 4`) 假设 D < 0 且 D = 1 (mod 4)。
+pub fn validate_discriminant(d: &Integer) -> Result<(), AlgebraError> {
+    if *d >= 0 {
+        return Err(AlgebraError::InvalidDiscriminant {
+            discriminant: d.clone(),
+            reason: "must be negative (imaginary quadratic field)",
+        });
+    }
+    if d.clone().rem_euc(Integer::from(4)) != 1 {
+        return Err(AlgebraError::InvalidDiscriminant {
+            discriminant: d.clone(),
+            reason: "must satisfy D = 1 (mod 4)",
+        });
+    }
+    Ok(())
+}
+
+impl fmt::Display for ClassGroupElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "(a={}, b={}, c={})",
+            abbreviate_integer(&self.a),
+            abbreviate_integer(&self.b),
+            abbreviate_integer(&self.c)
+        )
+    }
+}
+
 impl ClassGroupElement {
+    /// 📐 约化判定: |b| <= a <= c，且当 |b| = a 或 a = c 时 b >= 0
+    pub fn is_reduced(&self) -> bool {
+        Self::is_reduced_triple(&self.a, &self.b, &self.c)
+    }
+
+    fn is_reduced_triple(a: &Integer, b: &Integer, c: &Integer) -> bool {
+        if *a <= 0 {
+            return false;
+        }
+        let abs_b = Integer::from(b.abs_ref());
+        if abs_b > *a || a > c {
+            return false;
+        }
+        if (abs_b == *a || a == c) && *b < 0 {
+            return false;
+        }
+        true
+    }
+
+    /// 完整 (不截断) 的 `(a, b, c)` 表示
+    pub fn to_full_string(&self) -> String {
+        format!("(a={}, b={}, c={})", self.a, self.b, self.c)
+    }
+
     /// 构造单位元 (Identity Element)
     /// 对于判别式 D，单位元通常是 (1, 1, (1-D)/4)
     pub fn identity(discriminant: &Integer) -> Self {
@@ -27,10 +175,25 @@ impl ClassGroupElement {
     /// 🛡️ [Security]: Safe Generator Selection (SGS)
     /// 生成一个密码学安全的、非小阶的生成元。
     pub fn generator(discriminant: &Integer) -> Self {
-        let four = Integer::from(4);
         let mut hasher = Hasher::new();
         hasher.update(b"HTP_GENERATOR_SEED_V1");
         hasher.update(&discriminant.to_digits(rug::integer::Order::Lsf));
+        Self::derive_generator(discriminant, hasher)
+    }
+
+    /// 🎲 [Family]: 带索引的生成元族
+    /// 将 `index` 混入种子，得到一族相互独立、可复现的生成元
+    /// (例如 Pedersen 承诺需要多个独立生成元)。同样经过小阶过滤。
+    pub fn generator_indexed(discriminant: &Integer, index: u64) -> Self {
+        let mut hasher = Hasher::new();
+        hasher.update(b"HTP_GENERATOR_SEED_V1::INDEXED::");
+        hasher.update(&index.to_le_bytes());
+        hasher.update(&discriminant.to_digits(rug::integer::Order::Lsf));
+        Self::derive_generator(discriminant, hasher)
+    }
+
+    /// 由已灌入种子的 hasher 派生素理想 (p, b, c)，并过滤小阶元素
+    fn derive_generator(discriminant: &Integer, hasher: Hasher) -> Self {
         let hash_output = hasher.finalize();
         
         let mut p = Integer::from_digits(hash_output.as_bytes(), rug::integer::Order::Lsf);
@@ -47,22 +210,13 @@ impl ClassGroupElement {
             // 勒让德符号检测 (Delta/p) = 1
             let symbol = discriminant.jacobi(&p);
             if symbol == 1 {
-                let modulus = &p * &four;
-                let mut b = Integer::from(1);
-                
-                let b_limit = if &p < &Integer::from(10_000) { &modulus } else { &Integer::from(20_000) };
-                let mut found_b = false;
-                
-                while &b < b_limit {
-                    let sq_b = b.clone() * &b;
-                    if (sq_b - discriminant).is_divisible(&modulus) {
-                        found_b = true;
-                        break;
+                // 求解 b^2 = Delta (mod 4p)：先求模 p 的平方根，再调整为奇数
+                // (Delta = 1 mod 4 且 b 为奇数时 b^2 = Delta mod 4 自动成立)
+                if let Some(mut b) = Self::sqrt_mod_prime(discriminant, &p) {
+                    if b.is_even() {
+                        b = Integer::from(&p - &b);
                     }
-                    b += 2; // b 必须是奇数
-                }
 
-                if found_b {
                     match Self::reduce_form(p.clone(), b, discriminant) {
                         Ok(candidate) => {
                             // Critical: Real Small Order Filter (过滤小阶元素)
@@ -80,12 +234,66 @@ impl ClassGroupElement {
         }
     }
 
+    /// Tonelli-Shanks: 求 x 使得 x^2 = n (mod p)，p 为奇素数
+    fn sqrt_mod_prime(n: &Integer, p: &Integer) -> Option<Integer> {
+        let n = Integer::from(n.rem_euc_ref(p));
+        if n == 0 {
+            return Some(n);
+        }
+        if n.jacobi(p) != 1 {
+            return None;
+        }
+
+        // p - 1 = q * 2^s
+        let p_minus_1 = Integer::from(p - 1u32);
+        let s = p_minus_1.find_one(0)?;
+        let q = Integer::from(&p_minus_1 >> s);
+
+        // 寻找一个二次非剩余 z
+        let mut z = Integer::from(2);
+        while z.jacobi(p) != -1 {
+            z += 1;
+        }
+
+        let mut m = s;
+        let mut c = z.pow_mod(&q, p).ok()?;
+        let mut t = n.clone().pow_mod(&q, p).ok()?;
+        let mut r = n.pow_mod(&(Integer::from(&q + 1u32) >> 1), p).ok()?;
+
+        while t != 1 {
+            // 最小的 i 使得 t^(2^i) = 1
+            let mut i = 0;
+            let mut t_pow = t.clone();
+            while t_pow != 1 {
+                t_pow = Integer::from(&t_pow * &t_pow) % p;
+                i += 1;
+                if i == m {
+                    return None;
+                }
+            }
+
+            let b = c.pow_mod(&(Integer::from(1) << (m - i - 1)), p).ok()?;
+            m = i;
+            c = Integer::from(&b * &b) % p;
+            t = Integer::from(&t * &c) % p;
+            r = Integer::from(&r * &b) % p;
+        }
+        Some(r)
+    }
+
+    /// 🪞 歧义形式检测 (Ambiguous Form)
+    /// 对约化形式，b = 0、a = b 或 a = c 恰好是自逆元 (f = f^-1)，即阶为 1 或 2。
+    /// 协议可以据此显式避开 2-挠 (2-torsion) 生成元。
+    pub fn is_ambiguous(&self) -> bool {
+        self.b == 0 || self.a == self.b || self.a == self.c
+    }
+
     /// 🛡️ [SECURITY UPGRADE]: 真正的小阶元素检测
     fn has_small_order(&self, discriminant: &Integer, limit_val: u32) -> bool {
         let identity = Self::identity(discriminant);
         
         if self == &identity { return true; }
-        if self.a == self.b || self.a == self.c || self.b == 0 { return true; }
+        if self.is_ambiguous() { return true; }
         
         let mut annihilator = Integer::from(1);
         let mut p = Integer::from(2);
@@ -109,60 +317,98 @@ impl ClassGroupElement {
 
     /// 🌀 State Streaming Evolution (流式演化)
     /// S_new = S_old^p * q
-    pub fn apply_affine(&self, p: &Integer, q: &Self, discriminant: &Integer) -> Result<Self, String> {
+    pub fn apply_affine(&self, p: &Integer, q: &Self, discriminant: &Integer) -> Result<Self, AlgebraError> {
         let s_powered = self.pow(p, discriminant)?;
         let s_new = s_powered.compose(q, discriminant)?;
         Ok(s_new)
     }
 
+    /// 🔗 合成兼容性预检：判断 `compose` 是否会因输入本身而失败 (不执行完整合成)
+    ///
+    /// 早期原型要求 gcd(a1, a2) | s (s = (b1 + b2) / 2)，a 不互素的形式对会直接报错；
+    /// 当前的 Cohen 5.4.7 实现改用 d1 = gcd(a1, a2, s) 处理了一般情形。
+    /// 剩余的失败来源只有：退化形式 (a = 0)、两个形式不属于同一判别式
+    /// (b 奇偶不同，s 不是整数；或 b^2 - 4ac 不等)。
+    /// a1 != 0 时 d1 = gcd(a1, a2, s) 必不为 0，无需再单独检查。
+    pub fn can_compose(&self, other: &Self) -> bool {
+        if self.a == 0 || other.a == 0 {
+            return false;
+        }
+        if self.b.is_odd() != other.b.is_odd() {
+            return false;
+        }
+        let d_self = Integer::from(&self.b * &self.b) - Integer::from(4) * &self.a * &self.c;
+        let d_other = Integer::from(&other.b * &other.b) - Integer::from(4) * &other.a * &other.c;
+        d_self == d_other
+    }
+
+    /// 🗃️ 与 `apply_affine` 相同，但 S^p 经由 `PowCache` 查表
+    pub fn apply_affine_cached(&self, p: &Integer, q: &Self, discriminant: &Integer, cache: &mut PowCache) -> Result<Self, AlgebraError> {
+        let s_powered = cache.pow(self, p, discriminant)?;
+        s_powered.compose(q, discriminant)
+    }
+
     /// ✨ [FIXED] Composition Algorithm (Cohen Algo 5.4.7)
-    pub fn compose(&self, other: &Self, discriminant: &Integer) -> Result<Self, String> {
-        let s = (&self.b + &other.b) >> 1; 
-        
-        // Solve: u*a1 + v*a2 = d
-        let (d, _u, v) = Self::extended_gcd(&self.a, &other.a);
-        
-        let a1 = &self.a;
-        let a2 = &other.a;
-        
+    /// 完整处理 gcd(a1, a2) > 1 的情形：真正的约束是 d1 = gcd(a1, a2, s)，
+    /// 而不是要求 gcd(a1, a2) | s。
+    pub fn compose(&self, other: &Self, discriminant: &Integer) -> Result<Self, AlgebraError> {
+        #[cfg(test)]
+        COMPOSE_CALLS.with(|c| c.set(c.get() + 1));
+
+        // 约定 a1 <= a2
+        let (f1, f2) = if self.a > other.a { (other, self) } else { (self, other) };
+
+        let s = Integer::from(&f1.b + &f2.b) >> 1;
+        let n = Integer::from(&f2.b - &s);
+
+        // Step 2: y1*a2 + x1*a1 = d = gcd(a1, a2)
+        let (d, y1) = if f2.a.is_divisible(&f1.a) {
+            (f1.a.clone(), Integer::from(0))
+        } else {
+            let (d, u, _v) = Self::extended_gcd(&f2.a, &f1.a);
+            (d, u)
+        };
+
+        // Step 3: x2*s + y2*d = d1 = gcd(s, d)
+        let (d1, x2, y2) = if s.is_divisible(&d) {
+            (d, Integer::from(0), Integer::from(-1))
+        } else {
+            let (mut d1, mut x2, mut y2) = Self::extended_gcd(&s, &d);
+            if d1 < 0 { d1 = -d1; x2 = -x2; y2 = -y2; }
+            (d1, x2, -y2)
+        };
+
         // [FALSIFIABILITY POINT 1]: Composition Compatibility
-        // 检查 d | s 是否成立。如果不成立，说明这两个形式无法合成。
-        let (_q_dummy, r) = s.div_rem_ref(&d).into();
-        if r != Integer::from(0) {
-            return Err(format!("Composition Error: gcd(a1, a2)={} does not divide s (s={}). Forms are incompatible.", d, s));
+        if d1 == 0 {
+            return Err(AlgebraError::IncompatibleForms);
         }
-        
-        // Compute new A coefficient
-        // A = a1 * a2 / d^2
-        let a1_div_d = Integer::from(a1 / &d);
-        let a2_div_d = Integer::from(a2 / &d);
-        let new_a = Integer::from(&a1_div_d * &a2_div_d);
-
-        // Compute new B coefficient
-        let s_minus_b2 = &s - &other.b;
-        let val = &v * (&s_minus_b2 / &d); 
-        let mod_a1_d = &a1_div_d;
-        
-        let mut k = val;
-        k.rem_assign(mod_a1_d);
-        if k < 0 { k += mod_a1_d; }
 
-        let term = Integer::from(2) * &a2_div_d * &k;
-        let new_b = &other.b + &term;
+        // Step 4: A = a1 * a2 / d1^2, B = b2 + 2 * v2 * r
+        let v1 = Integer::from(&f1.a / &d1);
+        let v2 = Integer::from(&f2.a / &d1);
+        let r = (y1 * y2 * &n - x2 * &f2.c).rem_euc(&v1);
+
+        let new_b = Integer::from(&f2.b + Integer::from(2) * &v2 * &r);
+        let new_a = v1 * v2;
 
         // [SECURITY CHECK]: 通过 reduce_form 进行最终的边界验证
         Self::reduce_form(new_a, new_b, discriminant)
     }
 
     /// ✨ [FIXED] Square Algorithm (NUDUPL / Doubling)
-    pub fn square(&self, discriminant: &Integer) -> Result<Self, String> {
-        let (g, _x, y) = Self::extended_gcd(&self.a, &self.b);
+    /// 求 k 使得 c + (b/g)*k = 0 (mod a/g)，其中 y*b + x*a = g，故 k = -y*c。
+    pub fn square(&self, discriminant: &Integer) -> Result<Self, AlgebraError> {
+        #[cfg(test)]
+        SQUARE_CALLS.with(|c| c.set(c.get() + 1));
+
+        let (mut g, _x, mut y) = Self::extended_gcd(&self.a, &self.b);
+        if g < 0 { g = -g; y = -y; }
 
         let a_div_g = Integer::from(&self.a / &g);
         let new_a = Integer::from(&a_div_g * &a_div_g);
 
         let target_mod = &a_div_g;
-        let mut yc = Integer::from(&y * &self.c);
+        let mut yc = -Integer::from(&y * &self.c);
         yc.rem_assign(target_mod);
         if yc < 0 { yc += target_mod; }
 
@@ -172,8 +418,117 @@ impl ClassGroupElement {
         Self::reduce_form(new_a, new_b, discriminant)
     }
 
+    /// 🏃 [Variable-Time]: 带单位元短路的合成
+    /// 一侧为单位元且另一侧已是约化形式时直接返回另一侧 (仍经过 `post_mortem` 的不变量检查)，
+    /// 其余情形等同 `compose`。分支取决于操作数的取值，只用于公开数据的折叠 (`compose_many`)；
+    /// `pow` 与盲化路径必须使用常数序列的 `compose`。
+    pub fn compose_vartime(&self, other: &Self, discriminant: &Integer) -> Result<Self, AlgebraError> {
+        for (unit, rest) in [(self, other), (other, self)] {
+            if rest.is_reduced() && unit.is_identity(discriminant) {
+                return Self::post_mortem(rest.a.clone(), rest.b.clone(), rest.c.clone(), discriminant);
+            }
+        }
+        self.compose(other, discriminant)
+    }
+
+    /// 是否为主形式 (1, 1, (1 - D) / 4)，无需构造完整的单位元
+    pub fn is_identity(&self, discriminant: &Integer) -> bool {
+        self.a == 1 && self.b == 1 && Integer::from(&self.c << 2) == Integer::from(1 - discriminant)
+    }
+
+    /// 🧾 从十进制字符串构造 (FFI / 外部测试向量)
+    /// 依次校验：判别式合法、系数可解析、a > 0、b^2 - 4ac == D、gcd(a, b, c) = 1，最后约化。
+    pub fn from_str_coeffs(a: &str, b: &str, c: &str, discriminant: &Integer) -> Result<Self, AlgebraError> {
+        validate_discriminant(discriminant)?;
+        let parse = |name: &'static str, input: &str| {
+            Integer::from_str_radix(input.trim(), 10)
+                .map_err(|_| AlgebraError::InvalidCoefficient { name, input: input.to_string() })
+        };
+        let (a, b, c) = (parse("a", a)?, parse("b", b)?, parse("c", c)?);
+
+        if a <= 0 {
+            return Err(AlgebraError::NotPositiveDefinite);
+        }
+        let got = Integer::from(b.square_ref()) - Integer::from(4) * &a * &c;
+        if &got != discriminant {
+            return Err(AlgebraError::DiscriminantMismatch { got, expected: discriminant.clone() });
+        }
+        let g = Integer::from(a.gcd_ref(&b)).gcd(&c);
+        if g != 1 {
+            return Err(AlgebraError::NonPrimitive { gcd: g });
+        }
+
+        Self::reduce_form(a, b, discriminant)
+    }
+
+    /// 🧺 批量合成：左折叠 `identity ⊕ f0 ⊕ f1 ⊕ ...`，空切片返回单位元
+    /// 任何一步失败立即返回该错误。稀疏输入中的单位元经 `compose_vartime` 短路。
+    pub fn compose_many(forms: &[Self], discriminant: &Integer) -> Result<Self, AlgebraError> {
+        forms.iter().try_fold(Self::identity(discriminant), |acc, form| acc.compose_vartime(form, discriminant))
+    }
+
+    /// 🔁 逆元：(a, b, c)^-1 = (a, -b, c)，再约化
+    /// 等价于 `self.reflect().reduce(discriminant)`。
+    pub fn inverse(&self, discriminant: &Integer) -> Result<Self, AlgebraError> {
+        self.reflect().reduce(discriminant)
+    }
+
+    /// 🪞 反射 (共轭)：(a, b, c) -> (a, -b, c)，不做约化
+    /// 结果与 `self` 同属一个判别式，但当 b = a 时不再满足约化条件；
+    /// 需要规范代表元时使用 `reflect().reduce()`，它恒等于 `inverse()`。
+    pub fn reflect(&self) -> Self {
+        ClassGroupElement {
+            a: self.a.clone(),
+            b: Integer::from(-&self.b),
+            c: self.c.clone(),
+        }
+    }
+
+    /// 🧹 将 (a, b) 约化为规范代表元 (c 由判别式重新推出)
+    pub fn reduce(&self, discriminant: &Integer) -> Result<Self, AlgebraError> {
+        Self::reduce_form(self.a.clone(), self.b.clone(), discriminant)
+    }
+
+    /// 🎲 [Sampling]: 随机约化形式
+    /// 与 `generator` 相同的搜索流程，但由 RNG 驱动：采样素数 p (约 |Delta|^(1/2) 量级)，
+    /// 求 b^2 = Delta (mod 4p) 并约化。不做小阶过滤，供属性测试与蒙特卡洛实验使用。
+    pub fn random(discriminant: &Integer, rng: &mut impl Rng) -> Result<Self, AlgebraError> {
+        let bits = (discriminant.significant_bits() / 2).max(8);
+        let num_bytes = ((bits + 7) / 8) as usize;
+
+        for _ in 0..10_000 {
+            let mut buf = vec![0u8; num_bytes];
+            rng.fill_bytes(&mut buf);
+
+            let mut p = Integer::from_digits(&buf, rug::integer::Order::Lsf);
+            p.keep_bits_mut(bits);
+            p.next_prime_mut();
+            if p == 2 || discriminant.jacobi(&p) != 1 {
+                continue;
+            }
+
+            let mut b = match Self::sqrt_mod_prime(discriminant, &p) {
+                Some(b) => b,
+                None => continue,
+            };
+            if b.is_even() {
+                b = Integer::from(&p - &b);
+            }
+            // 随机选取 b 或 -b (即该形式或其逆)
+            if rng.next_u32() & 1 == 1 {
+                b = -b;
+            }
+
+            if let Ok(form) = Self::reduce_form(p, b, discriminant) {
+                return Ok(form);
+            }
+        }
+        Err(AlgebraError::NoSplitPrime)
+    }
+
     /// 🛡️ [Security]: Constant-Sequence Exponentiation (常数序列求幂)
-    pub fn pow(&self, exp: &Integer, discriminant: &Integer) -> Result<Self, String> {
+    /// 每一位固定执行一次 `compose` 与一次 `square` (NUDUPL)，操作序列不依赖操作数的取值。
+    pub fn pow(&self, exp: &Integer, discriminant: &Integer) -> Result<Self, AlgebraError> {
         if exp == &Integer::from(0) {
             return Ok(Self::identity(discriminant));
         }
@@ -195,6 +550,40 @@ impl ClassGroupElement {
         Ok(r0)
     }
 
+    /// 🏃 [Variable-Time]: 与 `pow` 相同的阶梯，但乘法的两个操作数相等时改走更便宜的 `square` (NUDUPL)
+    /// 分支取决于操作数的取值，与 `compose_vartime` 一样只用于公开数据；秘密指数必须使用 `pow`。
+    pub fn pow_vartime(&self, exp: &Integer, discriminant: &Integer) -> Result<Self, AlgebraError> {
+        if exp == &Integer::from(0) {
+            return Ok(Self::identity(discriminant));
+        }
+
+        let mut r0 = Self::identity(discriminant);
+        let mut r1 = self.clone();
+        let bits_count = exp.significant_bits();
+
+        for i in (0..bits_count).rev() {
+            let bit = exp.get_bit(i);
+            if !bit {
+                r1 = r0.multiply_vartime(&r1, discriminant)?;
+                r0 = r0.square(discriminant)?;
+            } else {
+                r0 = r0.multiply_vartime(&r1, discriminant)?;
+                r1 = r1.square(discriminant)?;
+            }
+        }
+        Ok(r0)
+    }
+
+    /// ⚖️ 乘法分派：两个操作数相等时用 `square`，否则用通用 `compose`
+    /// 两条路径的结果相同 (`x.compose(x) == x.square()`)。
+    fn multiply_vartime(&self, other: &Self, discriminant: &Integer) -> Result<Self, AlgebraError> {
+        if self == other {
+            self.square(discriminant)
+        } else {
+            self.compose(other, discriminant)
+        }
+    }
+
     fn extended_gcd(a: &Integer, b: &Integer) -> (Integer, Integer, Integer) {
         let (mut r0, mut r1) = (a.clone(), b.clone());
         let (mut s0, mut s1) = (Integer::from(1), Integer::from(0));
@@ -213,13 +602,37 @@ impl ClassGroupElement {
 
     /// 🛡️ [SECURITY CORE]: 增强型 Reduce Form (The Invariant Fortress)
     /// 包含严格的不变量检查和整除性断言。这是系统的“最高法院”。
-    fn reduce_form(mut a: Integer, mut b: Integer, discriminant: &Integer) -> Result<Self, String> {
+    ///
+    /// [Fast Path]: 输入已是约化形式 (两个约化形式 compose 后的常见情形) 时，
+    /// 跳过规范化与约化循环，直接进入最终的不变量检查。结果与完整路径完全一致。
+    fn reduce_form(a: Integer, b: Integer, discriminant: &Integer) -> Result<Self, AlgebraError> {
+        if a > 0 && Integer::from(b.abs_ref()) <= a {
+            let numerator = b.clone().pow(2) - discriminant;
+            let denominator = Integer::from(4) * &a;
+            let (c, rem) = numerator.div_rem_ref(&denominator).into();
+            if rem != Integer::from(0) {
+                return Err(AlgebraError::NotInDiscriminant { remainder: rem });
+            }
+            if Self::is_reduced_triple(&a, &b, &c) {
+                return Self::post_mortem(a, b, c, discriminant);
+            }
+        }
+        Self::reduce_form_full(a, b, discriminant, default_reduction_step_limit(discriminant))
+    }
+
+    /// 🧮 以显式步数上限约化 (a, b)，覆盖 `default_reduction_step_limit`
+    pub fn reduce_with_step_limit(a: Integer, b: Integer, discriminant: &Integer, max_steps: usize) -> Result<Self, AlgebraError> {
+        Self::reduce_form_full(a, b, discriminant, max_steps)
+    }
+
+    /// 完整的规范化 + 约化循环路径
+    fn reduce_form_full(mut a: Integer, mut b: Integer, discriminant: &Integer, max_steps: usize) -> Result<Self, AlgebraError> {
         let four = Integer::from(4);
         
         // [FALSIFIABILITY POINT 2]: Structural Integrity Check
         // a cannot be zero. A quadratic form with a=0 is degenerate.
         let mut two_a = Integer::from(2) * &a;
-        if two_a == 0 { return Err("Math Error: 'a' coefficient is zero (Degenerate Form).".to_string()); }
+        if two_a == 0 { return Err(AlgebraError::DegenerateForm); }
 
         // 1. Initial Normalization of b
         b = b.rem_euc(&two_a);
@@ -233,27 +646,22 @@ impl ClassGroupElement {
         
         let (c_val, rem) = numerator.div_rem_ref(&denominator).into();
         if rem != Integer::from(0) {
-            return Err(format!(
-                "Invariant Violated: (b^2 - D) not divisible by 4a. Remainder: {}. \
-                This implies the form does not belong to the discriminant group.", 
-                rem
-            ));
+            return Err(AlgebraError::NotInDiscriminant { remainder: rem });
         }
         let mut c = c_val;
 
         // 2. Reduction Loop with Divergence Protection
         let mut safety_counter = 0;
-        const MAX_STEPS: usize = 2000;
 
         while a > c || (a == c && b < Integer::from(0)) {
             // [FALSIFIABILITY POINT 4]: Algorithmic Convergence
-            if safety_counter > MAX_STEPS { 
-                return Err("Critical Error: Reduction loop diverged (Infinite Loop Risk / CPU DoS).".to_string());
+            if safety_counter >= max_steps { 
+                return Err(AlgebraError::ReductionDiverged { steps: safety_counter, limit: max_steps });
             }
             
             let num = &c + &b;
             let den = Integer::from(2) * &c;
-            if den == 0 { return Err("Math Error: Division by zero in reduction (c=0).".to_string()); }
+            if den == 0 { return Err(AlgebraError::DegenerateForm); }
 
             let s = num.div_floor(&den); 
             
@@ -264,23 +672,30 @@ impl ClassGroupElement {
             let num_new = b_new.clone().pow(2) - discriminant;
             let den_new = &four * &a_new;
             
-            if den_new == 0 { return Err("Math Error: Division by zero in reduction step.".to_string()); }
+            if den_new == 0 { return Err(AlgebraError::DegenerateForm); }
 
             // [FALSIFIABILITY POINT 5]: Intermediate Consistency
             let (c_new_val, rem_new) = num_new.div_rem_ref(&den_new).into();
             if rem_new != Integer::from(0) {
-                 return Err("Invariant Violated: Consistency lost during reduction step.".to_string());
+                 return Err(AlgebraError::ConsistencyLost);
             }
 
             a = a_new; b = b_new; c = c_new_val;
             safety_counter += 1;
+
+            #[cfg(test)]
+            REDUCTION_STEPS.with(|s| s.set(s.get() + 1));
         }
 
-        // 3. [FALSIFIABILITY POINT 6]: Final Security Post-Mortem
+        Self::post_mortem(a, b, c, discriminant)
+    }
+
+    /// 3. [FALSIFIABILITY POINT 6]: Final Security Post-Mortem
+    fn post_mortem(a: Integer, b: Integer, c: Integer, discriminant: &Integer) -> Result<Self, AlgebraError> {
         // Check A: Discriminant Consistency (b^2 - 4ac == D)
         let check_d = b.clone().pow(2) - Integer::from(4) * &a * &c;
         if &check_d != discriminant {
-             return Err(format!("Fatal Logic Error: Result discriminant mismatch. Got {}, Expected {}", check_d, discriminant));
+             return Err(AlgebraError::DiscriminantMismatch { got: check_d, expected: discriminant.clone() });
         }
         
         // Check B: Primitive Form (gcd(a, b, c) == 1)
@@ -288,9 +703,455 @@ impl ClassGroupElement {
         let gcd_ab = a.clone().gcd(&b);
         let gcd_abc = gcd_ab.gcd(&c);
         if gcd_abc != Integer::from(1) {
-             return Err(format!("Security Halt: Form is not primitive (gcd={}). Potential attack vector or non-invertible ideal.", gcd_abc));
+             return Err(AlgebraError::NonPrimitive { gcd: gcd_abc });
         }
 
         Ok(ClassGroupElement { a, b, c })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form(a: i32, b: i32, c: i32) -> ClassGroupElement {
+        ClassGroupElement { a: Integer::from(a), b: Integer::from(b), c: Integer::from(c) }
+    }
+
+    /// 测试用小判别式：Delta = -M，M 为 128-bit 且 M = 3 (mod 4) 的素数
+    fn test_discriminant() -> Integer {
+        let mut m = Integer::from(1) << 127;
+        loop {
+            m.next_prime_mut();
+            if m.mod_u(4) == 3 {
+                return -m;
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_discriminant() {
+        assert!(validate_discriminant(&Integer::from(-23)).is_ok());
+        assert!(validate_discriminant(&test_discriminant()).is_ok());
+
+        let positive = validate_discriminant(&Integer::from(21)).unwrap_err().to_string();
+        assert!(positive.contains("negative"));
+        assert!(validate_discriminant(&Integer::from(0)).is_err());
+        // D = 0 (mod 4)
+        let residue = validate_discriminant(&Integer::from(-20)).unwrap_err().to_string();
+        assert!(residue.contains("mod 4"));
+        // D = 2, 3 (mod 4)
+        assert!(validate_discriminant(&Integer::from(-22)).is_err());
+        assert!(validate_discriminant(&Integer::from(-21)).is_err());
+    }
+
+    #[test]
+    fn test_errors_are_matchable_by_kind() {
+        // (3, 3, 3) 属于 D = -27，但 gcd = 3，不是本原形式
+        let d = Integer::from(-27);
+        match ClassGroupElement::reduce_form(Integer::from(3), Integer::from(3), &d) {
+            Err(AlgebraError::NonPrimitive { gcd }) => assert_eq!(gcd, 3),
+            other => panic!("expected NonPrimitive, got {:?}", other),
+        }
+
+        // (2, 0, ?) 不属于 D = -23
+        let d = Integer::from(-23);
+        let err = ClassGroupElement::reduce_form(Integer::from(2), Integer::from(0), &d).unwrap_err();
+        assert!(matches!(err, AlgebraError::NotInDiscriminant { .. }));
+        assert!(err.to_string().contains("not divisible by 4a"));
+
+        assert_eq!(
+            ClassGroupElement::reduce_form(Integer::from(0), Integer::from(1), &d),
+            Err(AlgebraError::DegenerateForm)
+        );
+        // 仍可经由 `?` 转换为 String
+        let as_string: Result<(), String> = validate_discriminant(&Integer::from(5)).map_err(String::from);
+        assert!(as_string.unwrap_err().contains("negative"));
+    }
+
+    #[test]
+    fn test_reduced_input_skips_reduction_loop() {
+        let d = test_discriminant();
+        let mut rng = rand::thread_rng();
+        let g = ClassGroupElement::generator(&d);
+
+        let mut forms = vec![ClassGroupElement::identity(&d), g.clone()];
+        for _ in 0..6 {
+            forms.push(ClassGroupElement::random(&d, &mut rng).unwrap());
+        }
+        forms.push(g.compose(&forms[2], &d).unwrap());
+
+        for f in &forms {
+            assert!(f.is_reduced());
+            REDUCTION_STEPS.with(|s| s.set(0));
+            let fast = ClassGroupElement::reduce_form(f.a.clone(), f.b.clone(), &d).unwrap();
+            assert_eq!(REDUCTION_STEPS.with(|s| s.get()), 0);
+
+            let full = ClassGroupElement::reduce_form_full(f.a.clone(), f.b.clone(), &d, usize::MAX).unwrap();
+            assert_eq!(fast, full);
+            assert_eq!(&fast, f);
+        }
+
+        // 非约化输入仍走完整路径: (c, -b, a) 需要至少一步约化
+        let f = &forms[1];
+        assert!(f.a != f.c);
+        REDUCTION_STEPS.with(|s| s.set(0));
+        let swapped = ClassGroupElement::reduce_form(f.c.clone(), Integer::from(-&f.b), &d).unwrap();
+        assert!(REDUCTION_STEPS.with(|s| s.get()) > 0);
+        assert!(swapped.is_reduced());
+    }
+
+    #[test]
+    fn test_reduction_step_limit_scales_with_discriminant() {
+        // 小判别式: 上限远低于旧的固定值 2000
+        let small = Integer::from(-23);
+        assert!(default_reduction_step_limit(&small) < 2000);
+
+        // (3, -1, 2) 至少需要一步约化；上限为 0 时必须报告步数
+        let err = ClassGroupElement::reduce_with_step_limit(Integer::from(3), Integer::from(-1), &small, 0).unwrap_err();
+        assert_eq!(err, AlgebraError::ReductionDiverged { steps: 0, limit: 0 });
+        assert!(err.to_string().contains("after 0 steps"));
+        let ok = ClassGroupElement::reduce_with_step_limit(Integer::from(3), Integer::from(-1), &small, 8).unwrap();
+        assert_eq!((ok.a, ok.b, ok.c), (Integer::from(2), Integer::from(1), Integer::from(3)));
+
+        // 大判别式: 上限高于旧的固定值，合法约化不会被误判
+        let mut m = Integer::from(1) << 1023;
+        let big = loop {
+            m.next_prime_mut();
+            if m.mod_u(4) == 3 {
+                break Integer::from(-&m);
+            }
+        };
+        assert!(default_reduction_step_limit(&big) > 2000);
+
+        let f = ClassGroupElement::random(&big, &mut rand::thread_rng()).unwrap();
+        let reduced = ClassGroupElement::reduce_form(f.c.clone(), Integer::from(-&f.b), &big).unwrap();
+        assert!(reduced.is_reduced());
+    }
+
+    #[test]
+    fn test_can_compose_detects_incompatible_pairs() {
+        let d = Integer::from(-23);
+        let f = ClassGroupElement { a: Integer::from(2), b: Integer::from(1), c: Integer::from(3) };
+        let f_inv = ClassGroupElement { a: Integer::from(2), b: Integer::from(-1), c: Integer::from(3) };
+
+        // a 不互素 (gcd = 2)，旧的 gcd(a1, a2) | s 规则会拒绝；现在可以合成
+        assert!(f.can_compose(&f_inv));
+        assert_eq!(f.compose(&f_inv, &d).unwrap(), ClassGroupElement::identity(&d));
+
+        // 已知不兼容：(1, 1, 7) 属于 D = -27，而不是 D = -23
+        let foreign = ClassGroupElement { a: Integer::from(1), b: Integer::from(1), c: Integer::from(7) };
+        assert!(!f.can_compose(&foreign));
+
+        // b 奇偶不同：s 不是整数
+        let even_b = ClassGroupElement { a: Integer::from(1), b: Integer::from(0), c: Integer::from(6) };
+        assert!(!f.can_compose(&even_b));
+
+        // 退化形式
+        let degenerate = ClassGroupElement { a: Integer::from(0), b: Integer::from(1), c: Integer::from(0) };
+        assert!(!f.can_compose(&degenerate));
+        assert!(!degenerate.can_compose(&f));
+    }
+
+    #[test]
+    fn test_ambiguous_forms_are_self_inverse() {
+        // b = 0: (1, 0, 5) 属于 D = -20 (仅作形状检查)
+        assert!(form(1, 0, 5).is_ambiguous());
+
+        // a = b: (3, 3, 4) 属于 D = -39
+        let d = Integer::from(-39);
+        let f = form(3, 3, 4);
+        assert!(f.is_ambiguous());
+        assert_eq!(f.inverse(&d).unwrap(), f);
+        assert!(f.has_small_order(&d, 1000));
+
+        // a = c: (2, 1, 2) 属于 D = -15
+        let d = Integer::from(-15);
+        let f = form(2, 1, 2);
+        assert!(f.is_ambiguous());
+        assert_eq!(f.inverse(&d).unwrap(), f);
+        assert_eq!(f.square(&d).unwrap(), ClassGroupElement::identity(&d));
+
+        // 对照组：(2, 1, 3) 属于 D = -23，阶为 3
+        let d = Integer::from(-23);
+        let f = form(2, 1, 3);
+        assert!(!f.is_ambiguous());
+        assert_ne!(f.inverse(&d).unwrap(), f);
+    }
+
+    #[test]
+    fn test_from_str_coeffs_validates_and_reduces() {
+        let d = Integer::from(-23);
+        assert_eq!(ClassGroupElement::from_str_coeffs("2", "1", "3", &d).unwrap(), form(2, 1, 3));
+        // 未约化的输入被约化到规范代表元
+        assert_eq!(ClassGroupElement::from_str_coeffs("3", "-1", "2", &d).unwrap(), form(2, 1, 3));
+
+        assert!(matches!(
+            ClassGroupElement::from_str_coeffs("2", "1", "4", &d),
+            Err(AlgebraError::DiscriminantMismatch { .. })
+        ));
+        assert!(matches!(
+            ClassGroupElement::from_str_coeffs("3", "3", "3", &Integer::from(-27)),
+            Err(AlgebraError::NonPrimitive { .. })
+        ));
+        assert!(matches!(
+            ClassGroupElement::from_str_coeffs("-2", "1", "-3", &d),
+            Err(AlgebraError::NotPositiveDefinite)
+        ));
+        assert!(matches!(
+            ClassGroupElement::from_str_coeffs("two", "1", "3", &d),
+            Err(AlgebraError::InvalidCoefficient { name: "a", .. })
+        ));
+    }
+
+    #[test]
+    fn test_identity_short_circuit_matches_and_saves_work() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let d = test_discriminant();
+        let mut rng = StdRng::seed_from_u64(1635);
+        let identity = ClassGroupElement::identity(&d);
+        let x = ClassGroupElement::random(&d, &mut rng).unwrap();
+        let y = ClassGroupElement::random(&d, &mut rng).unwrap();
+
+        assert!(identity.is_identity(&d));
+        assert!(!x.is_identity(&d));
+        assert_eq!(identity.compose_vartime(&x, &d).unwrap(), x);
+        assert_eq!(x.compose_vartime(&identity, &d).unwrap(), x);
+        assert_eq!(identity.compose(&x, &d).unwrap(), x);
+
+        // 短路不调用 compose；常数序列的 compose 不短路
+        COMPOSE_CALLS.with(|c| c.set(0));
+        identity.compose_vartime(&x, &d).unwrap();
+        assert_eq!(COMPOSE_CALLS.with(|c| c.get()), 0);
+
+        // 未约化的操作数不走快速路径，仍由完整路径约化
+        let mut unreduced = x.clone();
+        unreduced.b += Integer::from(&x.a << 1);
+        unreduced.c = (Integer::from(unreduced.b.square_ref()) - &d) / (Integer::from(&x.a << 2));
+        assert_eq!(identity.compose_vartime(&unreduced, &d).unwrap(), x);
+
+        // 稀疏折叠：只有 x ⊕ y 需要完整合成
+        let sparse = vec![identity.clone(), x.clone(), identity.clone(), identity.clone(), y.clone(), identity.clone()];
+        COMPOSE_CALLS.with(|c| c.set(0));
+        let folded = ClassGroupElement::compose_many(&sparse, &d).unwrap();
+        assert_eq!(COMPOSE_CALLS.with(|c| c.get()), 1);
+        assert_eq!(folded, x.compose(&y, &d).unwrap());
+    }
+
+    #[test]
+    fn test_compose_many_matches_manual_fold() {
+        let d = test_discriminant();
+        let mut rng = rand::thread_rng();
+        let forms: Vec<ClassGroupElement> = (0..5)
+            .map(|_| ClassGroupElement::random(&d, &mut rng).unwrap())
+            .collect();
+
+        for len in [0, 1, 2, 5] {
+            let mut manual = ClassGroupElement::identity(&d);
+            for f in &forms[..len] {
+                manual = manual.compose(f, &d).unwrap();
+            }
+            assert_eq!(ClassGroupElement::compose_many(&forms[..len], &d).unwrap(), manual, "len {}", len);
+        }
+        assert_eq!(ClassGroupElement::compose_many(&forms[..1], &d).unwrap(), forms[0]);
+    }
+
+    #[test]
+    fn test_reflection_reduces_to_inverse() {
+        let d = test_discriminant();
+        let mut rng = rand::thread_rng();
+        let identity = ClassGroupElement::identity(&d);
+
+        for _ in 0..8 {
+            let f = ClassGroupElement::random(&d, &mut rng).unwrap();
+            let reflected = f.reflect();
+            assert_eq!((&reflected.a, &reflected.c), (&f.a, &f.c));
+            assert_eq!(reflected.b, Integer::from(-&f.b));
+
+            let reduced = reflected.reduce(&d).unwrap();
+            assert_eq!(reduced, f.inverse(&d).unwrap());
+            assert_eq!(f.compose(&reduced, &d).unwrap(), identity);
+        }
+
+        // b = a 的反射不是约化形式，约化后回到自身 (二阶元)
+        let d = Integer::from(-15);
+        let f = form(2, 1, 2);
+        assert!(!f.reflect().is_reduced());
+        assert_eq!(f.reflect().reduce(&d).unwrap(), f);
+    }
+
+    #[test]
+    fn test_pow_ladder_has_operand_independent_sequence() {
+        // 每一位固定一次 compose + 一次 square，与底数取值 (包括单位元) 和指数位模式无关
+        let d = test_discriminant();
+        let base = ClassGroupElement::generator(&d);
+        let identity = ClassGroupElement::identity(&d);
+        for exp in [Integer::from(0x8000_0000u32), Integer::from(0xFFFF_FFFFu32), Integer::from(0xDEAD_BEEFu32)] {
+            for g in [&base, &identity] {
+                COMPOSE_CALLS.with(|c| c.set(0));
+                SQUARE_CALLS.with(|c| c.set(0));
+                g.pow(&exp, &d).unwrap();
+                assert_eq!(COMPOSE_CALLS.with(|c| c.get()), exp.significant_bits() as usize);
+                assert_eq!(SQUARE_CALLS.with(|c| c.get()), exp.significant_bits() as usize);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pow_vartime_matches_pow_and_dispatches_to_square() {
+        use rand::{Rng, SeedableRng, rngs::StdRng};
+
+        let d = test_discriminant();
+        let mut rng = StdRng::seed_from_u64(1618);
+        let base = ClassGroupElement::random(&d, &mut rng).unwrap();
+        for _ in 0..32 {
+            let exp = Integer::from(rng.gen::<u64>());
+            assert_eq!(base.pow_vartime(&exp, &d).unwrap(), base.pow(&exp, &d).unwrap());
+        }
+        assert_eq!(base.pow_vartime(&Integer::from(0), &d).unwrap(), ClassGroupElement::identity(&d));
+
+        // 单位元为底时阶梯两侧始终相等：所有乘法都改走 square
+        let identity = ClassGroupElement::identity(&d);
+        let exp = Integer::from(0xDEAD_BEEFu32);
+        COMPOSE_CALLS.with(|c| c.set(0));
+        SQUARE_CALLS.with(|c| c.set(0));
+        assert_eq!(identity.pow_vartime(&exp, &d).unwrap(), identity);
+        assert_eq!(COMPOSE_CALLS.with(|c| c.get()), 0);
+        assert_eq!(SQUARE_CALLS.with(|c| c.get()), 2 * exp.significant_bits() as usize);
+    }
+
+    #[test]
+    fn test_apply_affine_cached_matches_and_skips_squaring() {
+        let d = test_discriminant();
+        let mut rng = rand::thread_rng();
+        let s = ClassGroupElement::random(&d, &mut rng).unwrap();
+        let q = ClassGroupElement::random(&d, &mut rng).unwrap();
+        let mut cache = PowCache::new();
+
+        for h_t in 1..=8u32 {
+            let p = Integer::from(h_t);
+            let plain = s.apply_affine(&p, &q, &d).unwrap();
+
+            SQUARE_CALLS.with(|c| c.set(0));
+            let first = s.apply_affine_cached(&p, &q, &d, &mut cache).unwrap();
+            let cold_squares = SQUARE_CALLS.with(|c| c.get());
+
+            SQUARE_CALLS.with(|c| c.set(0));
+            let second = s.apply_affine_cached(&p, &q, &d, &mut cache).unwrap();
+            let warm_squares = SQUARE_CALLS.with(|c| c.get());
+
+            assert_eq!(first, plain);
+            assert_eq!(second, plain);
+            assert!(cold_squares > 0);
+            assert_eq!(warm_squares, 0);
+        }
+        assert_eq!((cache.hits, cache.misses), (8, 8));
+    }
+
+    #[test]
+    fn test_display_truncation_boundaries() {
+        // 恰好 2 * DISPLAY_EDGE_DIGITS 位：不截断
+        assert_eq!(abbreviate_integer(&Integer::from(12345678)), "12345678");
+        assert_eq!(abbreviate_integer(&Integer::from(-12345678)), "-12345678");
+        // 多一位：截断并附带位长
+        assert_eq!(abbreviate_integer(&Integer::from(123456789)), "1234..6789 [27 bits]");
+        assert_eq!(abbreviate_integer(&Integer::from(-123456789)), "-1234..6789 [27 bits]");
+        assert_eq!(abbreviate_integer(&Integer::from(0)), "0");
+
+        let d = test_discriminant();
+        let e = ClassGroupElement::identity(&d);
+        let shown = format!("{}", e);
+        assert!(shown.starts_with("(a=1, b=1, c="));
+        assert!(shown.contains("bits]"));
+        assert_eq!(e.to_full_string(), format!("(a=1, b=1, c={})", e.c));
+    }
+
+    #[test]
+    fn test_indexed_generators_are_distinct_and_safe() {
+        let d = test_discriminant();
+        let g0 = ClassGroupElement::generator_indexed(&d, 0);
+        let g1 = ClassGroupElement::generator_indexed(&d, 1);
+        let g2 = ClassGroupElement::generator_indexed(&d, 2);
+
+        assert_ne!(g0, g1);
+        assert_ne!(g1, g2);
+        assert_ne!(g0, g2);
+        for g in [&g0, &g1, &g2] {
+            assert!(!g.has_small_order(&d, 1000));
+        }
+
+        // 可复现
+        assert_eq!(ClassGroupElement::generator_indexed(&d, 1), g1);
+    }
+
+    #[test]
+    fn test_compose_and_square_on_small_class_group() {
+        // Delta = -23，类数 3：g = (2, 1, 3)，g^2 = g^-1 = (2, -1, 3)
+        let d = Integer::from(-23);
+        let g = ClassGroupElement { a: Integer::from(2), b: Integer::from(1), c: Integer::from(3) };
+        let g_inv = ClassGroupElement { a: Integer::from(2), b: Integer::from(-1), c: Integer::from(3) };
+
+        // gcd(a1, a2) = 2 不整除 s = 1：旧实现在此直接报错
+        assert_eq!(g.compose(&g, &d), Ok(g_inv.clone()));
+        // square 求解新 b 时须用 -y*c
+        assert_eq!(g.square(&d), Ok(g_inv.clone()));
+        assert_eq!(g.compose(&g_inv, &d), Ok(ClassGroupElement::identity(&d)));
+    }
+
+    #[test]
+    fn test_random_samples_are_reduced_and_varied() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let d = test_discriminant();
+        let mut rng = StdRng::seed_from_u64(7);
+        let samples: Vec<_> = (0..8)
+            .map(|_| ClassGroupElement::random(&d, &mut rng).unwrap())
+            .collect();
+
+        for f in &samples {
+            assert_eq!(Integer::from(&f.b * &f.b) - Integer::from(4) * &f.a * &f.c, d);
+            assert!(Integer::from(f.b.abs_ref()) <= f.a && f.a <= f.c, "not reduced: {:?}", f);
+        }
+        let distinct: std::collections::HashSet<_> = samples.iter().collect();
+        assert!(distinct.len() > 1);
+
+        // 同一种子可复现
+        let mut replay = StdRng::seed_from_u64(7);
+        assert_eq!(ClassGroupElement::random(&d, &mut replay).unwrap(), samples[0]);
+    }
+
+    #[test]
+    fn test_group_axioms() {
+        use rand::{Rng, SeedableRng, rngs::StdRng};
+
+        let d = test_discriminant();
+        let id = ClassGroupElement::identity(&d);
+        let mut rng = StdRng::seed_from_u64(2025);
+
+        for _ in 0..16 {
+            let a = ClassGroupElement::random(&d, &mut rng).unwrap();
+            let b = ClassGroupElement::random(&d, &mut rng).unwrap();
+            let c = ClassGroupElement::random(&d, &mut rng).unwrap();
+            let triple = format!("\n a={:?}\n b={:?}\n c={:?}", a, b, c);
+
+            // 结合律
+            let ab_c = a.compose(&b, &d).and_then(|ab| ab.compose(&c, &d));
+            let a_bc = b.compose(&c, &d).and_then(|bc| a.compose(&bc, &d));
+            assert!(ab_c.is_ok(), "compose failed: {:?}{}", ab_c, triple);
+            assert_eq!(ab_c, a_bc, "associativity failed{}", triple);
+
+            // 单位元 / 逆元 / 平方
+            assert_eq!(a.compose(&id, &d), Ok(a.clone()), "identity failed{}", triple);
+            assert_eq!(a.compose(&a.inverse(&d).unwrap(), &d), Ok(id.clone()), "inverse failed{}", triple);
+            assert_eq!(a.compose(&a, &d), a.square(&d), "compose(a, a) != square(a){}", triple);
+
+            // 幂同态: a^m * a^n == a^(m+n)
+            let (m, n): (u32, u32) = (rng.gen_range(0..64), rng.gen_range(0..64));
+            let lhs = a.pow(&Integer::from(m), &d)
+                .and_then(|am| am.compose(&a.pow(&Integer::from(n), &d)?, &d));
+            let rhs = a.pow(&Integer::from(m + n), &d);
+            assert_eq!(lhs, rhs, "homomorphism failed (m={}, n={}){}", m, n, triple);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+// COPYRIGHT (C) 2025 M-Patek. ALL RIGHTS RESERVED.
+
+use rug::Integer;
+use std::fmt;
+
+/// 🧯 AlgebraError: 代数核心的结构化错误
+/// 让上层 (例如进化引擎) 可以按错误种类分支，而不必匹配字符串。
+/// `Display` 保留原有的人类可读信息；`From<AlgebraError> for String`
+/// 使仍返回 `Result<_, String>` 的调用方可以直接使用 `?`。
+///
+/// `net::wire` 不在转换范围内：它没有返回 `Result` 的函数 (证明校验只给出 `bool`，
+/// 重放时的 `AlgebraError` 在本地记录后即被折叠)，而 `HtpResponse::Error` 是
+/// 跨进程序列化的协议载荷，只携带可读文本，对端无法依赖本 crate 的枚举布局。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlgebraError {
+    /// 判别式不合法 (必须 D < 0 且 D = 1 mod 4)
+    InvalidDiscriminant { discriminant: Integer, reason: &'static str },
+    /// 约化结果不是本原形式 (gcd(a, b, c) != 1)
+    NonPrimitive { gcd: Integer },
+    /// 约化结果的判别式与期望不符
+    DiscriminantMismatch { got: Integer, expected: Integer },
+    /// (b^2 - D) 不能被 4a 整除：三元组不属于该判别式
+    NotInDiscriminant { remainder: Integer },
+    /// 约化步骤中不变量丢失
+    ConsistencyLost,
+    /// 约化循环超过步数上限 (疑似 DoS 输入)
+    ReductionDiverged { steps: usize, limit: usize },
+    /// 退化形式 (a = 0 或约化中出现除零)
+    DegenerateForm,
+    /// 合成时 gcd(a1, a2, s) = 0，形式不兼容
+    IncompatibleForms,
+    /// 仿射合成的 P 因子溢出熔断
+    PFactorOverflow { bits: u64, limit: u32 },
+    /// 空间聚合的 P 因子溢出熔断
+    SpatialOverflow { bits: u64, limit: u32 },
+    /// 随机采样找不到分裂素数
+    NoSplitPrime,
+    /// 外部输入的系数不是合法的十进制整数
+    InvalidCoefficient { name: &'static str, input: String },
+    /// a <= 0：不是正定形式
+    NotPositiveDefinite,
+}
+
+impl fmt::Display for AlgebraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlgebraError::InvalidDiscriminant { discriminant, reason } => {
+                write!(f, "Invalid discriminant {}: {}.", discriminant, reason)
+            }
+            AlgebraError::NonPrimitive { gcd } => write!(
+                f,
+                "Security Halt: Form is not primitive (gcd={}). Potential attack vector or non-invertible ideal.",
+                gcd
+            ),
+            AlgebraError::DiscriminantMismatch { got, expected } => write!(
+                f,
+                "Fatal Logic Error: Result discriminant mismatch. Got {}, Expected {}",
+                got, expected
+            ),
+            AlgebraError::NotInDiscriminant { remainder } => write!(
+                f,
+                "Invariant Violated: (b^2 - D) not divisible by 4a. Remainder: {}. \
+                This implies the form does not belong to the discriminant group.",
+                remainder
+            ),
+            AlgebraError::ConsistencyLost => {
+                write!(f, "Invariant Violated: Consistency lost during reduction step.")
+            }
+            AlgebraError::ReductionDiverged { steps, limit } => write!(
+                f,
+                "Critical Error: Reduction loop diverged after {} steps (limit {}). Infinite Loop Risk / CPU DoS.",
+                steps, limit
+            ),
+            AlgebraError::DegenerateForm => {
+                write!(f, "Math Error: Degenerate Form (zero coefficient or division by zero in reduction).")
+            }
+            AlgebraError::IncompatibleForms => {
+                write!(f, "Composition Error: gcd(a1, a2, s) is zero. Forms are incompatible.")
+            }
+            AlgebraError::PFactorOverflow { bits, limit } => write!(
+                f,
+                "❌ Falsified: Affine P-Factor overflow ({} bits > {}). Global accumulation is forbidden; use State Streaming instead.",
+                bits, limit
+            ),
+            AlgebraError::SpatialOverflow { bits, limit } => write!(
+                f,
+                "❌ Falsified: Affine P-Factor overflow in spatial merge ({} bits > {}). Spatial aggregation must stay within a chunk.",
+                bits, limit
+            ),
+            AlgebraError::NoSplitPrime => {
+                write!(f, "Sampling Error: no split prime found for this discriminant.")
+            }
+            AlgebraError::InvalidCoefficient { name, input } => {
+                write!(f, "Parse Error: coefficient {} = {:?} is not a decimal integer.", name, input)
+            }
+            AlgebraError::NotPositiveDefinite => {
+                write!(f, "Math Error: Form is not positive definite (a must be > 0).")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AlgebraError {}
+
+impl From<AlgebraError> for String {
+    fn from(e: AlgebraError) -> Self {
+        e.to_string()
+    }
+}
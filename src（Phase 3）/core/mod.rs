@@ -2,6 +2,7 @@
 
 pub mod affine;
 pub mod algebra;
+pub mod error;
 pub mod param;
 pub mod primes;
 pub mod neuron;
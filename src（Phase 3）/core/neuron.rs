@@ -2,10 +2,10 @@
 
 use crate::phase3::core::affine::AffineTuple;
 use crate::phase3::topology::tensor::HyperTensor;
-use crate::phase3::net::wire::HtpResponse; 
-use crate::phase3::core::algebra::ClassGroupElement;
+use crate::phase3::net::wire::{AnchorLayout, HtpResponse, MerkleProof, StateTransitionProof, merkle_root}; 
+use crate::phase3::core::algebra::{ClassGroupElement, PowCache, validate_discriminant};
 use rug::Integer;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 use rand::{Rng, thread_rng};
 
@@ -23,18 +23,39 @@ pub struct HTPNeuron {
     pub semantic_root: RwLock<ClassGroupElement>,
     /// [Micro-Buffer]: 用于构建局部 Checkpoint 的微观缓冲区
     pub commitment_buffer: RwLock<Vec<AffineTuple>>,
+    /// [Checkpoint Log]: 本次推理中依次记录的 Checkpoint 状态 (Merkle 叶子)
+    pub checkpoint_log: RwLock<Vec<ClassGroupElement>>,
+    /// [Anchor Axes]: 生成正交锚点时投影的轴 (默认全部轴)
+    pub anchor_axes: Vec<usize>,
+    /// [Noise Memo]: 时空噪声 g^(t+1) 的幂缓存，跨多次推理复用
+    pub noise_cache: Mutex<PowCache>,
+    /// [Side-Channel]: 加权前是否做盲化往返 (默认开启)
+    /// 盲化在数学上相互抵消，关闭后结果不变；仅在可信的单进程训练中关闭以省去约 2/3 的群运算。
+    pub blinding_enabled: bool,
 }
 
 impl HTPNeuron {
+    /// 构造神经元；判别式非法时 panic (见 `try_new`)
     pub fn new(semantic_fingerprint: Integer, dim: usize, side_len: usize, discriminant: Integer) -> Self {
+        Self::try_new(semantic_fingerprint, dim, side_len, discriminant)
+            .unwrap_or_else(|e| panic!("HTPNeuron::new: {}", e))
+    }
+
+    /// 构造神经元，先校验判别式 (D < 0 且 D = 1 mod 4)
+    pub fn try_new(semantic_fingerprint: Integer, dim: usize, side_len: usize, discriminant: Integer) -> Result<Self, String> {
+        validate_discriminant(&discriminant)?;
         let tensor = HyperTensor::new(dim, side_len, discriminant.clone());
-        HTPNeuron {
+        Ok(HTPNeuron {
             p_weight: semantic_fingerprint,
             memory: Arc::new(RwLock::new(tensor)),
             discriminant: discriminant.clone(),
             semantic_root: RwLock::new(ClassGroupElement::identity(&discriminant)),
             commitment_buffer: RwLock::new(Vec::new()),
-        }
+            checkpoint_log: RwLock::new(Vec::new()),
+            anchor_axes: (0..dim).collect(),
+            noise_cache: Mutex::new(PowCache::new()),
+            blinding_enabled: true,
+        })
     }
 
     /// ⚡ Activate: 执行流式推理
@@ -55,6 +76,16 @@ impl HTPNeuron {
         // Reset state for new inference pass
         *s_guard = ClassGroupElement::identity(&self.discriminant);
         buffer_guard.clear();
+        {
+            let mut log_guard = self.checkpoint_log.write().map_err(|_| "Checkpoint Log Lock poisoned")?;
+            log_guard.clear();
+            // Checkpoint #0: 起始状态
+            log_guard.push(s_guard.clone());
+        }
+
+        // 最后一个 Chunk 的起点与算子，用于构造状态转移证明
+        let mut last_from = 0usize;
+        let mut last_chunk: Vec<AffineTuple> = Vec::new();
         
         for (t, tuple) in input_stream.iter().enumerate() {
             // (a) Blinded Evolution: 加权演化
@@ -86,13 +117,17 @@ impl HTPNeuron {
 
                 let checkpoint_key = format!("chk:seq:{}", t);
                 // 写入全息张量，供后续 Oracle 查询或反向解码
-                memory_guard.insert(&checkpoint_key, checkpoint, t as u64)?;
-                buffer_guard.clear();
+                memory_guard.insert(&checkpoint_key, checkpoint)?;
+
+                // 追加到 Checkpoint Log
+                let mut log_guard = self.checkpoint_log.write().map_err(|_| "Checkpoint Log Lock poisoned")?;
+                last_from = log_guard.len() - 1;
+                last_chunk = std::mem::take(&mut *buffer_guard);
+                log_guard.push(s_guard.clone());
             }
         }
-
-        // 计算全息根 (Global Root)，用于一致性验证
-        let _raw_tensor_root = memory_guard.calculate_global_root()?;
+        // 构造证明时需要读取张量几何，先释放写锁
+        drop(memory_guard);
 
         // 返回最新的语义状态作为输出
         let final_output = AffineTuple {
@@ -100,22 +135,79 @@ impl HTPNeuron {
             q_shift: s_guard.clone(), 
         };
 
-        // 构造证明包 (简化版)
-        let proof_coord = memory_guard.map_id_to_coord(0); 
-        let proof_path = memory_guard.get_segment_tree_path(&proof_coord, 0);
-        
+        // 构造证明包：从倒数第二个 Checkpoint 重放最后一个 Chunk，到达最终状态
         let proof = HtpResponse::ProofBundle {
             request_id: 0,
-            primary_path: proof_path,
-            orthogonal_anchors: vec![],
-            epoch: recursion_depth as u64,
+            proof: self.build_transition_proof(last_from, &last_chunk)?,
+            log_epoch: recursion_depth as u64,
         };
 
         Ok((final_output, proof))
     }
 
+    /// 👑 Checkpoint Log 的 Merkle Root，即验证者使用的 `global_merkle_root`
+    pub fn checkpoint_root(&self) -> Result<[u8; 32], String> {
+        let log_guard = self.checkpoint_log.read().map_err(|_| "Checkpoint Log Lock poisoned")?;
+        let leaves: Vec<[u8; 32]> = log_guard.iter().map(|state| AffineTuple::checkpoint(state.clone()).leaf_hash()).collect();
+        Ok(merkle_root(&leaves))
+    }
+
+    /// ⏭️ 构建状态转移证明
+    /// 捕获第 `from_checkpoint` 个 Checkpoint 的状态及其 Merkle 包含证明，
+    /// 并在其上重放 `ops` 得到声称的最终状态。
+    pub fn build_transition_proof(&self, from_checkpoint: usize, ops: &[AffineTuple]) -> Result<StateTransitionProof, String> {
+        let log_guard = self.checkpoint_log.read().map_err(|_| "Checkpoint Log Lock poisoned")?;
+
+        let checkpoint_state = log_guard.get(from_checkpoint)
+            .cloned()
+            .ok_or_else(|| format!("Checkpoint {} not found in log (len = {})", from_checkpoint, log_guard.len()))?;
+
+        let leaves: Vec<[u8; 32]> = log_guard.iter().map(|state| AffineTuple::checkpoint(state.clone()).leaf_hash()).collect();
+        let log_inclusion_proof = MerkleProof::build(&leaves, from_checkpoint as u64)
+            .ok_or("Failed to build Merkle inclusion proof")?;
+
+        let anchor_layout = self.anchor_layout()?;
+        let orthogonal_anchors = anchor_layout.project(from_checkpoint as u64)
+            .ok_or_else(|| format!("Invalid anchor layout: {:?}", anchor_layout))?
+            .into_iter()
+            .map(|idx| MerkleProof::build(&leaves, idx).ok_or("Failed to build orthogonal anchor"))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut claimed_final_state = checkpoint_state.clone();
+        for op in ops {
+            claimed_final_state = claimed_final_state.apply_affine(&op.p_factor, &op.q_shift, &self.discriminant)?;
+        }
+
+        Ok(StateTransitionProof {
+            checkpoint_state,
+            log_inclusion_proof,
+            replay_ops: ops.to_vec(),
+            claimed_final_state,
+            orthogonal_anchors,
+            anchor_layout: Some(anchor_layout),
+        })
+    }
+
+    /// 🧭 正交锚点的投影几何：记忆张量的维度与边长，加上 `anchor_axes`
+    /// 调用方不得持有 `memory` 的写锁。
+    fn anchor_layout(&self) -> Result<AnchorLayout, String> {
+        let memory_guard = self.memory.read().map_err(|_| "Memory Lock poisoned")?;
+        Ok(AnchorLayout {
+            dimensions: memory_guard.dimensions,
+            side_length: memory_guard.side_length,
+            axes: self.anchor_axes.clone(),
+        })
+    }
+
     /// 内部逻辑：加权与盲化
     fn evolve_tuple_blinded(&self, tuple: &AffineTuple, weight: &Integer) -> Result<AffineTuple, String> {
+        if !self.blinding_enabled {
+            return Ok(AffineTuple {
+                p_factor: Integer::from(&tuple.p_factor * weight),
+                q_shift: tuple.q_shift.pow(weight, &self.discriminant)?,
+            });
+        }
+
         let mut rng = thread_rng();
         // 简单的盲化因子，防止侧信道分析
         let blind_exp = Integer::from(rng.gen::<u64>());
@@ -128,12 +220,8 @@ impl HTPNeuron {
         
         // 修正项
         let r_w = r_blind.pow(weight, &self.discriminant)?;
-        // 逆元: (a, -b, c)
-        let r_w_inv = ClassGroupElement {
-            a: r_w.a,
-            b: -r_w.b, 
-            c: r_w.c,
-        };
+        // 逆元: 反射 (a, -b, c)，不约化，由 compose 负责最终约化
+        let r_w_inv = r_w.reflect();
         
         let new_q = q_prime_blinded.compose(&r_w_inv, &self.discriminant)?;
         let new_p = Integer::from(&tuple.p_factor * weight);
@@ -147,10 +235,120 @@ impl HTPNeuron {
     fn generate_spacetime_noise(&self, t: usize) -> Result<AffineTuple, String> {
         let g = ClassGroupElement::generator(&self.discriminant);
         let h_t = Integer::from(t + 1);
-        let q_noise = g.pow(&h_t, &self.discriminant)?;
+        let q_noise = {
+            let mut cache = self.noise_cache.lock().map_err(|_| "Noise Cache Lock poisoned")?;
+            cache.pow(&g, &h_t, &self.discriminant)?
+        };
         Ok(AffineTuple {
             p_factor: Integer::from(1),
             q_shift: q_noise,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    fn test_neuron() -> HTPNeuron {
+        // Delta = -(2^61 - 1)，2^61 - 1 为素数且 = 3 (mod 4)
+        let discriminant = -((Integer::from(1) << 61) - 1u32);
+        HTPNeuron::new(Integer::from(7), 2, 4, discriminant)
+    }
+
+    #[test]
+    fn test_try_new_rejects_bad_discriminants() {
+        assert!(HTPNeuron::try_new(Integer::from(7), 2, 4, Integer::from(-23)).is_ok());
+        assert!(HTPNeuron::try_new(Integer::from(7), 2, 4, Integer::from(23)).is_err());
+        assert!(HTPNeuron::try_new(Integer::from(7), 2, 4, Integer::from(0)).is_err());
+        assert!(HTPNeuron::try_new(Integer::from(7), 2, 4, Integer::from(-24)).is_err());
+    }
+
+    #[test]
+    fn test_blinding_switch_preserves_result() {
+        let blinded = test_neuron();
+        let mut plain = test_neuron();
+        plain.blinding_enabled = false;
+        assert!(blinded.blinding_enabled);
+
+        let d = blinded.discriminant.clone();
+        let mut rng = StdRng::seed_from_u64(11);
+        let inputs: Vec<AffineTuple> = (0..4)
+            .map(|i| AffineTuple {
+                p_factor: Integer::from(i + 3),
+                q_shift: ClassGroupElement::random(&d, &mut rng).unwrap(),
+            })
+            .collect();
+
+        let weight = Integer::from(1_000_003u32);
+        for tuple in &inputs {
+            assert_eq!(
+                blinded.evolve_tuple_blinded(tuple, &weight).unwrap(),
+                plain.evolve_tuple_blinded(tuple, &weight).unwrap()
+            );
+        }
+
+        let (root_blinded, _) = blinded.activate(inputs.clone(), 0).unwrap();
+        let (root_plain, _) = plain.activate(inputs, 0).unwrap();
+        assert_eq!(root_blinded, root_plain);
+    }
+
+    #[test]
+    fn test_transition_proof_end_to_end() {
+        let neuron = test_neuron();
+        let d = neuron.discriminant.clone();
+        let mut rng = StdRng::seed_from_u64(3);
+
+        let ops: Vec<AffineTuple> = (0..3)
+            .map(|i| AffineTuple {
+                p_factor: Integer::from(i + 2),
+                q_shift: ClassGroupElement::random(&d, &mut rng).unwrap(),
+            })
+            .collect();
+
+        // 记录若干 Checkpoint
+        {
+            let mut log = neuron.checkpoint_log.write().unwrap();
+            for _ in 0..5 {
+                log.push(ClassGroupElement::random(&d, &mut rng).unwrap());
+            }
+        }
+        let root = neuron.checkpoint_root().unwrap();
+
+        let proof = neuron.build_transition_proof(2, &ops).unwrap();
+        assert_eq!(proof.orthogonal_anchors.len(), neuron.anchor_axes.len());
+        assert!(proof.verify(&root, &d));
+
+        // 伪造锚点
+        let mut forged = neuron.build_transition_proof(2, &ops).unwrap();
+        forged.orthogonal_anchors[0].leaf_hash = [0u8; 32];
+        assert!(!forged.verify(&root, &d));
+
+        // 用另一片合法叶子冒充锚点：包含证明本身有效，但序号不是投影
+        let leaves: Vec<[u8; 32]> = neuron.checkpoint_log.read().unwrap().iter()
+            .map(|state| AffineTuple::checkpoint(state.clone()).leaf_hash())
+            .collect();
+        let mut forged = neuron.build_transition_proof(2, &ops).unwrap();
+        forged.orthogonal_anchors[0] = MerkleProof::build(&leaves, 4).unwrap();
+        assert!(forged.orthogonal_anchors[0].verify(&root));
+        assert!(!forged.verify(&root, &d));
+
+        // 去掉投影几何同样失败
+        let mut forged = neuron.build_transition_proof(2, &ops).unwrap();
+        forged.anchor_layout = None;
+        assert!(!forged.verify(&root, &d));
+
+        // 篡改最终状态
+        let mut forged = neuron.build_transition_proof(2, &ops).unwrap();
+        forged.claimed_final_state = ClassGroupElement::identity(&d);
+        assert!(!forged.verify(&root, &d));
+
+        // 篡改 Checkpoint
+        let mut forged = neuron.build_transition_proof(2, &ops).unwrap();
+        forged.checkpoint_state = ClassGroupElement::identity(&d);
+        assert!(!forged.verify(&root, &d));
+
+        assert!(neuron.build_transition_proof(9, &ops).is_err());
+    }
+}
@@ -13,6 +13,9 @@ use wesolowski::{verify as vdf_verify, Error as VdfError};
 // 这是系统能接受的理论下限
 const MIN_DISCRIMINANT_BITS: u32 = 3072; 
 
+// 绝对下限：低于此位宽直接熔断。介于此值与 MIN_DISCRIMINANT_BITS 之间仅限测试。
+const TEST_FLOOR_DISCRIMINANT_BITS: u32 = 2048;
+
 // 域分离标签 (Domain Separation Tag)
 // 防止跨协议重放攻击
 const DOMAIN_TAG: &[u8] = b"Evolver_v1_System_Discriminant_Generation_DST";
@@ -21,6 +24,52 @@ const DOMAIN_TAG: &[u8] = b"Evolver_v1_System_Discriminant_Generation_DST";
 // VDF 时间参数 T，决定了计算必须经历的物理时间长度
 const VDF_TIME_PARAM_T: u64 = 1 << 40; 
 
+// 设置一个极高的上限，防止无限死循环，但如果在这么多次尝试后仍失败，
+// 说明熵源有问题或系统正处于极度异常的状态。
+const MAX_DISCRIMINANT_ATTEMPTS: u32 = 10_000_000;
+
+/// 🕰️ VDF 验证器抽象
+/// 允许注入自定义实现（例如测试桩），默认实现见 `DefaultVdfVerifier`。
+pub trait VdfVerifier {
+    fn verify(&self, input: &[u8], output: &[u8], proof: &[u8], t: u64) -> bool;
+}
+
+/// 默认验证器：开启 `production_vdf` 时使用 wesolowski，否则为 MOCK 模式。
+pub struct DefaultVdfVerifier;
+
+impl VdfVerifier for DefaultVdfVerifier {
+    #[allow(unused_variables)]
+    fn verify(&self, input: &[u8], output: &[u8], proof: &[u8], t: u64) -> bool {
+        #[cfg(feature = "production_vdf")]
+        {
+            match vdf_verify(input, output, proof, t) {
+                Ok(true) => return true,
+                Ok(false) => {
+                    eprintln!("[VDF Verify] ❌ Mathematical verification failed.");
+                    return false;
+                },
+                Err(e) => {
+                    eprintln!("[VDF Verify] ❌ Verification error: {:?}", e);
+                    return false;
+                }
+            }
+        }
+
+        #[cfg(not(feature = "production_vdf"))]
+        {
+            // 在非生产环境下，我们模拟 VDF 验证
+            // 注意：这仅用于单元测试，绝对不能用于主网
+            println!("[VDF Verify] ⚠️ WARNING: Running in MOCK mode. Not secure for mainnet.");
+            let mut hasher = Hasher::new();
+            hasher.update(b"EVOLVER_VDF_SIMULATION_BINDING");
+            hasher.update(input);
+            hasher.update(output);
+            let expected_proof_hash = hasher.finalize();
+            proof == expected_proof_hash.as_bytes()
+        }
+    }
+}
+
 pub struct SystemParameters {
     pub discriminant: Integer,
 }
@@ -29,31 +78,57 @@ impl SystemParameters {
     /// ⚠️ [DEPRECATED]: 仅用于开发或测试环境
     /// 这里的“证伪性”在于强制的安全参数检查。
     pub fn from_random_seed(seed_bytes: &[u8], bit_size: u32) -> Self {
-        // [FALSIFIABILITY POINT 1]: 安全参数下限检查
-        // 如果用户试图使用弱加密参数（例如为了性能牺牲安全性），
-        // 系统将直接熔断（Panic），拒绝不安全的启动。
-        if bit_size < 2048 {
-             panic!("❌ SECURITY VIOLATION: Discriminant size must be >= 2048 bits (Recommended 3072). System Halted.");
-        }
+        Self::enforce_security_floor(bit_size);
         
         println!("[System] ⚠️ WARNING: Using interactive seed setup. NOT SECURE for production.");
         Self::generate_internal(seed_bytes, bit_size)
     }
 
+    /// [FALSIFIABILITY POINT 1]: 安全参数下限检查
+    /// 如果用户试图使用弱加密参数（例如为了性能牺牲安全性），
+    /// 系统将直接熔断（Panic），拒绝不安全的启动。
+    fn enforce_security_floor(bit_size: u32) {
+        if bit_size < TEST_FLOOR_DISCRIMINANT_BITS {
+             panic!("❌ SECURITY VIOLATION: Discriminant size must be >= 2048 bits (Recommended 3072). System Halted.");
+        }
+    }
+
     /// 🛡️ [THEORETICAL OPTIMUM]: 无信参数生成协议 (Trustless Setup)
     /// 这是生产环境的标准入口。
+    ///
+    /// `bits`: 目标位宽，`None` 即 `MIN_DISCRIMINANT_BITS` (3072)。
+    /// ⚠️ [TEST ONLY]: 任何低于 3072 的取值都只能用于测试（加速集成测试），
+    /// 绝对不能用于生产环境！低于 2048 将直接熔断。
     pub fn derive_trustless_discriminant(
         beacon_block_hash: &[u8], 
         vdf_output: &[u8],      
-        vdf_proof: &[u8]        
+        vdf_proof: &[u8],
+        bits: Option<u32>
     ) -> Result<Self, String> {
+        Self::derive_trustless_discriminant_with(beacon_block_hash, vdf_output, vdf_proof, bits, &DefaultVdfVerifier)
+    }
+
+    /// 同 `derive_trustless_discriminant`，但使用调用者提供的 VDF 验证器。
+    pub fn derive_trustless_discriminant_with(
+        beacon_block_hash: &[u8], 
+        vdf_output: &[u8],      
+        vdf_proof: &[u8],
+        bits: Option<u32>,
+        verifier: &dyn VdfVerifier
+    ) -> Result<Self, String> {
+        let bit_size = bits.unwrap_or(MIN_DISCRIMINANT_BITS);
+        Self::enforce_security_floor(bit_size);
+
         println!("[System] Initiating Trustless Setup Protocol...");
-        println!("[System] Target Security Level: {} bits", MIN_DISCRIMINANT_BITS);
+        println!("[System] Target Security Level: {} bits", bit_size);
+        if bit_size < MIN_DISCRIMINANT_BITS {
+            println!("[System] ⚠️ WARNING: {} bits is below {} — TEST ONLY, NOT SECURE for production.", bit_size, MIN_DISCRIMINANT_BITS);
+        }
 
         // [FALSIFIABILITY POINT 2]: VDF 验证
         // 如果无法数学证明该参数经过了不可压缩的时间计算（即可能被预计算或操纵），
         // 函数返回 Error，上层调用者必须终止流程。
-        if !Self::verify_vdf(beacon_block_hash, vdf_output, vdf_proof) {
+        if !Self::verify_vdf(beacon_block_hash, vdf_output, vdf_proof, verifier) {
             return Err("❌ FATAL: VDF Proof Invalid. The randomness source may be manipulated.".to_string());
         }
 
@@ -68,8 +143,8 @@ impl SystemParameters {
         let final_seed = hasher.finalize();
 
         // 3. [Step 3]: 生成基本判别式
-        // 这里必须使用系统定义的最小安全位宽
-        let params = Self::generate_internal(final_seed.as_bytes(), MIN_DISCRIMINANT_BITS);
+        // 生产环境必须使用系统定义的最小安全位宽 (bits = None)
+        let params = Self::generate_internal(final_seed.as_bytes(), bit_size);
         
         Ok(params)
     }
@@ -78,97 +153,141 @@ impl SystemParameters {
     /// [SECURITY UPGRADE]: 使用 XOF 确保全位宽熵覆盖
     fn generate_internal(seed_bytes: &[u8], bit_size: u32) -> Self {
         println!("[System] Deriving Fundamental Discriminant (Full Entropy Mode)...");
-        
-        let mut attempt = 0;
-        // 设置一个极高的上限，防止无限死循环，但如果在这么多次尝试后仍失败，
-        // 说明熵源有问题或系统正处于极度异常的状态。
-        let max_attempts = 10_000_000; 
 
+        #[cfg(feature = "parallel")]
+        let found = Self::search_parallel(seed_bytes, bit_size);
+        #[cfg(not(feature = "parallel"))]
+        let found = Self::search_serial(seed_bytes, bit_size);
+
+        // [FALSIFIABILITY POINT 3]: 熵池耗尽 / 生成超时
+        // 防止进程陷入死锁状态 (CPU DoS)。
+        let (attempt, candidate) = match found {
+            Some(hit) => hit,
+            None => panic!("❌ Failed to generate System Parameters. Entropy pool exhausted or bad luck. System Halted."),
+        };
+
+        let discriminant = -candidate;
+        println!("✅ [Trustless Setup] Success! Found Fundamental Discriminant.");
+        println!("   Delta Fingerprint: ...{:X} (Last 64 bits)", discriminant.clone() % Integer::from(1u64 << 64));
+        println!("   Attempts: {}", attempt);
+        SystemParameters { discriminant }
+    }
+
+    /// 串行搜索：按 nonce 递增逐个测试
+    #[cfg_attr(feature = "parallel", allow(dead_code))]
+    fn search_serial(seed_bytes: &[u8], bit_size: u32) -> Option<(u32, Integer)> {
+        (0..=MAX_DISCRIMINANT_ATTEMPTS).find_map(|attempt| {
+            Self::candidate_at(seed_bytes, bit_size, attempt).map(|c| (attempt, c))
+        })
+    }
+
+    /// 并行搜索：rayon 工作线程测试互不相交的 nonce 区间。
+    /// `find_map_first` 保证返回**最小**的成功 nonce（而不是最先完成的线程），
+    /// 并在找到后取消更高 nonce 的兄弟任务，因此结果与串行路径完全一致。
+    #[cfg(feature = "parallel")]
+    fn search_parallel(seed_bytes: &[u8], bit_size: u32) -> Option<(u32, Integer)> {
+        use rayon::prelude::*;
+
+        (0..=MAX_DISCRIMINANT_ATTEMPTS).into_par_iter().find_map_first(|attempt| {
+            Self::candidate_at(seed_bytes, bit_size, attempt).map(|c| (attempt, c))
+        })
+    }
+
+    /// 测试单个 nonce：若派生出的 M 满足基本判别式条件则返回 M (Δ = -M)
+    fn candidate_at(seed_bytes: &[u8], bit_size: u32, attempt: u32) -> Option<Integer> {
         // 计算需要的字节数 (向上取整)
         let num_bytes = ((bit_size + 7) / 8) as usize;
 
-        loop {
-            // [FALSIFIABILITY POINT 3]: 熵池耗尽 / 生成超时
-            // 防止进程陷入死锁状态 (CPU DoS)。
-            if attempt > max_attempts {
-                panic!("❌ Failed to generate System Parameters. Entropy pool exhausted or bad luck. System Halted.");
-            }
-
-            // 1. CSPRNG 扩展: 使用 BLAKE3 XOF 模式
-            // 这确保了生成的 candidate 每一个比特都是由种子派生的，具有 3072-bit 级别的真实熵
-            let mut hasher = Hasher::new();
-            hasher.update(seed_bytes);
-            hasher.update(b"::NONCE::");
-            hasher.update(&attempt.to_le_bytes()); 
-            
-            // [CRITICAL FIX]: 使用 finalize_xof 填充整个缓冲区，而不是 finalize() 仅取前 32 字节
-            let mut entropy_buffer = vec![0u8; num_bytes];
-            let mut output_reader = hasher.finalize_xof();
-            output_reader.fill(&mut entropy_buffer);
-
-            // 2. 构造候选大整数
-            let mut candidate = Integer::from_digits(&entropy_buffer, rug::integer::Order::Lsf);
-            
-            // 确保高位为1，严格保证位宽安全性
-            candidate.set_bit(bit_size - 1, true);
-            
-            // 3. 基本判别式筛选条件 (Fundamental Discriminant Criteria)
-            // 定义 Delta = -M
-            // 要求 M = 3 mod 4 (从而导致 Delta = 1 mod 4)
-            // 且 M 必须是无平方因子的 (Square-free)。若 M 为素数，则自动满足。
-            let rem = candidate.mod_u(4);
-            if rem != 3 {
-                attempt += 1;
-                continue;
-            }
+        // 1. CSPRNG 扩展: 使用 BLAKE3 XOF 模式
+        // 这确保了生成的 candidate 每一个比特都是由种子派生的，具有 3072-bit 级别的真实熵
+        let mut hasher = Hasher::new();
+        hasher.update(seed_bytes);
+        hasher.update(b"::NONCE::");
+        hasher.update(&attempt.to_le_bytes()); 
+        
+        // [CRITICAL FIX]: 使用 finalize_xof 填充整个缓冲区，而不是 finalize() 仅取前 32 字节
+        let mut entropy_buffer = vec![0u8; num_bytes];
+        let mut output_reader = hasher.finalize_xof();
+        output_reader.fill(&mut entropy_buffer);
 
-            // 4. 强素性测试 (Miller-Rabin)
-            // 这是概率性测试，但对于加密应用来说，50轮测试的误判率可以忽略不计
-            if candidate.is_probably_prime(50) != rug::integer::IsPrime::No {
-                let discriminant = -candidate;
-                println!("✅ [Trustless Setup] Success! Found Fundamental Discriminant.");
-                println!("   Delta Fingerprint: ...{:X} (Last 64 bits)", discriminant.clone() % Integer::from(1u64 << 64));
-                println!("   Attempts: {}", attempt);
-                return SystemParameters { discriminant };
-            }
+        // 2. 构造候选大整数
+        let mut candidate = Integer::from_digits(&entropy_buffer, rug::integer::Order::Lsf);
+        
+        // 确保高位为1，严格保证位宽安全性
+        candidate.set_bit(bit_size - 1, true);
+        
+        // 3. 基本判别式筛选条件 (Fundamental Discriminant Criteria)
+        // 定义 Delta = -M
+        // 要求 M = 3 mod 4 (从而导致 Delta = 1 mod 4)
+        // 且 M 必须是无平方因子的 (Square-free)。若 M 为素数，则自动满足。
+        if candidate.mod_u(4) != 3 {
+            return None;
+        }
 
-            attempt += 1;
+        // 4. 强素性测试 (Miller-Rabin)
+        // 这是概率性测试，但对于加密应用来说，50轮测试的误判率可以忽略不计
+        if candidate.is_probably_prime(50) != rug::integer::IsPrime::No {
+            Some(candidate)
+        } else {
+            None
         }
     }
 
-    fn verify_vdf(input: &[u8], output: &[u8], proof: &[u8]) -> bool {
+    fn verify_vdf(input: &[u8], output: &[u8], proof: &[u8], verifier: &dyn VdfVerifier) -> bool {
         // 基本的完整性检查
         if input.is_empty() || output.is_empty() || proof.is_empty() {
             eprintln!("[VDF Verify] ❌ Security Alert: Empty payload detected.");
             return false;
         }
 
-        #[cfg(feature = "production_vdf")]
-        {
-            match vdf_verify(input, output, proof, VDF_TIME_PARAM_T) {
-                Ok(true) => return true,
-                Ok(false) => {
-                    eprintln!("[VDF Verify] ❌ Mathematical verification failed.");
-                    return false;
-                },
-                Err(e) => {
-                    eprintln!("[VDF Verify] ❌ Verification error: {:?}", e);
-                    return false;
-                }
-            }
-        }
+        verifier.verify(input, output, proof, VDF_TIME_PARAM_T)
+    }
+}
 
-        #[cfg(not(feature = "production_vdf"))]
-        {
-            // 在非生产环境下，我们模拟 VDF 验证
-            // 注意：这仅用于单元测试，绝对不能用于主网
-            println!("[VDF Verify] ⚠️ WARNING: Running in MOCK mode. Not secure for mainnet.");
-            let mut hasher = Hasher::new();
-            hasher.update(b"EVOLVER_VDF_SIMULATION_BINDING");
-            hasher.update(input);
-            hasher.update(output);
-            let expected_proof_hash = hasher.finalize();
-            proof == expected_proof_hash.as_bytes()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StubVerifier(bool);
+
+    impl VdfVerifier for StubVerifier {
+        fn verify(&self, _: &[u8], _: &[u8], _: &[u8], t: u64) -> bool {
+            assert_eq!(t, VDF_TIME_PARAM_T);
+            self.0
         }
     }
+
+    #[test]
+    fn test_invalid_vdf_is_rejected() {
+        let res = SystemParameters::derive_trustless_discriminant_with(b"beacon", b"out", b"proof", Some(2048), &StubVerifier(false));
+        assert!(res.is_err());
+
+        // 空负载在调用验证器之前即被拒绝
+        let res = SystemParameters::derive_trustless_discriminant_with(b"beacon", b"out", b"", Some(2048), &StubVerifier(true));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "SECURITY VIOLATION")]
+    fn test_bits_below_floor_panics() {
+        let _ = SystemParameters::derive_trustless_discriminant_with(b"beacon", b"out", b"proof", Some(1024), &StubVerifier(true));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_search_matches_serial() {
+        let seed = b"fixed-seed-for-discriminant-search";
+        let serial = SystemParameters::search_serial(seed, 256).unwrap();
+        let parallel = SystemParameters::search_parallel(seed, 256).unwrap();
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn test_candidate_is_fundamental_shape() {
+        let (_, m) = SystemParameters::search_serial(b"shape", 256).unwrap();
+        let delta = SystemParameters::generate_internal(b"shape", 256).discriminant;
+        assert_eq!(m.mod_u(4), 3);
+        assert_eq!(m.significant_bits(), 256);
+        assert_eq!(delta, -m);
+    }
 }
@@ -2,43 +2,113 @@
 
 use rug::Integer;
 use blake3::Hasher;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// 默认 Miller-Rabin 轮数
+pub const DEFAULT_MR_ROUNDS: u32 = 25;
+
+/// 概率性哈希试探的 nonce 上限，耗尽后进入确定性保底扫描
+const OPTIMAL_SEARCH_LIMIT: u64 = 1000;
+
+/// 试除轮 (Wheel): 7 到 256 之间的全部素数
+/// 在 Miller-Rabin 之前廉价地剔除明显的合数。3 与 5 由原有的筛单独处理。
+const SMALL_PRIMES: [u32; 51] = [
+    7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67,
+    71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131, 137, 139, 149,
+    151, 157, 163, 167, 173, 179, 181, 191, 193, 197, 199, 211, 223, 227, 229, 233,
+    239, 241, 251,
+];
+
+#[cfg(test)]
+thread_local! {
+    /// 测试用: 统计 Miller-Rabin 的调用次数
+    static MR_CALLS: std::cell::Cell<usize> = std::cell::Cell::new(0);
+}
+
+/// 📜 PrimeProvenance: 素数的来源，供审计与监控使用
+/// 保底扫描 (`next_prime`) 的分布与哈希试探不同，偏向素数间隙较大的素数。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrimeProvenance {
+    /// 命中的 nonce；来自保底扫描时为 None
+    pub nonce: Option<u64>,
+    pub used_fallback: bool,
+}
+
+/// 素数缓存的容量上限，超出时整体清空
+/// 键来自任意调用方字符串 (包括词表碰撞重试)，不设上限会无限增长。
+const PRIME_CACHE_CAPACITY: usize = 4096;
+
+type PrimeKey = (String, u32);
+
+/// [Memo]: (user_id, bit_size) -> prime 的进程级缓存
+/// 映射是确定性的，因此缓存结果与重新计算完全一致。
+fn prime_cache() -> &'static Mutex<HashMap<PrimeKey, Integer>> {
+    static CACHE: OnceLock<Mutex<HashMap<PrimeKey, Integer>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 写入缓存；已满时先清空，保证条目数不超过 `PRIME_CACHE_CAPACITY`
+fn remember_prime(cache: &mut HashMap<PrimeKey, Integer>, key: PrimeKey, prime: Integer) {
+    if cache.len() >= PRIME_CACHE_CAPACITY {
+        cache.clear();
+    }
+    cache.insert(key, prime);
+}
 
 /// 🛡️ Hash-to-Prime Map (With XOF Full Entropy)
 /// 将任意字符串确定性地映射为一个大素数。
 /// 
 /// [SECURITY UPDATE]: 启用了 BLAKE3 XOF，确保生成的素数在整个 `bit_size` 空间内均匀分布。
+/// [Perf]: 结果按 `(user_id, bit_size)` 缓存，重复嵌入同一 Token 为 O(1) 查表。
 pub fn hash_to_prime(user_id: &str, bit_size: u32) -> Result<Integer, String> {
+    let key = (user_id.to_string(), bit_size);
+
+    if let Ok(cache) = prime_cache().lock() {
+        if let Some(p) = cache.get(&key) {
+            return Ok(p.clone());
+        }
+    }
+
+    let prime = hash_to_prime_with_rounds(user_id, bit_size, DEFAULT_MR_ROUNDS)?;
+
+    if let Ok(mut cache) = prime_cache().lock() {
+        remember_prime(&mut cache, key, prime.clone());
+    }
+    Ok(prime)
+}
+
+/// 未缓存版本，可指定 Miller-Rabin 轮数。
+/// 轮数越少越快，但误判合数的概率上界为 4^(-rounds)。
+pub fn hash_to_prime_with_rounds(user_id: &str, bit_size: u32, rounds: u32) -> Result<Integer, String> {
+    hash_to_prime_search(user_id, bit_size, rounds, OPTIMAL_SEARCH_LIMIT).map(|(prime, _)| prime)
+}
+
+/// 🔍 可审计版本：同时返回素数的来源 (命中的 nonce，或是否走了保底扫描)
+/// 结果与 `hash_to_prime` 完全相同，但不经过缓存。
+pub fn hash_to_prime_audited(user_id: &str, bit_size: u32) -> Result<(Integer, PrimeProvenance), String> {
+    hash_to_prime_search(user_id, bit_size, DEFAULT_MR_ROUNDS, OPTIMAL_SEARCH_LIMIT)
+}
+
+fn hash_to_prime_search(user_id: &str, bit_size: u32, rounds: u32, search_limit: u64) -> Result<(Integer, PrimeProvenance), String> {
     let mut nonce = 0u64;
-    let optimal_search_limit = 1000; 
     let num_bytes = ((bit_size + 7) / 8) as usize;
     
     // --- Phase 1: 概率性哈希试探 (XOF Enabled) ---
-    while nonce < optimal_search_limit {
-        let mut hasher = Hasher::new();
-        hasher.update(&(user_id.len() as u64).to_le_bytes());
-        hasher.update(user_id.as_bytes());
-        hasher.update(&nonce.to_le_bytes());
-        
-        // [CRITICAL FIX]: 使用 XOF 填满整个缓冲区
-        let mut entropy_buffer = vec![0u8; num_bytes];
-        let mut output_reader = hasher.finalize_xof();
-        output_reader.fill(&mut entropy_buffer);
-
-        let mut candidate = Integer::from_digits(&entropy_buffer, rug::integer::Order::Lsf);
-        
-        // 强制设置最高位和最低位
-        candidate.set_bit(bit_size - 1, true);
-        candidate.set_bit(0, true);
+    while nonce < search_limit {
+        let candidate = probe_candidate(user_id, bit_size, num_bytes, nonce);
 
         // 小素数筛
-        if candidate.mod_u(3) == 0 || candidate.mod_u(5) == 0 {
+        if candidate.mod_u(3) == 0 || candidate.mod_u(5) == 0 || has_small_factor(&candidate) {
             nonce += 1;
             continue;
         }
 
         // Miller-Rabin
-        if candidate.is_probably_prime(25) != rug::integer::IsPrime::No {
-            return Ok(candidate);
+        #[cfg(test)]
+        MR_CALLS.with(|c| c.set(c.get() + 1));
+        if candidate.is_probably_prime(rounds) != rug::integer::IsPrime::No {
+            return Ok((candidate, PrimeProvenance { nonce: Some(nonce), used_fallback: false }));
         }
 
         nonce += 1;
@@ -56,10 +126,197 @@ pub fn hash_to_prime(user_id: &str, bit_size: u32) -> Result<Integer, String> {
     output_reader.fill(&mut entropy_buffer);
     
     let mut fallback_candidate = Integer::from_digits(&entropy_buffer, rug::integer::Order::Lsf);
+    fallback_candidate.keep_bits_mut(bit_size);
     fallback_candidate.set_bit(bit_size - 1, true);
     fallback_candidate.set_bit(0, true);
 
     fallback_candidate.next_prime_mut();
 
-    Ok(fallback_candidate)
+    Ok((fallback_candidate, PrimeProvenance { nonce: None, used_fallback: true }))
+}
+
+/// 第 `nonce` 次试探的候选数：XOF 填满 `num_bytes`，截断到 `bit_size` 位，再强制最高位与最低位
+fn probe_candidate(user_id: &str, bit_size: u32, num_bytes: usize, nonce: u64) -> Integer {
+    let mut hasher = Hasher::new();
+    hasher.update(&(user_id.len() as u64).to_le_bytes());
+    hasher.update(user_id.as_bytes());
+    hasher.update(&nonce.to_le_bytes());
+
+    // [CRITICAL FIX]: 使用 XOF 填满整个缓冲区
+    let mut entropy_buffer = vec![0u8; num_bytes];
+    let mut output_reader = hasher.finalize_xof();
+    output_reader.fill(&mut entropy_buffer);
+
+    let mut candidate = Integer::from_digits(&entropy_buffer, rug::integer::Order::Lsf);
+    // `bit_size` 非 8 的倍数时，整字节填充会多出高位，必须截掉
+    candidate.keep_bits_mut(bit_size);
+
+    // 强制设置最高位和最低位
+    candidate.set_bit(bit_size - 1, true);
+    candidate.set_bit(0, true);
+    candidate
+}
+
+/// 试除轮：存在真小素因子 (即候选本身不是该小素数) 时返回 true
+/// 被剔除的候选必然是合数，因此不会改变最终选出的素数。
+fn has_small_factor(candidate: &Integer) -> bool {
+    SMALL_PRIMES.iter().any(|&p| candidate.mod_u(p) == 0 && *candidate != p)
+}
+
+/// 🔐 Hash-to-Safe-Prime Map
+/// 确定性地生成安全素数 p = 2q + 1 (q 亦为素数)，用于作为指数的神经元权重，
+/// 避免小子群问题。
+///
+/// [PERF WARNING]: 安全素数密度约为 1/(ln p)^2，远比普通素数稀疏，
+/// 生成速度明显慢于 `hash_to_prime`，且结果不做缓存。
+pub fn hash_to_safe_prime(user_id: &str, bit_size: u32) -> Result<Integer, String> {
+    if bit_size < 3 {
+        return Err(format!("Safe prime requires at least 3 bits, got {}", bit_size));
+    }
+    let q_bits = bit_size - 1;
+    let num_bytes = ((q_bits + 7) / 8) as usize;
+
+    // XOF 熵源：为 Sophie Germain 素数 q 选取起点
+    let mut hasher = Hasher::new();
+    hasher.update(b"HTP_SAFE_PRIME_V1::");
+    hasher.update(&(user_id.len() as u64).to_le_bytes());
+    hasher.update(user_id.as_bytes());
+
+    let mut entropy_buffer = vec![0u8; num_bytes];
+    let mut output_reader = hasher.finalize_xof();
+    output_reader.fill(&mut entropy_buffer);
+
+    let mut q = Integer::from_digits(&entropy_buffer, rug::integer::Order::Lsf);
+    q.keep_bits_mut(q_bits);
+    q.set_bit(q_bits - 1, true);
+
+    let mut wrapped = false;
+    loop {
+        q.next_prime_mut();
+
+        // 越过 q_bits 范围：从区间下界重新扫描一次
+        if q.significant_bits() > q_bits {
+            if wrapped {
+                return Err(format!("No safe prime found in {}-bit range", bit_size));
+            }
+            wrapped = true;
+            q = (Integer::from(1) << (q_bits - 1)) - 1u32;
+            continue;
+        }
+
+        // q ≡ 1 (mod 3) ⇒ 3 | 2q + 1
+        if q.mod_u(3) == 1 && q != 3 {
+            continue;
+        }
+
+        let p = Integer::from(&q << 1) + 1u32;
+        if p.is_probably_prime(DEFAULT_MR_ROUNDS) != rug::integer::IsPrime::No {
+            return Ok(p);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_and_uncached_agree() {
+        for id in ["tok_0", "tok_1", "tok_42"] {
+            let uncached = hash_to_prime_with_rounds(id, 128, DEFAULT_MR_ROUNDS).unwrap();
+            let first = hash_to_prime(id, 128).unwrap();
+            let second = hash_to_prime(id, 128).unwrap(); // 命中缓存
+            assert_eq!(first, uncached);
+            assert_eq!(second, uncached);
+        }
+        // bit_size 是缓存键的一部分
+        assert_ne!(hash_to_prime("tok_0", 64).unwrap(), hash_to_prime("tok_0", 128).unwrap());
+    }
+
+    #[test]
+    fn test_prime_cache_is_bounded() {
+        let mut cache = HashMap::new();
+        for i in 0..PRIME_CACHE_CAPACITY + 10 {
+            remember_prime(&mut cache, (format!("tok_{}", i), 64), Integer::from(i));
+            assert!(cache.len() <= PRIME_CACHE_CAPACITY);
+        }
+        // 溢出后从空表重新开始，最新条目始终可查
+        assert_eq!(cache.len(), 10);
+        let last = format!("tok_{}", PRIME_CACHE_CAPACITY + 9);
+        assert_eq!(cache[&(last, 64)], Integer::from(PRIME_CACHE_CAPACITY + 9));
+    }
+
+    #[test]
+    fn test_audited_reports_provenance() {
+        let (prime, provenance) = hash_to_prime_audited("tok_7", 128).unwrap();
+        assert_eq!(prime, hash_to_prime("tok_7", 128).unwrap());
+        assert!(!provenance.used_fallback);
+        assert!(provenance.nonce.unwrap() < OPTIMAL_SEARCH_LIMIT);
+
+        // 试探上限为 0：强制进入保底扫描
+        let (fallback, provenance) = hash_to_prime_search("tok_7", 128, DEFAULT_MR_ROUNDS, 0).unwrap();
+        assert_eq!(provenance, PrimeProvenance { nonce: None, used_fallback: true });
+        assert_eq!(fallback.significant_bits(), 128);
+        assert_ne!(fallback.is_probably_prime(DEFAULT_MR_ROUNDS), rug::integer::IsPrime::No);
+    }
+
+    #[test]
+    fn test_primes_respect_non_byte_aligned_widths() {
+        for bits in [12u32, 20, 61] {
+            for id in ["tok_0", "tok_1", "tok_42"] {
+                let (prime, provenance) = hash_to_prime_audited(id, bits).unwrap();
+                assert!(!provenance.used_fallback);
+                assert_eq!(prime.significant_bits(), bits, "{} @ {} bits", id, bits);
+            }
+        }
+    }
+
+    #[test]
+    fn test_wheel_skips_miller_rabin_without_changing_output() {
+        // 参照实现：只筛 3 和 5 (加入试除轮之前的行为)
+        fn legacy(user_id: &str, bit_size: u32) -> (Integer, usize) {
+            let num_bytes = ((bit_size + 7) / 8) as usize;
+            let mut mr_calls = 0;
+            for nonce in 0..OPTIMAL_SEARCH_LIMIT {
+                let candidate = probe_candidate(user_id, bit_size, num_bytes, nonce);
+                if candidate.mod_u(3) == 0 || candidate.mod_u(5) == 0 {
+                    continue;
+                }
+                mr_calls += 1;
+                if candidate.is_probably_prime(DEFAULT_MR_ROUNDS) != rug::integer::IsPrime::No {
+                    return (candidate, mr_calls);
+                }
+            }
+            panic!("legacy search fell back");
+        }
+
+        let (mut before, mut after) = (0, 0);
+        for i in 0..32 {
+            let id = format!("wheel_{}", i);
+            let (expected, calls) = legacy(&id, 256);
+            before += calls;
+
+            MR_CALLS.with(|c| c.set(0));
+            assert_eq!(hash_to_prime_with_rounds(&id, 256, DEFAULT_MR_ROUNDS).unwrap(), expected);
+            after += MR_CALLS.with(|c| c.get());
+        }
+        assert!(after < before, "Miller-Rabin calls: {} with wheel, {} without", after, before);
+
+        // 候选本身是小素数时不被试除轮误杀
+        assert!(!has_small_factor(&Integer::from(251)));
+        assert!(has_small_factor(&Integer::from(7 * 251)));
+    }
+
+    #[test]
+    fn test_safe_prime() {
+        let p = hash_to_safe_prime("neuron_weight", 64).unwrap();
+        let q = Integer::from(&p - 1u32) >> 1;
+
+        assert_eq!(p.significant_bits(), 64);
+        assert_ne!(p.is_probably_prime(DEFAULT_MR_ROUNDS), rug::integer::IsPrime::No);
+        assert_ne!(q.is_probably_prime(DEFAULT_MR_ROUNDS), rug::integer::IsPrime::No);
+
+        // 确定性
+        assert_eq!(hash_to_safe_prime("neuron_weight", 64).unwrap(), p);
+    }
 }
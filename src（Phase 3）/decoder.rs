@@ -1,14 +1,16 @@
 // COPYRIGHT (C) 2025 M-Patek. ALL RIGHTS RESERVED.
 
 use crate::phase3::core::affine::AffineTuple;
+use crate::phase3::core::algebra::ClassGroupElement;
 use crate::phase3::core::primes::hash_to_prime;
 use crate::phase3::topology::tensor::Coordinate; 
 use rug::Integer;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 /// [Optimization]: K-D Tree Node
 /// 用于加速高维空间最近邻搜索的数据结构
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct KdNode {
     pub point: Coordinate,
     pub left: Option<Box<KdNode>>,
@@ -16,6 +18,9 @@ pub struct KdNode {
     pub axis: usize,
 }
 
+/// Token 素数指纹的默认位宽
+pub const DEFAULT_TOKEN_PRIME_BITS: u32 = 64;
+
 /// 🗺️ VocabularyTensor: 静态词汇宇宙 (The Atlas)
 /// 存储了 Token 在超空间中的确切位置。
 pub struct VocabularyTensor {
@@ -29,15 +34,22 @@ pub struct VocabularyTensor {
     
     pub dimensions: usize,
     pub side_length: usize,
+
+    /// Token 素数指纹位宽 (越大越不易碰撞)
+    pub prime_bits: u32,
+    /// [Telemetry]: 构建过程中触发 `collision_fix_` 重试的总次数
+    pub collision_retries: u64,
 }
 
 impl VocabularyTensor {
-    pub fn new(vocab_size: u32, dimensions: usize, side_length: usize) -> Self {
+    /// `prime_bits` 为 Token 素数指纹位宽，通常取 `DEFAULT_TOKEN_PRIME_BITS`
+    pub fn new(vocab_size: u32, dimensions: usize, side_length: usize, prime_bits: u32) -> Self {
         let mut star_map = HashMap::new();
         let mut prime_to_id = HashMap::new();
         let mut points_for_tree = Vec::new();
         
         let mut occupied_primes: HashSet<Integer> = HashSet::new();
+        let mut collision_retries = 0u64;
         let l = side_length as u64;
         
         // 初始化宇宙：将所有 Token 映射到空间中
@@ -51,7 +63,8 @@ impl VocabularyTensor {
 
             // [DCAP Algorithm]: 生成绝对唯一的 Token Prime
             let base_token_str = format!("tok_{}", tid);
-            let p = Self::generate_unique_prime(&base_token_str, &occupied_primes);
+            let (p, retries) = Self::generate_unique_prime(&base_token_str, &occupied_primes, prime_bits);
+            collision_retries += retries;
             
             occupied_primes.insert(p.clone());
             star_map.insert(coord.clone(), p.clone());
@@ -68,6 +81,8 @@ impl VocabularyTensor {
             kd_tree,
             dimensions,
             side_length,
+            prime_bits,
+            collision_retries,
         }
     }
 
@@ -89,8 +104,8 @@ impl VocabularyTensor {
 
 
     /// 🛡️ [FALSIFIABILITY BOUNDARY B2]: Vocabulary Space Exhausted
-    /// 确保语义指纹的绝对唯一性。
-    fn generate_unique_prime(base_str: &str, occupied: &HashSet<Integer>) -> Integer {
+    /// 确保语义指纹的绝对唯一性。返回 (素数, 碰撞重试次数)。
+    fn generate_unique_prime(base_str: &str, occupied: &HashSet<Integer>, prime_bits: u32) -> (Integer, u64) {
         let mut nonce = 0u64;
         const MAX_COLLISION_RETRIES: u64 = 1_000_000;
 
@@ -101,9 +116,9 @@ impl VocabularyTensor {
                 format!("{}#collision_fix_{}", base_str, nonce)
             };
 
-            if let Ok(candidate) = hash_to_prime(&input_str, 64) {
+            if let Ok(candidate) = hash_to_prime(&input_str, prime_bits) {
                 if !occupied.contains(&candidate) {
-                    return candidate;
+                    return (candidate, nonce);
                 }
             }
             nonce += 1;
@@ -112,11 +127,14 @@ impl VocabularyTensor {
         panic!("❌ Fatal Error: Vocabulary Space Exhausted. Unable to assign unique prime fingerprint.");
     }
 
+    /// 🌲 K-D Tree 构建
+    /// 同一切分轴上的取值相同时按完整坐标字典序决胜，使树的形状只取决于点集本身，
+    /// 与输入顺序无关 (重复坐标也会落到确定的位置)，从而保证重建后的解码结果逐位一致。
     fn build_kdtree(points: &mut [Coordinate], depth: usize, k: usize) -> Option<Box<KdNode>> {
         if points.is_empty() { return None; }
 
         let axis = depth % k;
-        points.sort_by(|a, b| a[axis].cmp(&b[axis]));
+        points.sort_by(|a, b| a[axis].cmp(&b[axis]).then_with(|| a.cmp(b)));
         let mid = points.len() / 2;
 
         let point = points[mid].clone();
@@ -132,10 +150,67 @@ impl VocabularyTensor {
     }
 }
 
+/// 置信度混合权重: 逆漂移 vs 超度量 CPL (与进化引擎的 fitness 权重一致)
+pub const CONFIDENCE_DRIFT_WEIGHT: f64 = 0.7;
+pub const CONFIDENCE_CPL_WEIGHT: f64 = 0.3;
+
 /// 解码结果
+#[derive(Debug)]
 pub struct DecodeResult {
     pub token_id: u32,
     pub drift: usize, // 曼哈顿漂移量
+    /// 归一化置信度 [0, 1]，漂移越小、CPL 越长越高
+    pub confidence: f64,
+}
+
+impl DecodeResult {
+    /// 🎯 置信度混合: 1/(1+drift)，若有参考坐标则再与 CPL/20 加权混合
+    pub fn blend_confidence(drift: usize, cpl: Option<u32>) -> f64 {
+        let inverse_drift = 1.0 / (1.0 + drift as f64);
+        match cpl {
+            Some(cpl) => {
+                let cpl_score = (cpl.min(20) as f64) / 20.0;
+                inverse_drift * CONFIDENCE_DRIFT_WEIGHT + cpl_score * CONFIDENCE_CPL_WEIGHT
+            }
+            None => inverse_drift,
+        }
+    }
+
+    /// 零漂移的精确命中
+    pub fn is_exact(&self) -> bool {
+        self.drift == 0
+    }
+}
+
+/// 🧭 DecodeError: 解码失败的原因
+/// 区分 "根本没有候选" 与 "最近的候选刚好在半径之外"，后者可用于自适应扩大搜索半径。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// 词表为空，K-D 树中没有任何候选
+    EmptyVocabulary,
+    /// 最近的候选位于 `search_radius` 之外 (`nearest_drift` 为其实际曼哈顿距离)
+    OutOfRadius { nearest_drift: usize, radius: usize },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::EmptyVocabulary => write!(f, "❌ Navigation Lost: Vocabulary is empty."),
+            DecodeError::OutOfRadius { nearest_drift, radius } => write!(
+                f,
+                "❌ Navigation Lost: No neighbors within radius {} (nearest at {}).",
+                radius, nearest_drift
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<DecodeError> for String {
+    fn from(e: DecodeError) -> Self {
+        e.to_string()
+    }
 }
 
 /// 🧭 InverseDecoder: 坐标导航器 (Phase 4 Upgraded)
@@ -149,43 +224,80 @@ pub struct InverseDecoder {
 impl InverseDecoder {
     pub fn new(vocab_size: u32) -> Self {
         InverseDecoder {
-            vocab_tensor: VocabularyTensor::new(vocab_size, 4, 32),
+            vocab_tensor: VocabularyTensor::new(vocab_size, 4, 32, DEFAULT_TOKEN_PRIME_BITS),
             search_radius: 5,
         }
     }
 
     /// 📍 Decode (Legacy): 仅用于兼容旧逻辑
-    pub fn decode(&self, target_root: &AffineTuple) -> Result<DecodeResult, String> {
-        self.decode_with_bias(target_root, &vec![0; self.vocab_tensor.dimensions])
+    pub fn decode(&self, target_root: &AffineTuple) -> Result<DecodeResult, DecodeError> {
+        self.decode_with_bias(target_root, &[])
     }
 
     /// 🚀 Decode with Bias (The VAPO Interface)
     /// 将 Bias 纳入观测链，使 fitness 能感知到 Bias 的微调。
     /// 这是解决 "Fatal Coupling" 的关键步骤：让优化器的动作 (Bias Mutation) 在观测端有响应。
-    pub fn decode_with_bias(&self, target_root: &AffineTuple, bias: &[usize]) -> Result<DecodeResult, String> {
+    pub fn decode_with_bias(&self, target_root: &AffineTuple, bias: &[usize]) -> Result<DecodeResult, DecodeError> {
+        self.decode_within(target_root, bias, self.search_radius)
+    }
+
+    /// 🔭 Adaptive Decode: 初始半径找不到邻居时，逐步放宽到 `max_radius` 为止
+    /// 返回首个成功的结果及实际使用的半径。`OutOfRadius` 携带最近候选的真实距离，
+    /// 因此只需一次重试即可直接跳到恰好够用的半径。
+    pub fn decode_with_bias_adaptive(
+        &self,
+        target_root: &AffineTuple,
+        bias: &[usize],
+        max_radius: usize,
+    ) -> Result<(DecodeResult, usize), DecodeError> {
+        let mut radius = self.search_radius;
+        loop {
+            match self.decode_within(target_root, bias, radius) {
+                Ok(result) => return Ok((result, radius)),
+                Err(DecodeError::OutOfRadius { nearest_drift, .. })
+                    if nearest_drift > radius && nearest_drift <= max_radius => radius = nearest_drift,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn decode_within(&self, target_root: &AffineTuple, bias: &[usize], radius: usize) -> Result<DecodeResult, DecodeError> {
         // 1. 原始代数投影 (Extract raw algebraic coordinate)
         let mut predicted_coord = self.extract_coordinate(target_root);
         
         // 2. 施加 Bias 校准 (Apply VAPO linear correction)
-        self.apply_bias_to_coord(&mut predicted_coord, bias);
+        // [Fast Path]: 空 / 全零 Bias 是热路径上的常见情形，直接跳过逐维模运算
+        if bias.iter().any(|&b| b != 0) {
+            self.apply_bias_to_coord(&mut predicted_coord, bias);
+        }
 
         // 3. 完美的零漂移匹配 (Exact Match)
         if let Some(token_prime) = self.vocab_tensor.star_map.get(&predicted_coord) {
              if let Some(&tid) = self.vocab_tensor.prime_to_id.get(token_prime) {
-                 return Ok(DecodeResult { token_id: tid, drift: 0 });
+                 return Ok(DecodeResult {
+                     token_id: tid,
+                     drift: 0,
+                     confidence: DecodeResult::blend_confidence(0, Some(20)),
+                 });
              }
         }
 
         // 4. KNN 鲁棒搜索 (Robust Search)
-        if let Some(nearest_coord) = self.find_nearest_neighbor_robust(&predicted_coord) {
-            let token_prime = self.vocab_tensor.star_map.get(&nearest_coord).unwrap();
-            let tid = self.vocab_tensor.prime_to_id.get(token_prime).unwrap();
-            
-            let drift = self.manhattan_distance(&predicted_coord, &nearest_coord);
-            return Ok(DecodeResult { token_id: *tid, drift });
+        let (nearest_coord, drift) = self.find_nearest_neighbor_robust(&predicted_coord)
+            .ok_or(DecodeError::EmptyVocabulary)?;
+        if drift > radius {
+            return Err(DecodeError::OutOfRadius { nearest_drift: drift, radius });
         }
 
-        Err(format!("❌ Navigation Lost: No neighbors within radius {}.", self.search_radius))
+        let token_prime = self.vocab_tensor.star_map.get(&nearest_coord).unwrap();
+        let tid = self.vocab_tensor.prime_to_id.get(token_prime).unwrap();
+        // 参考坐标: 命中的 Token 坐标
+        let cpl = self.ultrametric_cpl_20bits(&predicted_coord, &nearest_coord);
+        Ok(DecodeResult {
+            token_id: *tid,
+            drift,
+            confidence: DecodeResult::blend_confidence(drift, Some(cpl)),
+        })
     }
 
     /// 📏 [Ultrametric CPL]: Coarse-to-Fine Common Prefix Length
@@ -229,9 +341,13 @@ impl InverseDecoder {
 
     /// 🌀 Semantic Lattice Projection (代数晶格投影)
     pub fn extract_coordinate(&self, tuple: &AffineTuple) -> Coordinate {
-        let s = &tuple.q_shift; 
-        
-        let mut val = s.a.clone();
+        self.coord_of_element(&tuple.q_shift)
+    }
+
+    /// 📌 Element -> Coordinate: 直接投影任意类群元素 (不需要包装成 AffineTuple)
+    /// 用于可视化，例如绘制神经元 `semantic_root` 随时间的轨迹。
+    pub fn coord_of_element(&self, elem: &ClassGroupElement) -> Coordinate {
+        let mut val = elem.a.clone();
         let mut coord = Vec::new();
         
         let l = self.vocab_tensor.side_length as u64;
@@ -262,19 +378,16 @@ impl InverseDecoder {
     }
 
     /// 🔎 [Robust] K-D Tree Search
-    fn find_nearest_neighbor_robust(&self, target: &Coordinate) -> Option<Coordinate> {
+    /// 返回最近的 Token 坐标及其曼哈顿距离，不做半径过滤 (由调用方判定)。
+    fn find_nearest_neighbor_robust(&self, target: &Coordinate) -> Option<(Coordinate, usize)> {
         let mut best_dist = usize::MAX;
         let mut best_coord = None;
 
         if let Some(ref root) = self.vocab_tensor.kd_tree {
             self.search_kdtree_recursive(root, target, &mut best_dist, &mut best_coord);
         }
-        
-        if best_dist > self.search_radius {
-            return None;
-        }
 
-        best_coord
+        best_coord.map(|coord| (coord, best_dist))
     }
 
     fn search_kdtree_recursive(
@@ -320,3 +433,152 @@ impl InverseDecoder {
             .sum()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confidence_is_monotonic_in_drift() {
+        let decoder = InverseDecoder::new(8);
+        // 单位元 a = 1 投影到 [1, 0, 0, 0] (tid 1)，再用 Bias 沿第 1 维推开
+        let root = AffineTuple::identity(&Integer::from(-23));
+
+        let results: Vec<DecodeResult> = (0..3)
+            .map(|shift| decoder.decode_with_bias(&root, &[0, shift, 0, 0]).unwrap())
+            .collect();
+
+        for (shift, res) in results.iter().enumerate() {
+            assert_eq!(res.token_id, 1);
+            assert_eq!(res.drift, shift);
+        }
+        assert!((results[0].confidence - 1.0).abs() < 1e-12);
+        assert!(results[0].confidence > results[1].confidence);
+        assert!(results[1].confidence > results[2].confidence);
+
+        assert!(DecodeResult::blend_confidence(1, None) > DecodeResult::blend_confidence(2, None));
+    }
+
+    #[test]
+    fn test_exact_and_out_of_radius_are_distinguished() {
+        let decoder = InverseDecoder::new(8);
+        let root = AffineTuple::identity(&Integer::from(-23));
+
+        assert!(decoder.decode(&root).unwrap().is_exact());
+        let near = decoder.decode_with_bias(&root, &[0, 2, 0, 0]).unwrap();
+        assert!(!near.is_exact());
+
+        // [1, 6, 0, 0] 距离最近的 Token 为 6，刚好超出默认半径 5
+        let err = decoder.decode_with_bias(&root, &[0, 6, 0, 0]).unwrap_err();
+        assert_eq!(err, DecodeError::OutOfRadius { nearest_drift: 6, radius: 5 });
+
+        let empty = InverseDecoder {
+            vocab_tensor: VocabularyTensor::new(0, 4, 32, DEFAULT_TOKEN_PRIME_BITS),
+            search_radius: 5,
+        };
+        assert_eq!(empty.decode(&root).unwrap_err(), DecodeError::EmptyVocabulary);
+    }
+
+    #[test]
+    fn test_adaptive_radius_resolves_far_token() {
+        let mut decoder = InverseDecoder::new(8);
+        decoder.search_radius = 1;
+        let root = AffineTuple::identity(&Integer::from(-23));
+        // [1, 3, 0, 0] 距离最近的 Token ([1, 0, 0, 0]) 为 3
+        let bias = [0, 3, 0, 0];
+
+        assert_eq!(
+            decoder.decode_with_bias(&root, &bias).unwrap_err(),
+            DecodeError::OutOfRadius { nearest_drift: 3, radius: 1 }
+        );
+
+        let (res, radius) = decoder.decode_with_bias_adaptive(&root, &bias, 4).unwrap();
+        assert_eq!(radius, 3);
+        assert_eq!((res.token_id, res.drift), (1, 3));
+
+        // 已在初始半径内则不放宽
+        let (_, radius) = decoder.decode_with_bias_adaptive(&root, &[], 4).unwrap();
+        assert_eq!(radius, 1);
+
+        // 上限不足时保留原错误
+        assert_eq!(
+            decoder.decode_with_bias_adaptive(&root, &bias, 2).unwrap_err(),
+            DecodeError::OutOfRadius { nearest_drift: 3, radius: 1 }
+        );
+        assert_eq!(decoder.search_radius, 1);
+    }
+
+    #[test]
+    fn test_coord_of_element_matches_extract_coordinate() {
+        let decoder = InverseDecoder::new(8);
+        let d = -((Integer::from(1) << 61) - 1u32);
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..8 {
+            let elem = ClassGroupElement::random(&d, &mut rng).unwrap();
+            let wrapped = AffineTuple::checkpoint(elem.clone());
+            assert_eq!(decoder.coord_of_element(&elem), decoder.extract_coordinate(&wrapped));
+        }
+    }
+
+    #[test]
+    fn test_wider_primes_reduce_collision_retries() {
+        // 16-bit 区间内只有约 3000 个素数，256 个 Token 按生日界预计约 10 次碰撞；
+        // 64-bit 空间下碰撞概率可忽略
+        let narrow = VocabularyTensor::new(256, 4, 32, 16);
+        let wide = VocabularyTensor::new(256, 4, 32, DEFAULT_TOKEN_PRIME_BITS);
+
+        assert!(narrow.collision_retries > wide.collision_retries);
+        assert_eq!(wide.collision_retries, 0);
+
+        // 反向查找以完整 Integer 为键，不受位宽影响
+        for vocab in [&narrow, &wide] {
+            assert_eq!(vocab.prime_to_id.len(), 256);
+            assert!(vocab.prime_to_id.keys().all(|p| p.significant_bits() == vocab.prime_bits));
+            for (coord, prime) in &vocab.star_map {
+                let tid = vocab.prime_to_id[prime];
+                assert_eq!(&vocab.map_id_to_coord(tid as u64), coord);
+            }
+        }
+    }
+
+    #[test]
+    fn test_kdtree_is_canonical_across_input_orders() {
+        // 含重复坐标，且多个点在第 0 维上取值相同
+        let points: Vec<Coordinate> = vec![
+            vec![1, 3], vec![1, 0], vec![2, 2], vec![1, 3],
+            vec![0, 5], vec![2, 1], vec![1, 2], vec![0, 5],
+        ];
+        let mut forward = points.clone();
+        let mut reversed: Vec<Coordinate> = points.iter().rev().cloned().collect();
+
+        let a = VocabularyTensor::build_kdtree(&mut forward, 0, 2);
+        let b = VocabularyTensor::build_kdtree(&mut reversed, 0, 2);
+        assert!(a.is_some());
+        assert_eq!(a, b);
+
+        // 重建整个词表得到相同的树
+        let first = VocabularyTensor::new(16, 4, 32, DEFAULT_TOKEN_PRIME_BITS);
+        let second = VocabularyTensor::new(16, 4, 32, DEFAULT_TOKEN_PRIME_BITS);
+        assert_eq!(first.kd_tree, second.kd_tree);
+    }
+
+    #[test]
+    fn test_zero_bias_fast_path_matches_general_path() {
+        let decoder = InverseDecoder::new(8);
+        let root = AffineTuple::identity(&Integer::from(-23));
+
+        let fast_empty = decoder.decode_with_bias(&root, &[]).unwrap();
+        let fast_zero = decoder.decode_with_bias(&root, &[0, 0, 0, 0]).unwrap();
+        // side_length 的整数倍在模意义下也是零偏移，但会走通用路径
+        let l = decoder.vocab_tensor.side_length;
+        let general = decoder.decode_with_bias(&root, &[l, 0, 2 * l, 0]).unwrap();
+
+        for res in [&fast_empty, &fast_zero] {
+            assert_eq!(res.token_id, general.token_id);
+            assert_eq!(res.drift, general.drift);
+            assert_eq!(res.confidence, general.confidence);
+        }
+        assert_eq!(decoder.decode(&root).unwrap().token_id, general.token_id);
+    }
+}
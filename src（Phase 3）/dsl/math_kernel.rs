@@ -1,5 +1,6 @@
 use std::ops::{Add, Mul};
 use std::fmt;
+use std::collections::BTreeMap;
 
 /// 严格的半张量积 (STP) 数学内核
 /// 
@@ -124,6 +125,232 @@ impl Matrix {
         // 维度匹配，可以相乘
         a_expanded.matmul(&b_expanded).expect("STP Logic Error: Dimensions should align after expansion")
     }
+
+    /// 半张量积的列向量特化: A |x| v，v 为 p x 1
+    /// 不构造 A (x) I_alpha 与 v (x) I_beta，直接按索引累加：
+    /// result[i*alpha + k, m] = Σ_j A[i, c / alpha] * v[j]，其中 c = j*beta + m 且 c = k (mod alpha)。
+    /// beta == 1 (p 是 n 的倍数) 时退化为 result[i*alpha + k] = Σ_j A[i, j] * v[j*alpha + k]。
+    /// 右操作数不是列向量时回退到通用 `stp`。
+    pub fn stp_vec(&self, v: &Matrix) -> Matrix {
+        if v.cols != 1 {
+            return self.stp(v);
+        }
+
+        let n = self.cols;
+        let p = v.rows;
+        let t = lcm(n, p);
+        let alpha = t / n;
+        let beta = t / p;
+
+        let new_rows = self.rows * alpha;
+        let mut new_data = vec![0.0; new_rows * beta];
+
+        if beta == 1 {
+            for i in 0..self.rows {
+                for j in 0..n {
+                    let val_a = self.get(i, j);
+                    if val_a.abs() < 1e-10 { continue; } // 稀疏优化
+                    for k in 0..alpha {
+                        new_data[i * alpha + k] += val_a * v.data[j * alpha + k];
+                    }
+                }
+            }
+        } else {
+            for i in 0..self.rows {
+                for k in 0..alpha {
+                    for m in 0..beta {
+                        let mut acc = 0.0;
+                        for j in 0..p {
+                            let c = j * beta + m;
+                            if c % alpha == k {
+                                acc += self.get(i, c / alpha) * v.data[j];
+                            }
+                        }
+                        new_data[(i * alpha + k) * beta + m] = acc;
+                    }
+                }
+            }
+        }
+
+        Matrix::new(new_rows, beta, new_data)
+    }
+
+    /// 转置 A^T
+    pub fn transpose(&self) -> Matrix {
+        let mut new_data = vec![0.0; self.rows * self.cols];
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                new_data[j * self.rows + i] = self.get(i, j);
+            }
+        }
+        Matrix::new(self.cols, self.rows, new_data)
+    }
+
+    /// 换位矩阵 (Swap Matrix) W_[m,n]
+    /// 对任意 x ∈ R^m, y ∈ R^n 满足: W_[m,n] |x| (x (x) y) = y (x) x
+    /// 用于在 STP 表达式中交换因子顺序 (构造交换子类结构常数的基础)
+    pub fn swap_matrix(m: usize, n: usize) -> Matrix {
+        let size = m * n;
+        let mut data = vec![0.0; size * size];
+        for i in 0..m {
+            for j in 0..n {
+                // x_i * y_j 在 x (x) y 中位于 i*n + j，在 y (x) x 中位于 j*m + i
+                let src = i * n + j;
+                let dst = j * m + i;
+                data[dst * size + src] = 1.0;
+            }
+        }
+        Matrix::new(size, size, data)
+    }
+
+    /// 行列式 (部分主元高斯消元)
+    /// 仅对方阵有定义
+    pub fn determinant(&self) -> Result<f64, String> {
+        if self.rows != self.cols {
+            return Err(format!(
+                "Determinant requires a square matrix, got ({}, {})",
+                self.rows, self.cols
+            ));
+        }
+
+        let n = self.rows;
+        let mut m = self.data.clone();
+        let mut det = 1.0;
+
+        for col in 0..n {
+            // 选主元
+            let pivot_row = (col..n)
+                .max_by(|&a, &b| m[a * n + col].abs().partial_cmp(&m[b * n + col].abs()).unwrap_or(std::cmp::Ordering::Equal))
+                .unwrap_or(col);
+
+            let pivot = m[pivot_row * n + col];
+            if pivot.abs() < 1e-10 {
+                return Ok(0.0); // 奇异矩阵
+            }
+
+            if pivot_row != col {
+                for k in 0..n {
+                    m.swap(col * n + k, pivot_row * n + k);
+                }
+                det = -det;
+            }
+
+            det *= pivot;
+
+            for row in (col + 1)..n {
+                let factor = m[row * n + col] / pivot;
+                if factor.abs() < 1e-10 { continue; }
+                for k in col..n {
+                    m[row * n + k] -= factor * m[col * n + k];
+                }
+            }
+        }
+
+        Ok(det)
+    }
+}
+
+/// 稀疏矩阵 (坐标表 COO)
+///
+/// 逻辑结构矩阵 (ModAdd / And / Or ...) 每列只有一个 1.0，
+/// 稠密存储下 `stp` 的 Kronecker 扩充随维度平方膨胀；稀疏表示只保存非零元。
+/// `entries` 按 (row, col) 行优先排序且不含重复坐标。
+#[derive(Clone, Debug, PartialEq)]
+pub struct SparseMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub entries: Vec<(usize, usize, f64)>,
+}
+
+impl SparseMatrix {
+    /// 由稠密矩阵构造 (丢弃 |x| < 1e-10 的元素)
+    pub fn from_dense(m: &Matrix) -> Self {
+        let mut entries = Vec::new();
+        for i in 0..m.rows {
+            for j in 0..m.cols {
+                let val = m.get(i, j);
+                if val.abs() >= 1e-10 {
+                    entries.push((i, j, val));
+                }
+            }
+        }
+        SparseMatrix { rows: m.rows, cols: m.cols, entries }
+    }
+
+    /// 还原为稠密矩阵
+    pub fn to_dense(&self) -> Matrix {
+        let mut data = vec![0.0; self.rows * self.cols];
+        for &(i, j, val) in &self.entries {
+            data[i * self.cols + j] = val;
+        }
+        Matrix::new(self.rows, self.cols, data)
+    }
+
+    /// 稀疏单位矩阵 I_n
+    pub fn identity(n: usize) -> Self {
+        SparseMatrix { rows: n, cols: n, entries: (0..n).map(|i| (i, i, 1.0)).collect() }
+    }
+
+    /// 非零元个数
+    pub fn nnz(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 由 (row, col) -> value 的累加表构造，过滤数值零并保持行优先顺序
+    fn from_accumulator(rows: usize, cols: usize, acc: BTreeMap<(usize, usize), f64>) -> Self {
+        let entries = acc.into_iter()
+            .filter(|(_, val)| val.abs() >= 1e-10)
+            .map(|((i, j), val)| (i, j, val))
+            .collect();
+        SparseMatrix { rows, cols, entries }
+    }
+
+    /// Kronecker Product (张量积)，只遍历非零元对
+    pub fn kron(&self, other: &SparseMatrix) -> SparseMatrix {
+        let mut acc = BTreeMap::new();
+        for &(i, j, a) in &self.entries {
+            for &(k, l, b) in &other.entries {
+                acc.insert((i * other.rows + k, j * other.cols + l), a * b);
+            }
+        }
+        Self::from_accumulator(self.rows * other.rows, self.cols * other.cols, acc)
+    }
+
+    /// 标准矩阵乘法，要求 self.cols == other.rows
+    pub fn matmul(&self, other: &SparseMatrix) -> Result<SparseMatrix, String> {
+        if self.cols != other.rows {
+            return Err(format!(
+                "Dimension mismatch for sparse MatMul: ({}, {}) vs ({}, {})",
+                self.rows, self.cols, other.rows, other.cols
+            ));
+        }
+
+        // 按行索引右操作数的非零元
+        let mut other_rows: Vec<Vec<(usize, f64)>> = vec![Vec::new(); other.rows];
+        for &(k, j, b) in &other.entries {
+            other_rows[k].push((j, b));
+        }
+
+        let mut acc = BTreeMap::new();
+        for &(i, k, a) in &self.entries {
+            for &(j, b) in &other_rows[k] {
+                *acc.entry((i, j)).or_insert(0.0) += a * b;
+            }
+        }
+        Ok(Self::from_accumulator(self.rows, other.cols, acc))
+    }
+
+    /// 半张量积 (Semi-Tensor Product)，与 `Matrix::stp` 的 LCM 扩充规则一致
+    pub fn stp(&self, other: &SparseMatrix) -> SparseMatrix {
+        let t = lcm(self.cols, other.rows);
+        let alpha = t / self.cols;
+        let beta = t / other.rows;
+
+        let a_expanded = if alpha == 1 { self.clone() } else { self.kron(&SparseMatrix::identity(alpha)) };
+        let b_expanded = if beta == 1 { other.clone() } else { other.kron(&SparseMatrix::identity(beta)) };
+
+        a_expanded.matmul(&b_expanded).expect("STP Logic Error: Dimensions should align after expansion")
+    }
 }
 
 // 辅助函数：最大公约数
@@ -195,4 +422,100 @@ mod tests {
 
         assert_eq!(res_stp.data, res_mul.data);
     }
+
+    #[test]
+    fn test_stp_vec_matches_stp() {
+        let m22 = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+        let m24 = Matrix::new(2, 4, vec![1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0]);
+        let m33 = Matrix::new(3, 3, vec![1.0, 2.0, 0.0, 0.0, 1.0, 3.0, 4.0, 0.0, 1.0]);
+        let v = |n: usize| Matrix::new(n, 1, (1..=n).map(|x| x as f64).collect());
+
+        // (矩阵, 向量维度): 覆盖 n = p, p = k*n (beta = 1), n = k*p (alpha = 1), 非整除
+        let cases = vec![(&m22, 2), (&m22, 4), (&m22, 8), (&m24, 2), (&m24, 8), (&m33, 2), (&m33, 6)];
+        for (m, p) in cases {
+            let x = v(p);
+            assert_eq!(m.stp_vec(&x), m.stp(&x), "mismatch for {}x{} |x| {}x1", m.rows, m.cols, p);
+        }
+
+        // 非列向量回退到通用 stp
+        assert_eq!(m22.stp_vec(&m24), m22.stp(&m24));
+    }
+
+    #[test]
+    fn test_sparse_stp_matches_dense_on_mod_add() {
+        // Z2 加法结构矩阵 (ModAdd)
+        let m_add = Matrix::new(2, 4, vec![
+            1.0, 0.0, 0.0, 1.0,
+            0.0, 1.0, 1.0, 0.0
+        ]);
+        let sparse_add = SparseMatrix::from_dense(&m_add);
+        assert_eq!(sparse_add.nnz(), 4);
+        assert_eq!(sparse_add.to_dense(), m_add);
+
+        let even = Matrix::new(2, 1, vec![1.0, 0.0]);
+        let odd = Matrix::new(2, 1, vec![0.0, 1.0]);
+        for x in [&even, &odd] {
+            for y in [&even, &odd] {
+                let dense = m_add.stp(x).stp(y);
+                let sparse = sparse_add
+                    .stp(&SparseMatrix::from_dense(x))
+                    .stp(&SparseMatrix::from_dense(y));
+                assert_eq!(sparse.to_dense(), dense);
+            }
+        }
+
+        // kron / matmul 与稠密实现一致
+        let b = Matrix::new(2, 2, vec![1.0, 2.0, 0.0, 3.0]);
+        let sb = SparseMatrix::from_dense(&b);
+        assert_eq!(sparse_add.kron(&sb).to_dense(), m_add.kron(&b));
+        assert_eq!(sb.matmul(&sparse_add).unwrap().to_dense(), b.matmul(&m_add).unwrap());
+        assert!(sparse_add.matmul(&sb).is_err());
+    }
+
+    #[test]
+    fn test_transpose() {
+        let a = Matrix::new(2, 3, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let t = a.transpose();
+
+        assert_eq!(t.rows, 3);
+        assert_eq!(t.cols, 2);
+        assert_eq!(t.data, vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+        assert_eq!(t.transpose(), a);
+    }
+
+    #[test]
+    fn test_swap_matrix_reorders_factors() {
+        // x ∈ R^2, y ∈ R^3
+        let x = Matrix::new(2, 1, vec![1.0, 2.0]);
+        let y = Matrix::new(3, 1, vec![3.0, 5.0, 7.0]);
+
+        let w = Matrix::swap_matrix(2, 3);
+        let swapped = w.matmul(&x.kron(&y)).unwrap();
+
+        assert_eq!(swapped, y.kron(&x));
+
+        // W_[m,n] 是置换矩阵: W^T W = I
+        let wtw = w.transpose().matmul(&w).unwrap();
+        assert_eq!(wtw, Matrix::identity(6));
+    }
+
+    #[test]
+    fn test_determinant() {
+        let a = Matrix::new(3, 3, vec![
+            2.0, 0.0, 1.0,
+            1.0, 3.0, 2.0,
+            1.0, 1.0, 1.0
+        ]);
+        // 2*(3-2) - 0 + 1*(1-3) = 0
+        assert!(a.determinant().unwrap().abs() < 1e-9);
+
+        let b = Matrix::new(2, 2, vec![4.0, 7.0, 2.0, 6.0]);
+        assert!((b.determinant().unwrap() - 10.0).abs() < 1e-9);
+
+        // 换位矩阵为置换矩阵，行列式为 ±1
+        assert!((Matrix::swap_matrix(2, 2).determinant().unwrap().abs() - 1.0).abs() < 1e-9);
+
+        let non_square = Matrix::new(1, 2, vec![1.0, 2.0]);
+        assert!(non_square.determinant().is_err());
+    }
 }
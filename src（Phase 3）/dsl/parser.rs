@@ -3,6 +3,8 @@
 
 use crate::dsl::schema::{ProofAction, ProofSequence};
 use serde_json::Error;
+use std::collections::HashSet;
+use std::io::BufRead;
 
 pub struct ProofParser;
 
@@ -16,6 +18,69 @@ impl ProofParser {
     pub fn parse_action(json_input: &str) -> Result<ProofAction, Error> {
         serde_json::from_str(json_input)
     }
+
+    /// 从换行分隔的 JSON (JSONL) 流中逐行解析动作
+    /// 每一行是一个独立的 ProofAction；空行被跳过。
+    /// 单行解析失败只产出一个 Err，不会中断后续行的解析。
+    pub fn parse_stream<R: BufRead>(reader: R) -> impl Iterator<Item = Result<ProofAction, Error>> {
+        reader.lines().filter_map(|line| match line {
+            Ok(text) => {
+                if text.trim().is_empty() {
+                    None
+                } else {
+                    Some(Self::parse_action(&text))
+                }
+            },
+            Err(e) => Some(Err(Error::io(e))),
+        })
+    }
+
+    /// 结构校验：在交给 STPContext 之前拦截格式错误的生成结果
+    /// - Apply 的 inputs 与 output_symbol 必须已被 Define
+    /// - QED (如果存在) 必须是最后一步
+    /// - 同一符号不得重复 Define
+    /// 收集所有违规项，而非在第一个错误处停止。
+    pub fn validate(seq: &ProofSequence) -> Result<(), Vec<String>> {
+        let mut defined: HashSet<&str> = HashSet::new();
+        let mut violations = Vec::new();
+        let last_index = seq.steps.len().saturating_sub(1);
+
+        for (i, step) in seq.steps.iter().enumerate() {
+            match step {
+                ProofAction::Define { symbol, .. } => {
+                    if !defined.insert(symbol.as_str()) {
+                        violations.push(format!("Step {}: symbol '{}' is redefined", i, symbol));
+                    }
+                },
+                ProofAction::Apply { theorem_id, inputs, output_symbol } => {
+                    for input in inputs {
+                        if !defined.contains(input.as_str()) {
+                            violations.push(format!(
+                                "Step {}: {} uses undefined input '{}'", i, theorem_id, input
+                            ));
+                        }
+                    }
+                    if !defined.contains(output_symbol.as_str()) {
+                        violations.push(format!(
+                            "Step {}: {} targets undefined output '{}'", i, theorem_id, output_symbol
+                        ));
+                    }
+                },
+                ProofAction::QED => {
+                    if i != last_index {
+                        violations.push(format!("Step {}: QED must be the final step", i));
+                    }
+                },
+                ProofAction::Assert { .. } => {},
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -98,4 +163,69 @@ mod tests {
         // 检查最后一步：QED
         assert_eq!(proof.steps[4], ProofAction::QED);
     }
+
+    #[test]
+    fn test_parse_stream_survives_malformed_line() {
+        let jsonl = r#"{"action": "Define", "params": {"symbol": "n", "hierarchy_path": ["Odd"]}}
+{"action": "Define", "params": {"symbol": "m", "hierarchy_path": 
+{"action": "Apply", "params": {"theorem_id": "ModAdd", "inputs": ["n", "m"], "output_symbol": "sum"}}
+
+{"action": "QED", "params": {}}
+"#;
+
+        let results: Vec<_> = ProofParser::parse_stream(jsonl.as_bytes()).collect();
+
+        // 空行被跳过，剩余 4 行非空输入
+        assert_eq!(results.len(), 4);
+        assert!(matches!(&results[0], Ok(ProofAction::Define { symbol, .. }) if symbol == "n"));
+        assert!(results[1].is_err());
+        assert!(matches!(&results[2], Ok(ProofAction::Apply { theorem_id, .. }) if theorem_id == "ModAdd"));
+        assert_eq!(results[3].as_ref().unwrap(), &ProofAction::QED);
+    }
+
+    #[test]
+    fn test_validate_collects_all_violations() {
+        let define = |symbol: &str| ProofAction::Define {
+            symbol: symbol.to_string(),
+            hierarchy_path: vec!["Odd".to_string()],
+        };
+
+        let valid = ProofSequence {
+            goal: "ok".to_string(),
+            steps: vec![
+                define("n"),
+                define("m"),
+                define("sum"),
+                ProofAction::Apply {
+                    theorem_id: "ModAdd".to_string(),
+                    inputs: vec!["n".to_string(), "m".to_string()],
+                    output_symbol: "sum".to_string(),
+                },
+                ProofAction::QED,
+            ],
+        };
+        assert!(ProofParser::validate(&valid).is_ok());
+
+        let broken = ProofSequence {
+            goal: "broken".to_string(),
+            steps: vec![
+                define("n"),
+                define("n"),
+                ProofAction::QED,
+                ProofAction::Apply {
+                    theorem_id: "ModAdd".to_string(),
+                    inputs: vec!["n".to_string(), "ghost".to_string()],
+                    output_symbol: "sum".to_string(),
+                },
+            ],
+        };
+        let violations = ProofParser::validate(&broken).unwrap_err();
+
+        // 重复定义 + QED 不在末尾 + 未定义输入 + 未定义输出
+        assert_eq!(violations.len(), 4);
+        assert!(violations[0].contains("redefined"));
+        assert!(violations[1].contains("QED"));
+        assert!(violations[2].contains("ghost"));
+        assert!(violations[3].contains("sum"));
+    }
 }
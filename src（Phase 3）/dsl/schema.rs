@@ -1,7 +1,11 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde::de::IgnoredAny;
 
 /// Represents a rigorous mathematical action in the HTP system.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Wire format: `{"action": "<Variant>", "params": {...}}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", content = "params")]
 pub enum ProofAction {
     Define {
         symbol: String,
@@ -12,6 +16,59 @@ pub enum ProofAction {
         inputs: Vec<String>,
         output_symbol: String,
     },
+    /// Claims a relation about an already-defined symbol, e.g. `sum IsEven True`.
+    Assert {
+        subject: String,
+        relation: String,
+        object: String,
+    },
+    /// Closes the proof. Generators emit it as `{"action": "QED", "params": {}}`.
+    #[serde(deserialize_with = "deserialize_empty_params")]
+    QED,
+}
+
+/// Accepts `{}`, `null` or a missing `params` for unit actions.
+fn deserialize_empty_params<'de, D>(deserializer: D) -> Result<(), D::Error>
+where
+    D: Deserializer<'de>,
+{
+    IgnoredAny::deserialize(deserializer).map(|_| ())
+}
+
+/// A complete proof as emitted by the Generator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProofSequence {
+    pub goal: String,
+    pub steps: Vec<ProofAction>,
+}
+
+/// How a context string is canonicalized before it is hashed for binding.
+///
+/// Prover and verifier must use the same policy. `Exact` is the default so the
+/// binding is never weakened unless a caller opts in explicitly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NormalizationPolicy {
+    /// Hash the raw bytes.
+    #[default]
+    Exact,
+    /// Normalize line endings, trim, and collapse every whitespace run
+    /// (including newlines) into a single space.
+    Whitespace,
+}
+
+impl NormalizationPolicy {
+    /// Returns the canonical form of `context` under this policy.
+    pub fn apply(&self, context: &str) -> String {
+        match self {
+            NormalizationPolicy::Exact => context.to_string(),
+            NormalizationPolicy::Whitespace => context
+                .replace("\r\n", "\n")
+                .replace('\r', "\n")
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
 }
 
 /// The Holy Grail of the Evolver system.
@@ -32,7 +89,7 @@ pub struct ProofBundle {
     pub energy_signature: f64,
 
     /// SECURITY UPDATE (v0.2): Context Integrity
-    /// SHA256 Hash of the input prompt/context.
+    /// BLAKE3 Hash (hex) of the input prompt/context.
     /// Prevents "Context Splicing" attacks.
     pub context_hash: String,
 
@@ -43,11 +100,26 @@ pub struct ProofBundle {
 }
 
 impl ProofBundle {
+    /// Canonical context hash used for binding (BLAKE3, hex-encoded).
+    pub fn hash_context(context_str: &str) -> String {
+        blake3::hash(context_str.as_bytes()).to_hex().to_string()
+    }
+
+    /// Context hash after applying `policy` (`Exact` is identical to `hash_context`).
+    pub fn hash_context_with(context_str: &str, policy: NormalizationPolicy) -> String {
+        Self::hash_context(&policy.apply(context_str))
+    }
+
     /// A lightweight check to see if this bundle belongs to the given context.
+    /// Uses exact matching; see `verify_binding_with` for normalized modes.
     pub fn verify_binding(&self, current_context_str: &str) -> bool {
-        // In a real implementation, use sha2::Sha256
-        // Here we use a mock hash for demonstration
-        let calculated_hash = format!("{:x}", md5::compute(current_context_str));
+        self.verify_binding_with(current_context_str, NormalizationPolicy::Exact)
+    }
+
+    /// Like `verify_binding`, but canonicalizes the context with `policy` first.
+    /// The prover must have bound the bundle with `hash_context_with` under the same policy.
+    pub fn verify_binding_with(&self, current_context_str: &str, policy: NormalizationPolicy) -> bool {
+        let calculated_hash = Self::hash_context_with(current_context_str, policy);
         
         if self.context_hash != calculated_hash {
             println!("❌ [Security] Context Mismatch! Bundle bound to {}, but current is {}.", 
@@ -57,3 +129,59 @@ impl ProofBundle {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle_for(context: &str) -> ProofBundle {
+        ProofBundle {
+            bias_vector: vec![0.0; 4],
+            action: ProofAction::QED,
+            energy_signature: 0.0,
+            context_hash: ProofBundle::hash_context(context),
+            generator_seed: 7,
+        }
+    }
+
+    #[test]
+    fn test_context_hash_is_blake3_and_distinct() {
+        let a = ProofBundle::hash_context("Prove that the sum of two Odd numbers is Even");
+        let b = ProofBundle::hash_context("Prove that the sum of two Odd numbers is Odd");
+
+        assert_eq!(a.len(), 64); // 256-bit digest, hex-encoded
+        assert_ne!(a, b);
+        assert_eq!(a, ProofBundle::hash_context("Prove that the sum of two Odd numbers is Even"));
+    }
+
+    #[test]
+    fn test_verify_binding_rejects_mismatched_context() {
+        let bundle = bundle_for("context A");
+        assert!(bundle.verify_binding("context A"));
+        assert!(!bundle.verify_binding("context B"));
+    }
+
+    #[test]
+    fn test_exact_policy_rejects_whitespace_only_difference() {
+        let bundle = bundle_for("Prove X is Y");
+        assert!(bundle.verify_binding_with("Prove X is Y", NormalizationPolicy::Exact));
+        assert!(!bundle.verify_binding_with("Prove X is Y\n", NormalizationPolicy::Exact));
+        assert!(!bundle.verify_binding("Prove  X is Y"));
+    }
+
+    #[test]
+    fn test_whitespace_policy_tolerates_formatting() {
+        let policy = NormalizationPolicy::Whitespace;
+        let mut bundle = bundle_for("");
+        bundle.context_hash = ProofBundle::hash_context_with("Prove X\r\nis Y  ", policy);
+
+        assert!(bundle.verify_binding_with("Prove X\nis Y", policy));
+        assert!(bundle.verify_binding_with("  Prove   X is\tY\n", policy));
+        assert!(!bundle.verify_binding_with("Prove X is Z", policy));
+        // Content changes are still detected; exact mode stays strict
+        assert!(!bundle.verify_binding("Prove X\nis Y"));
+
+        assert_eq!(policy.apply("a\r\n b\rc "), "a b c");
+        assert_eq!(NormalizationPolicy::default(), NormalizationPolicy::Exact);
+    }
+}
@@ -1,7 +1,73 @@
 use std::collections::HashMap;
-use crate::dsl::schema::{ProofAction};
+use crate::dsl::schema::{ProofAction, ProofSequence};
 // [Fix] Correct import for Matrix
-use crate::dsl::math_kernel::Matrix; 
+use crate::dsl::math_kernel::{Matrix, SparseMatrix}; 
+
+/// 未知类型 / 非法层级路径的 Define 所对应的能量
+pub const INVALID_TYPE_ENERGY: f64 = 1.0;
+
+/// 类型格 (Type Lattice)
+/// `leaves`: 已知叶子类型 -> 逻辑向量
+/// `parents`: 子类型 -> 父类型，用于校验 `hierarchy_path` 是否为合法链
+#[derive(Debug, Clone)]
+pub struct TypeLattice {
+    pub leaves: HashMap<String, Matrix>,
+    pub parents: HashMap<String, String>,
+}
+
+impl TypeLattice {
+    /// 默认类型格:
+    /// Number -> Integer -> {Odd, Even}
+    /// Boolean -> {True, False}
+    pub fn standard() -> Self {
+        let mut leaves = HashMap::new();
+        leaves.insert("Even".to_string(), Matrix::new(2, 1, vec![1.0, 0.0]));
+        leaves.insert("Odd".to_string(), Matrix::new(2, 1, vec![0.0, 1.0]));
+        leaves.insert("True".to_string(), Matrix::new(2, 1, vec![1.0, 0.0]));
+        leaves.insert("False".to_string(), Matrix::new(2, 1, vec![0.0, 1.0]));
+
+        let mut parents = HashMap::new();
+        for (child, parent) in [
+            ("Integer", "Number"),
+            ("Odd", "Integer"),
+            ("Even", "Integer"),
+            ("True", "Boolean"),
+            ("False", "Boolean"),
+        ] {
+            parents.insert(child.to_string(), parent.to_string());
+        }
+
+        TypeLattice { leaves, parents }
+    }
+
+    /// 校验层级路径并返回叶子类型的向量
+    /// 路径可以只给出链的一段 (例如 ["Odd"] 或 ["Integer", "Odd"])，但相邻元素必须是父子关系。
+    pub fn resolve(&self, hierarchy_path: &[String]) -> Result<&Matrix, String> {
+        let leaf = hierarchy_path.last().ok_or("Empty hierarchy path")?;
+
+        for pair in hierarchy_path.windows(2) {
+            if self.parents.get(&pair[1]) != Some(&pair[0]) {
+                return Err(format!("'{}' is not a subtype of '{}' in the type lattice", pair[1], pair[0]));
+            }
+        }
+
+        self.leaves
+            .get(leaf)
+            .ok_or_else(|| format!("Unknown leaf type '{}' in path {:?}", leaf, hierarchy_path))
+    }
+}
+
+impl Default for TypeLattice {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+/// `STPContext` 符号状态的快照，用于投机搜索后的回滚
+#[derive(Debug, Clone)]
+pub struct STPSnapshot {
+    state: HashMap<String, Matrix>,
+}
 
 /// STP (Semi-Tensor Product) Context
 /// 负责维护逻辑状态并计算“能量值”（逻辑违背程度）。
@@ -11,19 +77,49 @@ pub struct STPContext {
     state: HashMap<String, Matrix>,
     
     // 结构常数矩阵缓存 (Structure Constants)
-    operators: HashMap<String, Matrix>,
+    // 结构矩阵每列只有一个非零元，稀疏存储避免 STP 扩充时的平方膨胀
+    operators: HashMap<String, SparseMatrix>,
+
+    // 类型格，用于解析 Define 的层级路径
+    types: TypeLattice,
 }
 
 impl STPContext {
     pub fn new() -> Self {
+        Self::with_type_lattice(TypeLattice::standard())
+    }
+
+    /// 使用自定义类型格构造
+    pub fn with_type_lattice(types: TypeLattice) -> Self {
         let mut ctx = STPContext {
             state: HashMap::new(),
             operators: HashMap::new(),
+            types,
         };
         ctx.init_operators();
         ctx
     }
 
+    /// 清空所有已定义的符号，保留算子与类型格
+    pub fn reset_state(&mut self) {
+        self.state.clear();
+    }
+
+    /// 捕获当前符号状态
+    pub fn snapshot(&self) -> STPSnapshot {
+        STPSnapshot { state: self.state.clone() }
+    }
+
+    /// 回滚到之前捕获的快照
+    pub fn restore(&mut self, snapshot: &STPSnapshot) {
+        self.state = snapshot.state.clone();
+    }
+
+    /// 符号是否已在当前状态中定义
+    pub fn is_defined(&self, symbol: &str) -> bool {
+        self.state.contains_key(symbol)
+    }
+
     /// 初始化常用的逻辑算子矩阵
     fn init_operators(&mut self) {
         // [Fix] Matrix constructor usage fixed to (rows, cols, data)
@@ -34,72 +130,416 @@ impl STPContext {
             0.0, 1.0, 1.0, 0.0  // Row 2 (Odd result maps)
         ]);
         
-        self.operators.insert("ModAdd".to_string(), m_add);
+        self.operators.insert("ModAdd".to_string(), SparseMatrix::from_dense(&m_add));
+
+        // 布尔逻辑算子 (Cheng 结构矩阵, True = δ2^1 = [1, 0], False = δ2^2 = [0, 1])
+        // Not: M_n = δ2[2, 1]
+        let m_not = Matrix::new(2, 2, vec![
+            0.0, 1.0,
+            1.0, 0.0
+        ]);
+        // And: M_c = δ2[1, 2, 2, 2]
+        let m_and = Matrix::new(2, 4, vec![
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 1.0, 1.0
+        ]);
+        // Or: M_d = δ2[1, 1, 1, 2]
+        let m_or = Matrix::new(2, 4, vec![
+            1.0, 1.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ]);
+
+        self.operators.insert("Not".to_string(), SparseMatrix::from_dense(&m_not));
+        self.operators.insert("And".to_string(), SparseMatrix::from_dense(&m_and));
+        self.operators.insert("Or".to_string(), SparseMatrix::from_dense(&m_or));
+    }
+
+    /// 通过 STP 计算算子作用于输入向量的结果: M |x| v1 |x| v2 ...
+    /// 二元算子可作用于任意多个输入，按左结合折叠:
+    /// ModAdd(a, b, c) = M |x| (M |x| a |x| b) |x| c
+    /// 任一输入未定义时返回 None (未知状态不参与能量计算)
+    fn evaluate_operator(&self, theorem_id: &str, inputs: &[String]) -> Option<Matrix> {
+        let operator = self.operators.get(theorem_id)?;
+
+        let vectors = inputs
+            .iter()
+            .map(|input| self.state.get(input).map(SparseMatrix::from_dense))
+            .collect::<Option<Vec<SparseMatrix>>>()?;
+
+        // 结构矩阵 2 x 2^k 对应 k 元算子
+        if !operator.cols.is_power_of_two() {
+            return None;
+        }
+        let arity = operator.cols.trailing_zeros() as usize;
+
+        if arity > 0 && vectors.len() == arity {
+            let mut result = operator.clone();
+            for v in &vectors {
+                result = result.stp(v);
+            }
+            Some(result.to_dense())
+        } else if arity == 2 && vectors.len() > 2 {
+            let mut acc = vectors[0].clone();
+            for v in &vectors[1..] {
+                acc = operator.stp(&acc).stp(v);
+            }
+            Some(acc.to_dense())
+        } else {
+            None
+        }
     }
 
     /// 核心能量计算函数
     /// Energy = 0.0 表示逻辑自洽
     /// Energy > 0.0 表示存在逻辑矛盾
     pub fn calculate_energy(&mut self, action: &ProofAction) -> f64 {
+        self.calculate_energy_detailed(action).energy
+    }
+
+    /// 整段证明的能量评估：按顺序执行每一步并累计能量
+    /// 执行会修改符号状态 (与逐步调用 `calculate_energy` 相同)，需要时请先 `snapshot`。
+    pub fn evaluate_proof(&mut self, seq: &ProofSequence) -> ProofEvaluation {
+        let per_step: Vec<f64> = seq.steps.iter().map(|step| self.calculate_energy(step)).collect();
+        ProofEvaluation {
+            total_energy: per_step.iter().sum(),
+            first_violation: per_step.iter().position(|&e| e > 0.0),
+            per_step,
+        }
+    }
+
+    /// 带诊断信息的能量计算
+    /// 能量非零时，`conflict` 指明冲突的算子 / 符号以及期望与声明的取值。
+    pub fn calculate_energy_detailed(&mut self, action: &ProofAction) -> EnergyReport {
         match action {
             ProofAction::Define { symbol, hierarchy_path } => {
-                // 解析定义，将符号映射为向量
-                // 简化逻辑: "Odd" -> [0, 1], "Even" -> [1, 0]
-                let val_type = hierarchy_path.last().map(|s| s.as_str()).unwrap_or("");
-                
-                let vector = if val_type == "Odd" {
-                    Matrix::new(2, 1, vec![0.0, 1.0]) // Odd: Vector [0, 1]
-                } else {
-                    Matrix::new(2, 1, vec![1.0, 0.0]) // Even: Vector [1, 0]
-                };
-                
-                self.state.insert(symbol.clone(), vector);
-                0.0 // 定义动作本身默认为“合法”
+                // 解析定义，通过类型格将符号映射为向量
+                // 例如: "Odd"/"False" -> [0, 1], "Even"/"True" -> [1, 0]
+                match self.types.resolve(hierarchy_path) {
+                    Ok(vector) => {
+                        let vector = vector.clone();
+                        self.state.insert(symbol.clone(), vector);
+                        EnergyReport::consistent() // 合法定义本身不产生能量
+                    },
+                    Err(reason) => {
+                        // 未知类型不再默认为 Even，符号保持未定义
+                        EnergyReport {
+                            energy: INVALID_TYPE_ENERGY,
+                            conflict: Some(format!("Define({}) rejected: {}", symbol, reason)),
+                        }
+                    },
+                }
             },
             
             ProofAction::Apply { theorem_id, inputs, output_symbol } => {
                 // 验证推理的一致性
-                
-                // 1. 获取输入向量
-                let v1 = match self.state.get(&inputs[0]) {
+
+                // 1. 获取预期的输出向量 (即 Generator 声称的结果)
+                // 此时 output_symbol 必须已经在 state 中定义
+                let v_claim = match self.state.get(output_symbol) {
                     Some(v) => v,
-                    None => return 0.0, // 未知输入暂不惩罚
+                    None => return EnergyReport::consistent(), // 如果未定义，无法计算能量违规
                 };
-                
-                let empty_string = "".to_string();
-                let v2_key = inputs.get(1).unwrap_or(&empty_string);
-                let v2 = match self.state.get(v2_key) {
+
+                // 2. 执行 STP 运算: M |x| v1 |x| v2
+                // 例如 ModAdd: Odd + Odd = Even 由结构矩阵 M_add 的第 4 列给出
+                let predicted = match self.evaluate_operator(theorem_id, inputs) {
                     Some(v) => v,
-                    None => return 0.0,
+                    None => return EnergyReport::consistent(), // 未知算子或未知输入暂不惩罚
                 };
-                
-                // 2. 获取预期的输出向量 (即 Generator 声称的结果)
-                // 此时 output_symbol 必须已经在 state 中定义
-                let v_claim = match self.state.get(output_symbol) {
+
+                // 3. 能量 = 预测向量与声明向量之间的 L1 距离
+                // 对逻辑向量而言，完全相反的结论 ([1,0] vs [0,1]) 对应能量 2.0
+                let energy = l1_distance(&predicted, v_claim);
+                let conflict = (energy > 0.0).then(|| {
+                    let boolean = is_boolean_operator(theorem_id);
+                    format!(
+                        "{}({}) expected {} but {} declared {}",
+                        theorem_id,
+                        inputs.join(","),
+                        describe_vector(&predicted, boolean),
+                        output_symbol,
+                        describe_vector(v_claim, boolean)
+                    )
+                });
+                EnergyReport { energy, conflict }
+            },
+            
+            ProofAction::Assert { subject, relation, object } => {
+                // 断言检查: 将 (relation, object) 编码为期望向量，与已存储的向量比较
+                let v_subject = match self.state.get(subject) {
                     Some(v) => v,
-                    None => return 0.0, // 如果未定义，无法计算能量违规
+                    None => return EnergyReport::consistent(), // 未定义的主体暂不惩罚
                 };
 
-                // 3. 执行 STP 运算 (简化模拟版)
-                if theorem_id == "ModAdd" {
-                    // 假设 data[1] > 0.5 表示是 Odd (向量为 [0, 1])
-                    let is_v1_odd = v1.data.get(1).copied().unwrap_or(0.0) > 0.5;
-                    let is_v2_odd = v2.data.get(1).copied().unwrap_or(0.0) > 0.5;
-                    
-                    // 逻辑运算: Odd + Odd = Even (即 1 ^ 1 = 0)
-                    // XOR 逻辑: 结果为奇数当且仅当只有一个输入是奇数
-                    let should_be_odd = is_v1_odd ^ is_v2_odd; 
-                    
-                    let claim_is_odd = v_claim.data.get(1).copied().unwrap_or(0.0) > 0.5;
-                    
-                    if should_be_odd != claim_is_odd {
-                        return 1.0; // 逻辑矛盾！能量激增！
-                    }
-                }
-                0.0
+                // IsEven/IsTrue 指向 [1, 0]，IsOdd/IsFalse 指向 [0, 1]
+                let relation_points_first = match relation.as_str() {
+                    "IsEven" | "IsTrue" => true,
+                    "IsOdd" | "IsFalse" => false,
+                    _ => return EnergyReport::consistent(), // 未知关系暂不惩罚
+                };
+                let holds = match object.as_str() {
+                    "True" => true,
+                    "False" => false,
+                    _ => return EnergyReport::consistent(),
+                };
+
+                let expected = if relation_points_first == holds {
+                    Matrix::new(2, 1, vec![1.0, 0.0])
+                } else {
+                    Matrix::new(2, 1, vec![0.0, 1.0])
+                };
+                let energy = l1_distance(&expected, v_subject);
+                let conflict = (energy > 0.0).then(|| {
+                    let boolean = matches!(relation.as_str(), "IsTrue" | "IsFalse");
+                    format!(
+                        "Assert({} {} {}) expected {} but {} declared {}",
+                        subject,
+                        relation,
+                        object,
+                        describe_vector(&expected, boolean),
+                        subject,
+                        describe_vector(v_subject, boolean)
+                    )
+                });
+                EnergyReport { energy, conflict }
             },
-            
-            _ => 0.0,
+
+            ProofAction::QED => EnergyReport::consistent(),
         }
     }
 }
+
+/// 整段证明的评估结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProofEvaluation {
+    pub total_energy: f64,
+    /// 每一步的能量，与 `ProofSequence::steps` 一一对应
+    pub per_step: Vec<f64>,
+    /// 第一个能量非零的步骤下标；`None` 表示整段证明自洽
+    pub first_violation: Option<usize>,
+}
+
+/// 能量诊断报告
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnergyReport {
+    pub energy: f64,
+    /// 冲突描述，例如 "ModAdd(n,m) expected Even but sum_truth declared Odd"
+    pub conflict: Option<String>,
+}
+
+impl EnergyReport {
+    fn consistent() -> Self {
+        EnergyReport { energy: 0.0, conflict: None }
+    }
+}
+
+fn is_boolean_operator(theorem_id: &str) -> bool {
+    matches!(theorem_id, "Not" | "And" | "Or")
+}
+
+/// 将逻辑向量还原为可读名称 ([1,0] -> Even/True, [0,1] -> Odd/False)
+fn describe_vector(v: &Matrix, boolean: bool) -> String {
+    let names = if boolean { ("True", "False") } else { ("Even", "Odd") };
+    match v.data.as_slice() {
+        [x, y] if *x == 1.0 && *y == 0.0 => names.0.to_string(),
+        [x, y] if *x == 0.0 && *y == 1.0 => names.1.to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// L1 距离 (维度不一致时视为完全不相容)
+fn l1_distance(a: &Matrix, b: &Matrix) -> f64 {
+    if a.rows != b.rows || a.cols != b.cols {
+        return f64::MAX;
+    }
+    a.data.iter().zip(b.data.iter()).map(|(x, y)| (x - y).abs()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn define(symbol: &str, leaf: &str) -> ProofAction {
+        ProofAction::Define {
+            symbol: symbol.to_string(),
+            hierarchy_path: vec![leaf.to_string()],
+        }
+    }
+
+    fn apply(theorem_id: &str, inputs: &[&str], output_symbol: &str) -> ProofAction {
+        ProofAction::Apply {
+            theorem_id: theorem_id.to_string(),
+            inputs: inputs.iter().map(|s| s.to_string()).collect(),
+            output_symbol: output_symbol.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_proof_locates_first_violation() {
+        // parser 测试中的 odd + odd = even 证明，补上 sum 的定义
+        let proof = |sum_leaf: &str| ProofSequence {
+            goal: "Prove sum of two odd integers is even".to_string(),
+            steps: vec![
+                define("n", "Odd"),
+                define("m", "Odd"),
+                define("sum", sum_leaf),
+                apply("ModAdd", &["n", "m"], "sum"),
+                ProofAction::Assert {
+                    subject: "sum".to_string(),
+                    relation: "IsEven".to_string(),
+                    object: "True".to_string(),
+                },
+                ProofAction::QED,
+            ],
+        };
+
+        let sound = STPContext::new().evaluate_proof(&proof("Even"));
+        assert_eq!(sound.total_energy, 0.0);
+        assert_eq!(sound.per_step, vec![0.0; 6]);
+        assert_eq!(sound.first_violation, None);
+
+        let corrupted = STPContext::new().evaluate_proof(&proof("Odd"));
+        assert_eq!(corrupted.first_violation, Some(3));
+        assert_eq!(corrupted.per_step[3], 2.0);
+        assert_eq!(corrupted.total_energy, corrupted.per_step.iter().sum::<f64>());
+        assert!(corrupted.total_energy >= 2.0);
+    }
+
+    #[test]
+    fn test_mod_add_is_matrix_driven() {
+        let mut ctx = STPContext::new();
+        ctx.calculate_energy(&define("n", "Odd"));
+        ctx.calculate_energy(&define("m", "Odd"));
+        ctx.calculate_energy(&define("even_sum", "Even"));
+        ctx.calculate_energy(&define("odd_sum", "Odd"));
+
+        // M_add |x| Odd |x| Odd = Even
+        let predicted = ctx.evaluate_operator("ModAdd", &["n".to_string(), "m".to_string()]).unwrap();
+        assert_eq!(predicted, Matrix::new(2, 1, vec![1.0, 0.0]));
+
+        assert_eq!(ctx.calculate_energy(&apply("ModAdd", &["n", "m"], "even_sum")), 0.0);
+        assert_eq!(ctx.calculate_energy(&apply("ModAdd", &["n", "m"], "odd_sum")), 2.0);
+    }
+
+    #[test]
+    fn test_define_validates_type_lattice() {
+        let mut ctx = STPContext::new();
+
+        let full_path = ProofAction::Define {
+            symbol: "n".to_string(),
+            hierarchy_path: vec!["Number".to_string(), "Integer".to_string(), "Odd".to_string()],
+        };
+        assert_eq!(ctx.calculate_energy(&full_path), 0.0);
+
+        // 未知叶子类型不再被当作 Even
+        let real = ProofAction::Define {
+            symbol: "x".to_string(),
+            hierarchy_path: vec!["Number".to_string(), "Real".to_string()],
+        };
+        let report = ctx.calculate_energy_detailed(&real);
+        assert_eq!(report.energy, INVALID_TYPE_ENERGY);
+        assert!(report.conflict.unwrap().contains("Real"));
+        assert!(ctx.evaluate_operator("Not", &["x".to_string()]).is_none());
+
+        // 链断裂: Boolean 不是 Odd 的父类型
+        let broken = ProofAction::Define {
+            symbol: "y".to_string(),
+            hierarchy_path: vec!["Boolean".to_string(), "Odd".to_string()],
+        };
+        assert!(ctx.calculate_energy(&broken) > 0.0);
+    }
+
+    #[test]
+    fn test_snapshot_restore_and_reset() {
+        let mut ctx = STPContext::new();
+        ctx.calculate_energy(&define("n", "Odd"));
+        let snap = ctx.snapshot();
+
+        ctx.calculate_energy(&define("x", "Even"));
+        assert!(ctx.is_defined("x"));
+        ctx.restore(&snap);
+        assert!(!ctx.is_defined("x"));
+        assert!(ctx.is_defined("n"));
+
+        ctx.reset_state();
+        assert!(!ctx.is_defined("n"));
+        // 算子仍然可用
+        ctx.calculate_energy(&define("n", "Odd"));
+        ctx.calculate_energy(&define("m", "Odd"));
+        ctx.calculate_energy(&define("s", "Even"));
+        assert_eq!(ctx.calculate_energy(&apply("ModAdd", &["n", "m"], "s")), 0.0);
+    }
+
+    #[test]
+    fn test_n_ary_mod_add() {
+        let mut ctx = STPContext::new();
+        for symbol in ["a", "b", "c"] {
+            ctx.calculate_energy(&define(symbol, "Odd"));
+        }
+        ctx.calculate_energy(&define("odd_sum", "Odd"));
+        ctx.calculate_energy(&define("even_sum", "Even"));
+
+        // Odd + Odd + Odd = Odd
+        assert_eq!(ctx.calculate_energy(&apply("ModAdd", &["a", "b", "c"], "odd_sum")), 0.0);
+        assert_eq!(ctx.calculate_energy(&apply("ModAdd", &["a", "b", "c"], "even_sum")), 2.0);
+        // 未定义的符号仍视为未知
+        assert_eq!(ctx.calculate_energy(&apply("ModAdd", &["a", "b", "ghost"], "even_sum")), 0.0);
+        // 一元算子不接受多个输入
+        assert_eq!(ctx.calculate_energy(&apply("Not", &["a", "b"], "even_sum")), 0.0);
+    }
+
+    #[test]
+    fn test_boolean_operators() {
+        let mut ctx = STPContext::new();
+        ctx.calculate_energy(&define("p", "True"));
+        ctx.calculate_energy(&define("q", "False"));
+        ctx.calculate_energy(&define("t", "True"));
+        ctx.calculate_energy(&define("f", "False"));
+
+        assert_eq!(ctx.calculate_energy(&apply("And", &["p", "q"], "f")), 0.0);
+        assert_eq!(ctx.calculate_energy(&apply("Or", &["p", "q"], "t")), 0.0);
+        assert_eq!(ctx.calculate_energy(&apply("Not", &["q"], "t")), 0.0);
+        assert!(ctx.calculate_energy(&apply("And", &["p", "q"], "t")) > 0.0);
+    }
+
+    #[test]
+    fn test_unknown_symbol_is_not_penalized() {
+        let mut ctx = STPContext::new();
+        ctx.calculate_energy(&define("n", "Odd"));
+        ctx.calculate_energy(&define("sum", "Odd"));
+        assert_eq!(ctx.calculate_energy(&apply("ModAdd", &["n", "ghost"], "sum")), 0.0);
+    }
+
+    #[test]
+    fn test_assert_checks_stored_vector() {
+        let mut ctx = STPContext::new();
+        ctx.calculate_energy(&define("sum", "Even"));
+
+        let assert_action = |relation: &str, object: &str| ProofAction::Assert {
+            subject: "sum".to_string(),
+            relation: relation.to_string(),
+            object: object.to_string(),
+        };
+
+        assert_eq!(ctx.calculate_energy(&assert_action("IsEven", "True")), 0.0);
+        assert_eq!(ctx.calculate_energy(&assert_action("IsOdd", "False")), 0.0);
+        assert!(ctx.calculate_energy(&assert_action("IsOdd", "True")) > 0.0);
+        assert_eq!(ctx.calculate_energy(&ProofAction::QED), 0.0);
+    }
+
+    #[test]
+    fn test_energy_report_names_the_conflict() {
+        let mut ctx = STPContext::new();
+        ctx.calculate_energy(&define("n", "Odd"));
+        ctx.calculate_energy(&define("m", "Odd"));
+        ctx.calculate_energy(&define("sum_truth", "Odd"));
+
+        let report = ctx.calculate_energy_detailed(&apply("ModAdd", &["n", "m"], "sum_truth"));
+        assert_eq!(report.energy, 2.0);
+        assert_eq!(report.conflict.as_deref(), Some("ModAdd(n,m) expected Even but sum_truth declared Odd"));
+
+        ctx.calculate_energy(&define("sum_truth", "Even"));
+        let report = ctx.calculate_energy_detailed(&apply("ModAdd", &["n", "m"], "sum_truth"));
+        assert_eq!(report, EnergyReport { energy: 0.0, conflict: None });
+    }
+}
@@ -3,7 +3,7 @@
 use crate::phase3::evolution::gene::{ProbeGene, ProbeState};
 use crate::phase3::evolution::mutagen::{BiasVapo, PrimeAdaptive};
 use crate::phase3::core::neuron::HTPNeuron;
-use crate::phase3::core::affine::AffineTuple;
+use crate::phase3::core::affine::{AffineTuple, MAX_CHUNK_P_BITS};
 use crate::phase3::core::algebra::ClassGroupElement;
 use crate::phase3::decoder::InverseDecoder;
 
@@ -34,9 +34,13 @@ pub struct EvolutionaryEngine {
     visited_hashes: HashSet<u64>,
 
     /// [Parameters]
+    /// 可接受的最大漂移 (单位: 晶格格点的曼哈顿距离)，0.0 表示只接受精确解
     precision_target: f64, 
     max_generations: usize,
     target_token_id: Option<u32>, // 当前搜索的目标 Token
+
+    /// [Telemetry]: Prime 轨因 P 因子逼近熔断而切换到流式演化的次数
+    pub streaming_fallbacks: u64,
 }
 
 impl EvolutionaryEngine {
@@ -44,12 +48,13 @@ impl EvolutionaryEngine {
         neuron: Arc<RwLock<HTPNeuron>>, 
         vocab_size: u32
     ) -> Self {
-        // 假设 vocab tensor 维度为 4
-        let dims = 4;
+        // Bias 维度与解码器的词表坐标维度一致
+        let decoder = InverseDecoder::new(vocab_size);
+        let dims = decoder.vocab_tensor.dimensions;
         
         EvolutionaryEngine {
             neuron_core: neuron,
-            decoder: InverseDecoder::new(vocab_size),
+            decoder,
             bias_mutator: BiasVapo::new(dims),
             prime_mutator: PrimeAdaptive::new(),
             search_queue: BinaryHeap::new(),
@@ -57,6 +62,7 @@ impl EvolutionaryEngine {
             precision_target: 0.0, 
             max_generations: 1000,
             target_token_id: None,
+            streaming_fallbacks: 0,
         }
     }
 
@@ -65,6 +71,19 @@ impl EvolutionaryEngine {
         self.target_token_id = Some(target_id);
     }
 
+    /// 🎯 设置精度目标：接受解码漂移不超过 `drift` 个晶格格点 (曼哈顿距离) 的解
+    /// 默认 0.0 只接受零漂移的精确解；噪声较大的嵌入可能永远无法精确命中，
+    /// 此时可放宽到一个小的正值。`drift` 必须是有限的非负数。
+    pub fn set_precision_target(&mut self, drift: f64) {
+        assert!(drift.is_finite() && drift >= 0.0, "precision target must be finite and >= 0, got {}", drift);
+        self.precision_target = drift;
+    }
+
+    /// 当前的精度目标 (晶格格点)
+    pub fn precision_target(&self) -> f64 {
+        self.precision_target
+    }
+
     /// 🌪️ 主要进化循环：寻找真理
     pub fn evolve_until_optimality(&mut self, initial_state: AffineTuple) -> Result<ProbeGene, String> {
         self.seed_population(initial_state);
@@ -79,49 +98,17 @@ impl EvolutionaryEngine {
             let parent_gene = &parent_state.gene;
 
             // 撞墙检测与目标检查
-            match self.decoder.decode_with_bias(&parent_gene.current_state, &parent_gene.bias_vector) {
-                Ok(result) => {
-                    if (result.drift as f64) <= self.precision_target {
-                        // 如果设置了特定目标 ID，还需检查 ID 是否匹配
-                        if let Some(tid) = self.target_token_id {
-                            if result.token_id == tid {
-                                println!("🏆 Truth Found! Gen: {}, Depth: {}", generation, parent_gene.depth);
-                                return Ok(parent_gene.clone());
-                            }
-                        } else {
-                            // 无特定目标，只求无漂移
-                            return Ok(parent_gene.clone());
-                        }
-                    }
+            match self.reaches_target(parent_gene) {
+                Some(true) => {
+                    println!("🏆 Truth Found! Gen: {}, Depth: {}", generation, parent_gene.depth);
+                    return Ok(parent_gene.clone());
                 },
-                Err(_) => {
-                    continue; // 撞墙，放弃该分支
-                }
+                Some(false) => {},
+                None => continue, // 撞墙，放弃该分支
             }
 
-            // 裂变：生成子代
-            let offspring = self.spawn_offspring(parent_gene);
-
-            // 评估并入队
-            for (child, mut_meta) in offspring {
-                if let Some((scored_child, reward)) = self.evaluate_fitness(child) {
-                    self.search_queue.push(scored_child);
-                    
-                    // 🔥 反馈回路 (Feedback Loop)
-                    // 根据子代的表现，反向更新突变器的参数
-                    match mut_meta {
-                        MutationType::Bias { level } => {
-                            self.bias_mutator.update_feedback(level, reward);
-                        },
-                        MutationType::Prime { strategy } => {
-                            // 简单的二值奖励：如果 fitness 较高则算成功
-                            // 这里阈值设为 0.5 仅作示例
-                            let success = reward > 0.5;
-                            self.prime_mutator.update_stats(strategy, success);
-                        }
-                    }
-                }
-            }
+            // 裂变：生成子代，评估并入队
+            self.expand(parent_gene);
 
             generation += 1;
         }
@@ -129,22 +116,141 @@ impl EvolutionaryEngine {
         Err("Extinction.".to_string())
     }
 
-    fn seed_population(&mut self, initial_state: AffineTuple) {
-        let seed = ProbeGene {
-            p_weight: Integer::from(1), 
-            bias_vector: vec![0; 4],
+    /// ⏳ Anytime 进化：总是返回迄今为止适应度最高的基因
+    /// 即使精度目标从未达成 (超时或灭绝)，也不会返回错误，
+    /// 便于交互界面持续展示不断改进的最佳候选。
+    pub fn evolve_anytime(&mut self, initial_state: AffineTuple, max_generations: usize) -> ProbeGene {
+        let fallback = ProbeGene {
+            p_weight: Integer::from(1),
+            bias_vector: vec![0; self.decoder.vocab_tensor.dimensions],
             depth: 0,
-            current_state: initial_state,
+            current_state: initial_state.clone(),
         };
-        
-        // 初始扩散
-        // 此时还无法获得反馈，只进行生成
-        let offspring = self.spawn_offspring(&seed);
-        for (child, _) in offspring {
-             if let Some((scored, _)) = self.evaluate_fitness(child) {
-                 self.search_queue.push(scored);
-             }
+        self.seed_population(initial_state);
+
+        let mut best: Option<ProbeState> = self.search_queue.peek().cloned();
+        let mut generation = 0;
+
+        while let Some(parent_state) = self.search_queue.pop() {
+            if generation > max_generations {
+                break;
+            }
+
+            match self.reaches_target(&parent_state.gene) {
+                Some(true) => return parent_state.gene,
+                Some(false) => {},
+                None => continue,
+            }
+
+            self.expand(&parent_state.gene);
+
+            // 新入队的最优子代即为本代候选
+            if let Some(top) = self.search_queue.peek() {
+                if best.as_ref().map_or(true, |b| top.fitness_score > b.fitness_score) {
+                    best = Some(top.clone());
+                }
+            }
+
+            generation += 1;
+        }
+
+        best.map(|s| s.gene).unwrap_or(fallback)
+    }
+
+    /// 🎯 目标检查: Some(true) 命中, Some(false) 未命中, None 撞墙 (解码失败)
+    fn reaches_target(&self, gene: &ProbeGene) -> Option<bool> {
+        let result = self.decoder.decode_with_bias(&gene.current_state, &gene.bias_vector).ok()?;
+        if (result.drift as f64) > self.precision_target {
+            return Some(false);
+        }
+        // 如果设置了特定目标 ID，还需检查 ID 是否匹配；否则只求无漂移
+        Some(self.target_token_id.map_or(true, |tid| result.token_id == tid))
+    }
+
+    /// 🌱 裂变一个父代：生成子代、评估入队，并把奖励反馈给突变器
+    fn expand(&mut self, parent_gene: &ProbeGene) {
+        let offspring = self.spawn_offspring(parent_gene);
+
+        for (child, mut_meta) in offspring {
+            if let Some((scored_child, reward)) = self.evaluate_fitness(child) {
+                self.search_queue.push(scored_child);
+                
+                // 🔥 反馈回路 (Feedback Loop)
+                // 根据子代的表现，反向更新突变器的参数
+                match mut_meta {
+                    MutationType::Bias { level } => {
+                        self.bias_mutator.update_feedback(level, reward);
+                    },
+                    MutationType::Prime { strategy } => {
+                        // 简单的二值奖励：如果 fitness 较高则算成功
+                        // 这里阈值设为 0.5 仅作示例
+                        let success = reward > 0.5;
+                        self.prime_mutator.update_stats(strategy, success);
+                    }
+                }
+            }
+        }
+    }
+
+    fn seed_population(&mut self, initial_state: AffineTuple) {
+        self.seed_population_multi(initial_state, &[Integer::from(1)]);
+    }
+
+    /// 🌱 多根播种：从一组起始素数同时扩散，拓宽优先队列的初始前沿
+    /// 每个种子都以相同的初始状态出发，各自裂变出子代。
+    pub fn seed_population_multi(&mut self, initial_state: AffineTuple, seeds: &[Integer]) {
+        for p in seeds {
+            let seed = ProbeGene {
+                p_weight: p.clone(), 
+                bias_vector: vec![0; self.decoder.vocab_tensor.dimensions],
+                depth: 0,
+                current_state: initial_state.clone(),
+            };
+            
+            // 初始扩散
+            // 此时还无法获得反馈，只进行生成
+            let offspring = self.spawn_offspring(&seed);
+            for (child, _) in offspring {
+                 if let Some((scored, _)) = self.evaluate_fitness(child) {
+                     self.search_queue.push(scored);
+                 }
+            }
+        }
+    }
+
+    /// 💾 导出搜索前沿 (按适应度从高到低)，用于持久化长时间进化
+    pub fn dump_frontier(&self) -> Vec<ProbeGene> {
+        self.search_queue.clone()
+            .into_sorted_vec()
+            .into_iter()
+            .rev()
+            .map(|s| s.gene)
+            .collect()
+    }
+
+    /// 📂 载入搜索前沿
+    /// 目标可能已改变，因此每个基因都会经 `evaluate_fitness` 重新打分；
+    /// 无法解码的基因被丢弃。返回实际入队的数量。
+    pub fn load_frontier(&mut self, genes: Vec<ProbeGene>) -> usize {
+        let mut loaded = 0;
+        for gene in genes {
+            if let Some((scored, _)) = self.evaluate_fitness(gene) {
+                self.search_queue.push(scored);
+                loaded += 1;
+            }
         }
+        loaded
+    }
+
+    /// 🔢 前 n 个素数 (2, 3, 5, ...)，作为 `seed_population_multi` 的默认种子集
+    pub fn first_primes(n: usize) -> Vec<Integer> {
+        let mut primes = Vec::with_capacity(n);
+        let mut p = Integer::from(1);
+        for _ in 0..n {
+            p = p.next_prime();
+            primes.push(p.clone());
+        }
+        primes
     }
 
     /// 🧬 修正后的 spawn_offspring
@@ -181,13 +287,25 @@ impl EvolutionaryEngine {
         let strat = self.prime_mutator.select_strategy();
         let new_p = self.prime_mutator.generate(strat, &parent.p_weight);
 
+        let mut streamed = false;
         if let Ok(neuron_guard) = self.neuron_core.read() {
-            let p_op = AffineTuple {
-                p_factor: new_p.clone(),
-                q_shift: ClassGroupElement::identity(&neuron_guard.discriminant),
+            let d = &neuron_guard.discriminant;
+            let identity = ClassGroupElement::identity(d);
+
+            // [Fuse Guard]: 若累积的 P 因子即将触发 MAX_CHUNK_P_BITS 熔断，
+            // 则该谱系切换为流式形式 S' = S^p * 1 (P 重置为 1)，状态规模保持恒定。
+            let p_bits_new = parent.current_state.p_factor.significant_bits() + new_p.significant_bits();
+            let next_state = if p_bits_new > MAX_CHUNK_P_BITS {
+                streamed = true;
+                parent.current_state.q_shift
+                    .apply_affine(&new_p, &identity, d)
+                    .map(AffineTuple::checkpoint)
+            } else {
+                let p_op = AffineTuple { p_factor: new_p.clone(), q_shift: identity };
+                parent.current_state.compose(&p_op, d)
             };
 
-            if let Ok(new_state) = parent.current_state.compose(&p_op, &neuron_guard.discriminant) {
+            if let Ok(new_state) = next_state {
                 offspring.push((
                     ProbeGene {
                         p_weight: new_p,
@@ -199,6 +317,9 @@ impl EvolutionaryEngine {
                 ));
             }
         }
+        if streamed {
+            self.streaming_fallbacks += 1;
+        }
 
         offspring
     }
@@ -239,14 +360,8 @@ impl EvolutionaryEngine {
             cpl_score = (cpl as f64) / 20.0; // Normalize to [0, 1]
         }
 
-        // 3. 综合 Fitness
-        let drift_score = 1.0 / (1.0 + res.drift as f64);
-
-        // 避免无限追深：对深度加入轻微惩罚
-        let depth_penalty = (gene.depth as f64) * 0.001;
-
-        // Drift 越小越好，CPL 越大越好
-        let fitness = drift_score * 0.7 + cpl_score * 0.3 - depth_penalty;
+        // 3. 综合 Fitness (非有限值直接淘汰，不进入优先队列)
+        let fitness = combine_fitness(res.confidence, cpl_score, gene.depth)?;
 
         Some((
             ProbeState { gene, fitness_score: fitness },
@@ -257,8 +372,185 @@ impl EvolutionaryEngine {
 
 }
 
+/// 综合 Fitness = 解码置信度 * 0.7 + 目标 CPL * 0.3 - 深度惩罚
+/// `confidence` 即 `DecodeResult::confidence` (已随漂移单调下降)。
+/// 结果为 NaN / 无穷时返回 None，防止污染 BinaryHeap。
+fn combine_fitness(confidence: f64, cpl_score: f64, depth: usize) -> Option<f64> {
+    // 避免无限追深：对深度加入轻微惩罚
+    let depth_penalty = (depth as f64) * 0.001;
+
+    // 置信度越高越好，CPL 越大越好
+    let fitness = confidence * 0.7 + cpl_score * 0.3 - depth_penalty;
+    fitness.is_finite().then_some(fitness)
+}
+
 /// 辅助枚举，用于记录突变类型以便反馈
 enum MutationType {
     Bias { level: usize },
     Prime { strategy: u8 },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiny_engine() -> EvolutionaryEngine {
+        let d = Integer::from(-23);
+        let neuron = Arc::new(RwLock::new(HTPNeuron::new(Integer::from(2), 2, 4, d)));
+        EvolutionaryEngine::new(neuron, 8)
+    }
+
+    #[test]
+    fn test_evolve_anytime_returns_best_on_unsatisfiable_target() {
+        let d = Integer::from(-23);
+        let mut engine = tiny_engine();
+        engine.set_target(u32::MAX); // 词表外，永远无法命中
+        engine.max_generations = 5;
+
+        assert!(engine.evolve_until_optimality(AffineTuple::identity(&d)).is_err());
+
+        let mut engine = tiny_engine();
+        engine.set_target(u32::MAX);
+        let best = engine.evolve_anytime(AffineTuple::identity(&d), 5);
+
+        let (best_state, _) = engine.evaluate_fitness(best).expect("best gene must decode");
+        // 队列中剩余的候选不可能优于返回的最佳基因
+        for remaining in engine.search_queue.iter() {
+            assert!(remaining.fitness_score <= best_state.fitness_score + 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_precision_target_accepts_near_solution() {
+        let d = Integer::from(-23);
+        let mut engine = tiny_engine();
+        engine.set_target(1);
+        assert_eq!(engine.precision_target(), 0.0);
+
+        // 单位元投影到 Token 1 的坐标，Bias 沿第 1 维推开 2 格
+        let gene = ProbeGene {
+            p_weight: Integer::from(1),
+            bias_vector: vec![0, 2, 0, 0],
+            depth: 0,
+            current_state: AffineTuple::identity(&d),
+        };
+        assert_eq!(engine.reaches_target(&gene), Some(false));
+
+        engine.set_precision_target(2.0);
+        assert_eq!(engine.reaches_target(&gene), Some(true));
+
+        // 放宽精度不影响 Token 匹配
+        engine.set_target(2);
+        assert_eq!(engine.reaches_target(&gene), Some(false));
+    }
+
+    #[test]
+    fn test_fallback_gene_matches_decoder_dimensions() {
+        use crate::phase3::decoder::{VocabularyTensor, DEFAULT_TOKEN_PRIME_BITS};
+
+        let d = Integer::from(-23);
+        let mut engine = tiny_engine();
+        // 空词表: 所有解码都失败，前沿为空，只能返回保底基因
+        engine.decoder = InverseDecoder {
+            vocab_tensor: VocabularyTensor::new(0, 3, 32, DEFAULT_TOKEN_PRIME_BITS),
+            search_radius: 5,
+        };
+
+        let best = engine.evolve_anytime(AffineTuple::identity(&d), 3);
+        assert_eq!(best.bias_vector, vec![0; 3]);
+        assert_eq!(best.depth, 0);
+    }
+
+    #[test]
+    fn test_non_finite_fitness_is_rejected() {
+        assert!(combine_fitness(1.0, f64::NAN, 0).is_none());
+        assert!(combine_fitness(0.25, f64::INFINITY, 1).is_none());
+        assert!(combine_fitness(f64::NAN, 0.5, 0).is_none());
+
+        // 零置信度得到有限的最低分，仍然保留
+        assert_eq!(combine_fitness(0.0, 0.0, 0), Some(0.0));
+        assert_eq!(combine_fitness(1.0, 1.0, 0), Some(1.0));
+    }
+
+    #[test]
+    fn test_deep_lineage_switches_to_streaming() {
+        let d = Integer::from(-23);
+        let mut engine = tiny_engine();
+        let q = ClassGroupElement { a: Integer::from(2), b: Integer::from(1), c: Integer::from(3) };
+
+        // P 因子已贴近熔断上限，下一次 compose 必然溢出
+        let mut parent = ProbeGene {
+            p_weight: Integer::from(3),
+            bias_vector: vec![0; 4],
+            depth: 100,
+            current_state: AffineTuple {
+                p_factor: (Integer::from(1) << (MAX_CHUNK_P_BITS - 1)) + 1u32,
+                q_shift: q,
+            },
+        };
+        assert!(parent.current_state.compose(&parent.current_state, &d).is_err());
+
+        for generation in 1..=3u64 {
+            let offspring = engine.spawn_offspring(&parent);
+            let (child, _) = offspring.into_iter()
+                .find(|(_, m)| matches!(m, MutationType::Prime { .. }))
+                .expect("prime lineage must keep evolving");
+
+            let expected = parent.current_state.q_shift.pow(&child.p_weight, &d).unwrap();
+            assert_eq!(child.current_state.q_shift, expected);
+            if generation == 1 {
+                assert_eq!(child.current_state.p_factor, Integer::from(1));
+                assert_eq!(engine.streaming_fallbacks, 1);
+            }
+            parent = child;
+        }
+        // 重置后的谱系重新走普通 compose 路径
+        assert_eq!(engine.streaming_fallbacks, 1);
+    }
+
+    #[test]
+    fn test_frontier_round_trip_preserves_best() {
+        let d = Integer::from(-23);
+        let mut engine = tiny_engine();
+        engine.set_target(3);
+        engine.seed_population_multi(AffineTuple::identity(&d), &EvolutionaryEngine::first_primes(2));
+
+        let frontier = engine.dump_frontier();
+        assert_eq!(frontier.len(), engine.search_queue.len());
+        let json = serde_json::to_string(&frontier).unwrap();
+        let restored: Vec<ProbeGene> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, frontier);
+
+        let mut resumed = tiny_engine();
+        resumed.set_target(3);
+        assert_eq!(resumed.load_frontier(restored), frontier.len());
+
+        let best = engine.search_queue.peek().unwrap();
+        let resumed_best = resumed.search_queue.peek().unwrap();
+        assert_eq!(resumed_best.fitness_score, best.fitness_score);
+        // 导出顺序以最佳候选开头
+        let (_, head_fitness) = engine.evaluate_fitness(frontier[0].clone()).unwrap();
+        assert_eq!(head_fitness, best.fitness_score);
+    }
+
+    #[test]
+    fn test_seed_population_multi_spawns_from_each_seed() {
+        let d = Integer::from(-23);
+        let mut engine = tiny_engine();
+        // 放宽搜索半径，保证 Bias 轨子代全部可解码
+        engine.decoder.search_radius = 64;
+
+        let seeds = EvolutionaryEngine::first_primes(3);
+        assert_eq!(seeds, vec![Integer::from(2), Integer::from(3), Integer::from(5)]);
+
+        engine.seed_population_multi(AffineTuple::identity(&d), &seeds);
+
+        // Bias 轨子代保留父代的 P，可据此追溯来源
+        for seed in &seeds {
+            let derived = engine.search_queue.iter()
+                .filter(|s| &s.gene.p_weight == seed && s.gene.depth == 1)
+                .count();
+            assert!(derived >= 3, "seed {} spawned only {} offspring", seed, derived);
+        }
+    }
+}
@@ -3,11 +3,13 @@
 use rug::Integer;
 use crate::phase3::core::affine::AffineTuple;
 use std::cmp::Ordering;
+use serde::{Serialize, Deserialize};
 
 /// 🧬 ProbeGene: 探针基因
 /// 代表一个在代数空间中探索的“个体”。
 /// 它携带了到达当前位置的完整逻辑链条 (Logic DNA) 和微调参数 (Control DNA)。
-#[derive(Clone, Debug, Eq, PartialEq)]
+/// 支持 serde，以便对搜索前沿做 Checkpoint 并断点续跑。
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ProbeGene {
     /// [Logic DNA]: 核心语义素数 (P_weight)
     /// 决定了逻辑的“大方向” (因果、转折、递进...)
@@ -39,24 +41,101 @@ pub struct ProbeState {
     pub fitness_score: f64,
 }
 
+/// 适应度比较：NaN 一律视为最低优先级 (两个 NaN 相等)，其余按数值比较
+/// 保证全序，NaN 不会破坏 BinaryHeap 的堆序。
+fn fitness_cmp(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+    }
+}
+
 // 实现大根堆排序：适应度高的排前面
+// 适应度相同时按 (更浅的 depth, 更小的 p_weight, 更小的 q_shift) 裁决，
+// 保证 BinaryHeap 的弹出顺序确定、可复现。
 impl Ord for ProbeState {
     fn cmp(&self, other: &Self) -> Ordering {
-        // f64 不实现 Ord，所以我们需要 partial_cmp 并处理 NaN
-        self.partial_cmp(other).unwrap_or(Ordering::Equal)
+        fitness_cmp(self.fitness_score, other.fitness_score)
+            .then_with(|| other.gene.depth.cmp(&self.gene.depth))
+            .then_with(|| other.gene.p_weight.cmp(&self.gene.p_weight))
+            .then_with(|| other.gene.current_state.q_shift.cmp(&self.gene.current_state.q_shift))
     }
 }
 
 impl PartialOrd for ProbeState {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.fitness_score.partial_cmp(&other.fitness_score)
+        Some(self.cmp(other))
     }
 }
 
 impl PartialEq for ProbeState {
     fn eq(&self, other: &Self) -> bool {
-        self.fitness_score == other.fitness_score
+        self.cmp(other) == Ordering::Equal
     }
 }
 
 impl Eq for ProbeState {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phase3::core::algebra::ClassGroupElement;
+    use std::collections::BinaryHeap;
+
+    fn state(p: u32, depth: usize, q: (i32, i32, i32)) -> ProbeState {
+        ProbeState {
+            gene: ProbeGene {
+                p_weight: Integer::from(p),
+                bias_vector: vec![0; 4],
+                depth,
+                current_state: AffineTuple {
+                    p_factor: Integer::from(1),
+                    q_shift: ClassGroupElement { a: Integer::from(q.0), b: Integer::from(q.1), c: Integer::from(q.2) },
+                },
+            },
+            fitness_score: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_equal_fitness_pops_in_fixed_order() {
+        let states = vec![
+            state(3, 2, (1, 1, 6)),
+            state(2, 2, (2, 1, 3)),
+            state(2, 2, (2, -1, 3)),
+            state(5, 1, (1, 1, 6)),
+            state(7, 3, (1, 1, 6)),
+        ];
+        let expected: Vec<(u32, usize, i32)> = vec![(5, 1, 1), (2, 2, -1), (2, 2, 1), (3, 2, 1), (7, 3, 1)];
+
+        // 不同的入堆顺序必须得到相同的弹出顺序
+        for rotation in 0..states.len() {
+            let mut heap = BinaryHeap::new();
+            for s in states.iter().cycle().skip(rotation).take(states.len()) {
+                heap.push(s.clone());
+            }
+            let popped: Vec<(u32, usize, i32)> = std::iter::from_fn(|| heap.pop())
+                .map(|s| (s.gene.p_weight.to_u32().unwrap(), s.gene.depth, s.gene.current_state.q_shift.b.to_i32().unwrap()))
+                .collect();
+            assert_eq!(popped, expected);
+        }
+    }
+
+    #[test]
+    fn test_nan_fitness_sinks_to_the_bottom() {
+        let fitness = [0.2, f64::NAN, 0.9, f64::NEG_INFINITY, f64::NAN, 0.5];
+        let mut heap = BinaryHeap::new();
+        for (i, f) in fitness.iter().enumerate() {
+            let mut s = state(i as u32 + 2, 1, (1, 1, 6));
+            s.fitness_score = *f;
+            heap.push(s);
+        }
+
+        let popped: Vec<f64> = std::iter::from_fn(|| heap.pop()).map(|s| s.fitness_score).collect();
+        assert_eq!(&popped[..4], &[0.9, 0.5, 0.2, f64::NEG_INFINITY]);
+        assert!(popped[4..].iter().all(|f| f.is_nan()));
+        assert_eq!(fitness_cmp(f64::NAN, f64::NAN), Ordering::Equal);
+    }
+}
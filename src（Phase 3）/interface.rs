@@ -79,6 +79,7 @@ impl PyEvolver {
             max_iterations: 50,      // 快速搜索限制
             initial_temperature: 1.5, // 较高的初始温度以跳出局部最优
             valuation_decay: 0.9,     // 快速冷却
+            ..VapoConfig::default()
         };
         PyEvolver {
             inner: EvolverEngine::new(Some(config)),
@@ -89,7 +90,8 @@ impl PyEvolver {
     /// 执行逻辑对齐
     /// Python 调用示例: 
     /// evolver.align(logits_list, "Prove sum of two Odds is Even")
-    fn align(&mut self, logits: Vec<f64>, context: String) -> PyResult<String> {
+    /// VAPO 循环期间释放 GIL，避免阻塞其他 Python 线程
+    fn align(&mut self, py: Python, logits: Vec<f64>, context: String) -> PyResult<String> {
         let decoder = SimpleRustDecoder { vocab_size: self.action_size };
         
         // [Lite Mode] 使用简单的非加密哈希生成种子，仅用于重现性
@@ -106,7 +108,11 @@ impl PyEvolver {
             seed: seed_gen, 
         };
 
-        match self.inner.align_generation(request, &decoder) {
+        // 释放区内不得触碰任何 Python 对象
+        let inner = &mut self.inner;
+        let result = py.allow_threads(move || inner.align_generation(request, &decoder));
+
+        match result {
             Ok(response) => {
                 // 返回 JSON 格式的纠偏结果
                 serde_json::to_string(&response)
@@ -4,13 +4,19 @@
 
 use pyo3::prelude::*;
 use pyo3::exceptions::PyValueError;
+use pyo3::types::PyDict;
 use rug::Integer; 
+use std::sync::{Arc, RwLock};
 
 use crate::control::bias_channel::{VapoConfig};
 use crate::interface::{EvolverEngine, ActionDecoder, CorrectionRequest};
-use crate::dsl::schema::ProofAction;
-use crate::crypto::algebra::ClassGroupElement;
+use crate::dsl::schema::{ProofAction, ProofBundle};
+// 使用完整 (Cohen) 合成的类群实现，原型版 crypto::algebra 无法合成非互素的形式
+use crate::phase3::core::algebra::ClassGroupElement;
 use crate::crypto::primes::hash_to_prime;
+use crate::phase3::core::neuron::HTPNeuron;
+use crate::phase3::core::affine::AffineTuple;
+use crate::phase3::evolution::engine::EvolutionaryEngine;
 
 mod dsl;
 mod control;
@@ -82,17 +88,46 @@ impl PyClassGroup {
             return Err(PyValueError::new_err("Discriminant mismatch! Cannot compose elements from different groups."));
         }
         let res = self.inner.compose(&other.inner, &self.d)
-            .map_err(|e| PyValueError::new_err(e))?;
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
         
         Ok(PyClassGroup { inner: res, d: self.d.clone() })
     }
 
+    /// 单位元 (主形式) 构造器
+    #[staticmethod]
+    fn identity(discriminant_str: String) -> PyResult<Self> {
+        let d = Integer::from_str_radix(&discriminant_str, 10)
+            .map_err(|e| PyValueError::new_err(format!("Invalid Integer: {}", e)))?;
+
+        Ok(PyClassGroup { inner: ClassGroupElement::identity(&d), d })
+    }
+
+    /// 由显式系数 (a, b, c) 构造并校验，用于加载外部参考实现的测试向量
+    #[staticmethod]
+    fn from_coeffs(a: String, b: String, c: String, discriminant_str: String) -> PyResult<Self> {
+        let d = Integer::from_str_radix(&discriminant_str, 10)
+            .map_err(|e| PyValueError::new_err(format!("Invalid Integer: {}", e)))?;
+
+        let elem = ClassGroupElement::from_str_coeffs(&a, &b, &c, &d)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(PyClassGroup { inner: elem, d })
+    }
+
+    fn is_identity(&self) -> bool {
+        self.inner.is_identity(&self.d)
+    }
+
+    /// 比较约化后的 (a, b, c)；不同判别式的元素永不相等
+    fn __eq__(&self, other: &PyClassGroup) -> bool {
+        self.d == other.d && self.inner == other.inner
+    }
+
     fn pow(&self, exp_str: String) -> PyResult<PyClassGroup> {
         let exp = Integer::from_str_radix(&exp_str, 10)
             .map_err(|e| PyValueError::new_err(format!("Invalid Exponent: {}", e)))?;
         
         let res = self.inner.pow(&exp, &self.d)
-            .map_err(|e| PyValueError::new_err(e))?;
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
             
         Ok(PyClassGroup { inner: res, d: self.d.clone() })
     }
@@ -120,6 +155,7 @@ impl PyEvolver {
             max_iterations: 50,
             initial_temperature: 1.5,
             valuation_decay: 0.9,
+            ..VapoConfig::default()
         };
         PyEvolver {
             inner: EvolverEngine::new(Some(config)),
@@ -129,7 +165,8 @@ impl PyEvolver {
 
     /// [Fix] 更新签名，接收 context 参数 (字符串)
     /// Python 调用示例: evolver.align(logits_list, "Prove X is Y")
-    fn align(&mut self, logits: Vec<f64>, context: String) -> PyResult<String> {
+    /// VAPO 循环期间释放 GIL，避免阻塞其他 Python 线程
+    fn align(&mut self, py: Python, logits: Vec<f64>, context: String) -> PyResult<String> {
         let decoder = SimpleRustDecoder { vocab_size: self.action_size };
         
         // [New] 生成一个随机种子或基于 context hash 的种子
@@ -144,7 +181,11 @@ impl PyEvolver {
             seed: seed,       // [New] 传入种子
         };
 
-        match self.inner.align_generation(request, &decoder) {
+        // 释放区内不得触碰任何 Python 对象
+        let inner = &mut self.inner;
+        let result = py.allow_threads(move || inner.align_generation(request, &decoder));
+
+        match result {
             Ok(response) => {
                 serde_json::to_string(&response)
                     .map_err(|e| PyValueError::new_err(format!("Serialization error: {}", e)))
@@ -153,6 +194,12 @@ impl PyEvolver {
         }
     }
     
+    /// 校验 Bundle 是否绑定到给定 context (BLAKE3)
+    fn verify_binding(&self, bundle_json: String, context: String) -> PyResult<bool> {
+        let bundle = PyProofBundle::from_json(bundle_json)?;
+        Ok(bundle.verify_binding(context))
+    }
+
     fn inject_context(&mut self, action_json: String) -> PyResult<()> {
         let action: ProofAction = serde_json::from_str(&action_json)
             .map_err(|e| PyValueError::new_err(format!("Invalid JSON: {}", e)))?;
@@ -162,6 +209,123 @@ impl PyEvolver {
     }
 }
 
+// =========================================================================
+// Python 类导出：PyProofBundle (只读视图)
+// =========================================================================
+
+#[pyclass]
+struct PyProofBundle {
+    inner: ProofBundle,
+}
+
+#[pymethods]
+impl PyProofBundle {
+    #[staticmethod]
+    fn from_json(bundle_json: String) -> PyResult<Self> {
+        let inner: ProofBundle = serde_json::from_str(&bundle_json)
+            .map_err(|e| PyValueError::new_err(format!("Invalid JSON: {}", e)))?;
+        Ok(PyProofBundle { inner })
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.inner)
+            .map_err(|e| PyValueError::new_err(format!("Serialization error: {}", e)))
+    }
+
+    #[getter]
+    fn bias_vector(&self) -> Vec<f64> {
+        self.inner.bias_vector.clone()
+    }
+
+    /// 以 JSON 形式返回逻辑动作
+    #[getter]
+    fn action(&self) -> PyResult<String> {
+        serde_json::to_string(&self.inner.action)
+            .map_err(|e| PyValueError::new_err(format!("Serialization error: {}", e)))
+    }
+
+    #[getter]
+    fn energy_signature(&self) -> f64 {
+        self.inner.energy_signature
+    }
+
+    #[getter]
+    fn context_hash(&self) -> String {
+        self.inner.context_hash.clone()
+    }
+
+    #[getter]
+    fn generator_seed(&self) -> u64 {
+        self.inner.generator_seed
+    }
+
+    fn verify_binding(&self, context: String) -> bool {
+        self.inner.verify_binding(&context)
+    }
+}
+
+// =========================================================================
+// Python 类导出：PyEvolutionEngine (完整进化搜索)
+// =========================================================================
+
+#[pyclass]
+struct PyEvolutionEngine {
+    inner: EvolutionaryEngine,
+    /// 与引擎共享的同一个神经元句柄 (引擎的 `neuron_core` 为私有字段)，
+    /// `evolve` 用它在搜索前校验初始状态的判别式。
+    neuron: Arc<RwLock<HTPNeuron>>,
+}
+
+#[pymethods]
+impl PyEvolutionEngine {
+    #[new]
+    fn new(weight_str: String, dim: usize, side_len: usize, discriminant_str: String, vocab_size: u32) -> PyResult<Self> {
+        let weight = Integer::from_str_radix(&weight_str, 10)
+            .map_err(|e| PyValueError::new_err(format!("Invalid Weight: {}", e)))?;
+        let d = Integer::from_str_radix(&discriminant_str, 10)
+            .map_err(|e| PyValueError::new_err(format!("Invalid Integer: {}", e)))?;
+
+        let neuron = HTPNeuron::try_new(weight, dim, side_len, d)
+            .map_err(|e| PyValueError::new_err(e))?;
+        let neuron = Arc::new(RwLock::new(neuron));
+        Ok(PyEvolutionEngine {
+            inner: EvolutionaryEngine::new(Arc::clone(&neuron), vocab_size),
+            neuron,
+        })
+    }
+
+    fn set_target(&mut self, tid: u32) {
+        self.inner.set_target(tid);
+    }
+
+    /// 从 JSON 编码的 `AffineTuple` 出发运行进化搜索
+    /// 返回 {"bias_vector": [...], "depth": int, "p_weight": str}
+    /// 灭绝 (Extinction) 或超时 (Timeout) 时抛出 ValueError
+    fn evolve(&mut self, py: Python, initial_state_json: String) -> PyResult<PyObject> {
+        let initial_state: AffineTuple = serde_json::from_str(&initial_state_json)
+            .map_err(|e| PyValueError::new_err(format!("Invalid JSON: {}", e)))?;
+
+        {
+            let neuron = self.neuron.read()
+                .map_err(|_| PyValueError::new_err("Neuron Lock Poisoned"))?;
+            let q = &initial_state.q_shift;
+            let state_d = Integer::from(&q.b * &q.b) - Integer::from(4) * &q.a * &q.c;
+            if state_d != neuron.discriminant {
+                return Err(PyValueError::new_err("Discriminant mismatch between initial state and neuron."));
+            }
+        }
+
+        let gene = self.inner.evolve_until_optimality(initial_state)
+            .map_err(|e| PyValueError::new_err(e))?;
+
+        let result = PyDict::new(py);
+        result.set_item("bias_vector", gene.bias_vector)?;
+        result.set_item("depth", gene.depth)?;
+        result.set_item("p_weight", gene.p_weight.to_string())?;
+        Ok(result.to_object(py))
+    }
+}
+
 // =========================================================================
 // 模块定义
 // =========================================================================
@@ -169,6 +333,53 @@ impl PyEvolver {
 fn new_evolver(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<PyEvolver>()?;
     m.add_class::<PyClassGroup>()?;
+    m.add_class::<PyEvolutionEngine>()?;
+    m.add_class::<PyProofBundle>()?;
     m.add_function(wrap_pyfunction!(py_hash_to_prime, m)?)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_py_class_group_equality() {
+        // Delta = -(2^61 - 1)
+        let d_str = (-((Integer::from(1) << 61) - 1u32)).to_string();
+        let g = PyClassGroup::new(d_str.clone()).unwrap();
+
+        // g.pow("2").compose(g.pow("3")) == g.pow("5")
+        let lhs = g.pow("2".to_string()).unwrap()
+            .compose(&g.pow("3".to_string()).unwrap()).unwrap();
+        assert!(lhs.__eq__(&g.pow("5".to_string()).unwrap()));
+        assert!(!lhs.__eq__(&g));
+
+        let e = PyClassGroup::identity(d_str).unwrap();
+        assert!(e.is_identity());
+        assert!(!g.is_identity());
+        assert!(g.pow("0".to_string()).unwrap().__eq__(&e));
+    }
+
+    #[test]
+    fn test_py_proof_bundle_round_trip() {
+        let bundle = ProofBundle {
+            bias_vector: vec![0.5, -0.25],
+            action: ProofAction::QED,
+            energy_signature: 0.0,
+            context_hash: ProofBundle::hash_context("Prove X is Y"),
+            generator_seed: 42,
+        };
+        let json = serde_json::to_string(&bundle).unwrap();
+
+        let py_bundle = PyProofBundle::from_json(json.clone()).unwrap();
+        assert_eq!(py_bundle.bias_vector(), vec![0.5, -0.25]);
+        assert_eq!(py_bundle.generator_seed(), 42);
+        assert_eq!(py_bundle.context_hash(), bundle.context_hash);
+        assert_eq!(py_bundle.to_json().unwrap(), json);
+        assert!(py_bundle.verify_binding("Prove X is Y".to_string()));
+        assert!(!py_bundle.verify_binding("Prove X is Z".to_string()));
+
+        assert!(PyProofBundle::from_json("{}".to_string()).is_err());
+    }
+}
@@ -23,8 +23,10 @@ fn main() {
         max_iterations: 100,
         initial_temperature: 2.0,
         valuation_decay: 0.95,
+        verbose: true,
+        ..VapoConfig::default()
     }));
-    println!("[Init] VAPO Controller ready (Bias Dim: 16)");
+    println!("[Init] VAPO Controller ready (Bias Dim: {})", control::bias_channel::DEFAULT_BIAS_DIM);
 
     // ------------------------------------------------------------------
     // 场景模拟：证明 "两个奇数之和是偶数"
@@ -82,10 +84,10 @@ fn main() {
         output_symbol: "sum_truth".to_string(),
     };
     
-    let energy = stp_ctx.calculate_energy(&check_action);
+    let report = stp_ctx.calculate_energy_detailed(&check_action);
 
-    if energy > 0.0 {
-        println!("   -> STP Check: VIOLATION detected! (Energy: {}, Odd + Odd != Odd)", energy);
+    if let Some(conflict) = report.conflict {
+        println!("   -> STP Check: VIOLATION detected! (Energy: {}, {})", report.energy, conflict);
     } else {
         println!("   -> STP Check: NO VIOLATION (Unexpected!).");
     }
@@ -118,7 +120,7 @@ fn main() {
     println!("\n🛡️  [VAPO] Bias Controller Engaging...");
 
     // 调用 optimize，传入 context 和 seed
-    let proof = controller.optimize(
+    let (proof, stats) = controller.optimize_with_stats(
         mission_context,
         execution_seed,
         &raw_logits, 
@@ -130,5 +132,6 @@ fn main() {
     println!("   -> Final Action: {:?}", proof.action);
     println!("   -> Applied Bias Vector: {:?}", proof.bias_vector);
     println!("   -> Context Hash: {}", proof.context_hash); // 验证绑定
+    println!("   -> Iterations: {} (rotations: {}, converged: {})", stats.iterations, stats.rotations, stats.converged);
     println!("   -> Logic is now ALIGNED.");
 }
@@ -5,9 +5,10 @@ use crate::phase3::core::algebra::ClassGroupElement;
 use rug::Integer;
 use serde::{Serialize, Deserialize};
 use blake3::Hasher;
+use std::collections::HashMap;
 
 /// 🌳 Merkle Inclusion Proof
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct MerkleProof {
     pub leaf_index: u64,
     pub leaf_hash: [u8; 32],
@@ -15,23 +16,42 @@ pub struct MerkleProof {
 }
 
 impl MerkleProof {
+    /// 🔨 为 `leaves[leaf_index]` 构建包含证明
+    /// 树形与 `merkle_root` 一致：奇数层末节点与自身配对。
+    pub fn build(leaves: &[[u8; 32]], leaf_index: u64) -> Option<Self> {
+        let idx = leaf_index as usize;
+        if idx >= leaves.len() {
+            return None;
+        }
+
+        let mut siblings = Vec::new();
+        let mut level = leaves.to_vec();
+        let mut index = idx;
+
+        while level.len() > 1 {
+            let sibling_index = if index % 2 == 0 { (index + 1).min(level.len() - 1) } else { index - 1 };
+            siblings.push(level[sibling_index]);
+            level = merkle_parent_level(&level);
+            index /= 2;
+        }
+
+        Some(MerkleProof {
+            leaf_index,
+            leaf_hash: leaves[idx],
+            siblings,
+        })
+    }
+
     pub fn verify(&self, global_root: &[u8; 32]) -> bool {
         let mut current_hash = self.leaf_hash;
         let mut index = self.leaf_index;
 
         for sibling in &self.siblings {
-            let mut hasher = Hasher::new();
-            hasher.update(b"HTP_MERKLE_NODE");
-
-            if index % 2 == 0 {
-                hasher.update(&current_hash);
-                hasher.update(sibling);
+            current_hash = if index % 2 == 0 {
+                hash_merkle_node(&current_hash, sibling)
             } else {
-                hasher.update(sibling);
-                hasher.update(&current_hash);
-            }
-            
-            current_hash = hasher.finalize().into();
+                hash_merkle_node(sibling, &current_hash)
+            };
             index /= 2;
         }
 
@@ -39,40 +59,130 @@ impl MerkleProof {
     }
 }
 
+fn hash_merkle_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Hasher::new();
+    hasher.update(b"HTP_MERKLE_NODE");
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+fn merkle_parent_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| hash_merkle_node(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
+}
+
+/// 👑 Checkpoint 日志的 Merkle Root (空日志为全零)
+pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return [0u8; 32];
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = merkle_parent_level(&level);
+    }
+    level[0]
+}
+
+/// 🧭 AnchorLayout: 正交锚点的投影几何
+/// Checkpoint 序号按 base-`side_length` 展开为 `dimensions` 维坐标
+/// (与 `HyperTensor::map_id_to_coord` 一致)，沿 `axes` 中每条轴投影。
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnchorLayout {
+    pub dimensions: usize,
+    pub side_length: usize,
+    pub axes: Vec<usize>,
+}
+
+impl AnchorLayout {
+    /// 第 `checkpoint_index` 个 Checkpoint 的锚点序号：保留该轴分量、其余分量归零后映射回日志序号。
+    /// 投影序号不大于原序号，因此总落在日志范围内。轴越界或边长为 0 时返回 `None`。
+    pub fn project(&self, checkpoint_index: u64) -> Option<Vec<u64>> {
+        if self.side_length == 0 {
+            return None;
+        }
+        let side = self.side_length as u64;
+        self.axes
+            .iter()
+            .map(|&axis| {
+                if axis >= self.dimensions {
+                    return None;
+                }
+                // side^axis 溢出时该分量必为 0
+                Some(match side.checked_pow(axis as u32) {
+                    Some(place) => (checkpoint_index / place % side) * place,
+                    None => 0,
+                })
+            })
+            .collect()
+    }
+}
+
 /// ⏭️ State Transition Proof
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct StateTransitionProof {
     pub checkpoint_state: ClassGroupElement,
     pub log_inclusion_proof: MerkleProof,
     pub replay_ops: Vec<AffineTuple>,
     pub claimed_final_state: ClassGroupElement,
+    /// 🧭 正交锚点：沿各轴投影坐标的 Checkpoint 包含证明
+    /// 与主路径共用同一 Global Root，伪造者必须同时伪造多条独立路径。
+    #[serde(default)]
+    pub orthogonal_anchors: Vec<MerkleProof>,
+    /// 锚点的投影几何；携带锚点时必需，验证者据此检查每条锚点的叶子序号
+    #[serde(default)]
+    pub anchor_layout: Option<AnchorLayout>,
 }
 
 impl StateTransitionProof {
     /// 🛡️ 执行跳表验证 (Security Patched)
     /// 这是 HTP 的“最高法院”，审判一切状态转移的合法性。
     pub fn verify(&self, global_merkle_root: &[u8; 32], discriminant: &Integer) -> bool {
+        let computed_leaf_hash = AffineTuple::checkpoint(self.checkpoint_state.clone()).leaf_hash();
+        self.verify_with_leaf_hash(&computed_leaf_hash, global_merkle_root, discriminant)
+    }
+
+    /// 📦 批量验证 (吞吐优化)
+    /// 相同 checkpoint_state 的叶子哈希只计算一次；开启 `parallel` feature 时并行重放。
+    /// 返回结果与输入顺序一一对应。
+    pub fn verify_batch(proofs: &[Self], global_merkle_root: &[u8; 32], discriminant: &Integer) -> Vec<bool> {
+        let mut leaf_cache: HashMap<&ClassGroupElement, [u8; 32]> = HashMap::new();
+        let leaf_hashes: Vec<[u8; 32]> = proofs
+            .iter()
+            .map(|p| *leaf_cache
+                .entry(&p.checkpoint_state)
+                .or_insert_with(|| AffineTuple::checkpoint(p.checkpoint_state.clone()).leaf_hash()))
+            .collect();
+
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            proofs
+                .par_iter()
+                .zip(leaf_hashes.par_iter())
+                .map(|(p, h)| p.verify_with_leaf_hash(h, global_merkle_root, discriminant))
+                .collect()
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        {
+            proofs
+                .iter()
+                .zip(leaf_hashes.iter())
+                .map(|(p, h)| p.verify_with_leaf_hash(h, global_merkle_root, discriminant))
+                .collect()
+        }
+    }
+
+    fn verify_with_leaf_hash(&self, computed_leaf_hash: &[u8; 32], global_merkle_root: &[u8; 32], discriminant: &Integer) -> bool {
         // [Fix Step 0]: Binding Check (状态-哈希绑定检查)
         // 边界一：身份绑定。
         // 验证者必须确信：这个 checkpoint_state 生成的哈希值，
         // 确实等于 Merkle Proof 中声称的 leaf_hash。
         // 这防止了“拿着真的 Proof 验证假的 State”的攻击。
-        
-        let mut hasher = Hasher::new();
-        hasher.update(b"HTP_LOG_ENTRY_V1"); // Tag 必须一致
-        
-        // 重构 Checkpoint Tuple (P 固定为 1, Q 为状态)
-        let p_one = Integer::from(1);
-        hasher.update(&p_one.to_digits(rug::integer::Order::Lsf));
-        
-        // Hash Q components (a, b, c)
-        hasher.update(&self.checkpoint_state.a.to_digits(rug::integer::Order::Lsf));
-        hasher.update(&self.checkpoint_state.b.to_digits(rug::integer::Order::Lsf));
-        hasher.update(&self.checkpoint_state.c.to_digits(rug::integer::Order::Lsf));
-        
-        let computed_leaf_hash: [u8; 32] = hasher.finalize().into();
-
-        if computed_leaf_hash != self.log_inclusion_proof.leaf_hash {
+        if *computed_leaf_hash != self.log_inclusion_proof.leaf_hash {
              println!("❌ Security Alert: Checkpoint State does not match the Merkle Proof.");
              return false;
         }
@@ -85,6 +195,28 @@ impl StateTransitionProof {
             return false;
         }
 
+        // [Fix Step 1b]: Cross-Axis Consistency (正交锚点)
+        // 若携带锚点，则每一条都必须指向主路径 Checkpoint 的投影序号，并收敛到同一个 Global Root。
+        // 否则任意一片合法叶子都能冒充锚点。
+        if !self.orthogonal_anchors.is_empty() || self.anchor_layout.is_some() {
+            let expected = self.anchor_layout.as_ref()
+                .and_then(|layout| layout.project(self.log_inclusion_proof.leaf_index));
+            let bound = expected.map_or(false, |indices| {
+                indices.len() == self.orthogonal_anchors.len()
+                    && indices.iter().zip(&self.orthogonal_anchors).all(|(idx, anchor)| *idx == anchor.leaf_index)
+            });
+            if !bound {
+                println!("❌ Verification Failed: Orthogonal anchors are not bound to the checkpoint projection.");
+                return false;
+            }
+        }
+        for (axis, anchor) in self.orthogonal_anchors.iter().enumerate() {
+            if !anchor.verify(global_merkle_root) {
+                println!("❌ Verification Failed: Orthogonal anchor #{} does not reach the global root.", axis);
+                return false;
+            }
+        }
+
         // [Fix Step 2]: Replay Evolution (重放演化)
         // 边界三：逻辑一致性。
         // 从起点出发，严格按照记录的步骤走，必须能走到终点。
@@ -112,7 +244,9 @@ impl StateTransitionProof {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// 📡 HTP 协议响应 (唯一权威定义)
+/// `core::neuron` 等所有生产者都必须构造此枚举，不得另行定义。
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum HtpResponse {
     ProofBundle {
         request_id: u64,
@@ -120,5 +254,96 @@ pub enum HtpResponse {
         log_epoch: u64,
     },
     Ack,
+    /// 协议层错误文本 (有意保留为 `String`：线上格式不绑定 `AlgebraError` 的枚举布局)
     Error(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint_leaf_hash(state: &ClassGroupElement) -> [u8; 32] {
+        AffineTuple::checkpoint(state.clone()).leaf_hash()
+    }
+
+    fn sample_proof() -> StateTransitionProof {
+        // Delta = -23 (class number 3)
+        let d = Integer::from(-23);
+        let state = ClassGroupElement::identity(&d);
+        let leaf = checkpoint_leaf_hash(&state);
+
+        StateTransitionProof {
+            checkpoint_state: state.clone(),
+            log_inclusion_proof: MerkleProof::build(&[leaf, [7u8; 32]], 0).unwrap(),
+            replay_ops: vec![AffineTuple::identity(&d)],
+            claimed_final_state: state,
+            orthogonal_anchors: vec![MerkleProof::build(&[leaf, [7u8; 32]], 0).unwrap()],
+            anchor_layout: Some(AnchorLayout { dimensions: 1, side_length: 2, axes: vec![0] }),
+        }
+    }
+
+    #[test]
+    fn test_htp_response_serde_round_trip() {
+        let variants = vec![
+            HtpResponse::ProofBundle { request_id: 42, proof: sample_proof(), log_epoch: 3 },
+            HtpResponse::Ack,
+            HtpResponse::Error("checkpoint missing".to_string()),
+        ];
+
+        for resp in variants {
+            let json = serde_json::to_string(&resp).unwrap();
+            let back: HtpResponse = serde_json::from_str(&json).unwrap();
+            assert_eq!(back, resp);
+        }
+    }
+
+    #[test]
+    fn test_leaf_hash_resists_field_splitting() {
+        let form = |a: u32, b: i32, c: u32| ClassGroupElement {
+            a: Integer::from(a),
+            b: Integer::from(b),
+            c: Integer::from(c),
+        };
+
+        // V1 下二者的字节流同为 [02 01 03 05]：a 与 b 的边界被移动
+        let left = form(0x0102, 3, 5);
+        let right = form(2, 0x0301, 5);
+        assert_ne!(checkpoint_leaf_hash(&left), checkpoint_leaf_hash(&right));
+
+        // 符号同样参与编码：(a, b, c) 与其逆 (a, -b, c)
+        assert_ne!(checkpoint_leaf_hash(&form(2, 1, 3)), checkpoint_leaf_hash(&form(2, -1, 3)));
+    }
+
+    #[test]
+    fn test_anchor_layout_projects_each_axis() {
+        let layout = AnchorLayout { dimensions: 3, side_length: 4, axes: vec![0, 1, 2] };
+        // 27 = 3 + 2*4 + 1*16 -> [3, 2, 1]
+        assert_eq!(layout.project(27), Some(vec![3, 8, 16]));
+        assert_eq!(layout.project(0), Some(vec![0, 0, 0]));
+
+        assert_eq!(AnchorLayout { dimensions: 2, side_length: 4, axes: vec![2] }.project(5), None);
+        assert_eq!(AnchorLayout { dimensions: 1, side_length: 0, axes: vec![0] }.project(5), None);
+    }
+
+    #[test]
+    fn test_verify_batch_preserves_order() {
+        let d = Integer::from(-23);
+        let valid = sample_proof();
+        let root = merkle_root(&[checkpoint_leaf_hash(&valid.checkpoint_state), [7u8; 32]]);
+
+        // 错误的最终状态: (2, 1, 3) 同属 Delta = -23，但不是重放结果
+        let mut wrong_final = valid.clone();
+        wrong_final.claimed_final_state = ClassGroupElement { a: Integer::from(2), b: Integer::from(1), c: Integer::from(3) };
+
+        // Checkpoint 与叶子哈希不匹配
+        let mut wrong_leaf = valid.clone();
+        wrong_leaf.checkpoint_state = wrong_final.claimed_final_state.clone();
+
+        let batch = vec![valid.clone(), wrong_final, valid, wrong_leaf];
+        let results = StateTransitionProof::verify_batch(&batch, &root, &d);
+
+        assert_eq!(results, vec![true, false, true, false]);
+        let singles: Vec<bool> = batch.iter().map(|p| p.verify(&root, &d)).collect();
+        assert_eq!(results, singles);
+    }
+}
@@ -1,9 +1,10 @@
 // COPYRIGHT (C) 2025 M-Patek. ALL RIGHTS RESERVED.
 
-use crate::core::affine::AffineTuple;
+use crate::core::affine::{AffineTuple, hash_integer_field};
 use crate::core::neuron::HTPNeuron;
-use crate::core::algebra::ClassGroupElement;
+use crate::core::algebra::{ClassGroupElement, validate_discriminant};
 use crate::core::primes::hash_to_prime;
+use crate::net::wire::HtpResponse;
 use rug::Integer;
 use std::sync::{Arc, RwLock};
 
@@ -29,16 +30,28 @@ impl EvolutionaryLayer {
     }
 
     /// 前向传播：Stream(In) -> [Neurons] -> Stream(Out)
+    /// 任一神经元返回 `HtpResponse::Error` 时立即中止，错误信息中带有该神经元的序号。
     pub fn forward(&self, input_stream: &[AffineTuple], recursion_depth: usize) -> Result<Vec<AffineTuple>, String> {
+        self.forward_with(input_stream, recursion_depth, HTPNeuron::activate)
+    }
+
+    /// 以给定的激活函数执行前向传播 (测试中可替换为桩实现)
+    fn forward_with<F>(&self, input_stream: &[AffineTuple], recursion_depth: usize, activate: F) -> Result<Vec<AffineTuple>, String>
+    where
+        F: Fn(&HTPNeuron, Vec<AffineTuple>, usize) -> Result<(AffineTuple, HtpResponse), String>,
+    {
         let mut output_stream = Vec::new();
 
         // ⚡ Parallel Activation
         // 在推理阶段，我们只需要获取 Read Lock (读锁)
-        for neuron_arc in &self.neurons {
+        for (idx, neuron_arc) in self.neurons.iter().enumerate() {
             let neuron_guard = neuron_arc.read().map_err(|_| "Neuron Lock Poisoned")?;
             
             // 激活神经元
-            let (root, _proof) = neuron_guard.activate(input_stream.to_vec(), recursion_depth)?;
+            let (root, response) = activate(&neuron_guard, input_stream.to_vec(), recursion_depth)?;
+            if let HtpResponse::Error(msg) = response {
+                return Err(format!("Neuron {} failed: {}", idx, msg));
+            }
             output_stream.push(root);
         }
 
@@ -46,6 +59,79 @@ impl EvolutionaryLayer {
     }
 }
 
+/// 🗃️ ForwardCache: 自回归解码用的前缀缓存
+/// 以 token 前缀的哈希为键，保存嵌入流与每层的输出流。
+/// 由于组合是非交换的，且每个神经元的输出依赖于完整输入流，
+/// 只有嵌入可以跨前缀复用；任何前缀变化都会使整个缓存失效。
+/// 注意：模型权重被突变后必须调用 `clear()`。
+#[derive(Default)]
+pub struct ForwardCache {
+    prefix_hash: Option<[u8; 32]>,
+    prefix_len: usize,
+    embedded: Vec<AffineTuple>,
+    layer_streams: Vec<Vec<AffineTuple>>,
+    root: Option<AffineTuple>,
+}
+
+impl ForwardCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// 缓存的逐层输出流
+    pub fn layer_streams(&self) -> &[Vec<AffineTuple>] {
+        &self.layer_streams
+    }
+
+    fn hash_prefix(token_ids: &[u32]) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(b"HTP_FORWARD_CACHE_V1");
+        for tid in token_ids {
+            hasher.update(&tid.to_le_bytes());
+        }
+        *hasher.finalize().as_bytes()
+    }
+
+    /// 缓存内容是否为 `token_ids` 的前缀
+    fn is_prefix_of(&self, token_ids: &[u32]) -> bool {
+        match self.prefix_hash {
+            Some(hash) => self.prefix_len <= token_ids.len()
+                && Self::hash_prefix(&token_ids[..self.prefix_len]) == hash,
+            None => false,
+        }
+    }
+}
+
+/// 📐 LayerSummary: 单层的结构概要
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerSummary {
+    pub width: usize,
+    /// 每个神经元权重素数的位宽
+    pub weight_bits: Vec<u32>,
+    /// 记忆张量的维度与边长 (取自该层第一个神经元)
+    pub memory_dimensions: usize,
+    pub memory_side_length: usize,
+}
+
+/// 📋 ModelSummary: `HTPModel::summary` 的聚合结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelSummary {
+    pub layers: Vec<LayerSummary>,
+    pub total_neurons: usize,
+    pub discriminant_bits: u32,
+}
+
+impl ModelSummary {
+    /// 可学习参数个数：每个神经元一个权重素数
+    pub fn num_parameters(&self) -> usize {
+        self.total_neurons
+    }
+}
+
 /// HTPModel: The Evolutionary Neural System
 pub struct HTPModel {
     pub layers: Vec<EvolutionaryLayer>,
@@ -53,17 +139,104 @@ pub struct HTPModel {
 }
 
 impl HTPModel {
+    /// 构造模型；判别式非法时 panic (见 `try_new`)
     pub fn new(layer_configs: Vec<(usize, usize, usize)>, discriminant: Integer) -> Self {
+        Self::try_new(layer_configs, discriminant)
+            .unwrap_or_else(|e| panic!("HTPModel::new: {}", e))
+    }
+
+    /// 构造模型，先校验判别式 (D < 0 且 D = 1 mod 4)
+    pub fn try_new(layer_configs: Vec<(usize, usize, usize)>, discriminant: Integer) -> Result<Self, String> {
+        validate_discriminant(&discriminant)?;
         let mut layers = Vec::new();
         for (width, dim, side_len) in layer_configs {
             layers.push(EvolutionaryLayer::new(width, dim, side_len, discriminant.clone()));
         }
-        HTPModel { layers, discriminant }
+        Ok(HTPModel { layers, discriminant })
+    }
+
+    /// 📋 结构概要：逐层读取每个神经元的权重位宽与记忆张量几何
+    /// 任一锁被毒化时返回错误，而不是 panic。
+    pub fn summary(&self) -> Result<ModelSummary, String> {
+        let mut layers = Vec::with_capacity(self.layers.len());
+        for (idx, layer) in self.layers.iter().enumerate() {
+            let mut weight_bits = Vec::with_capacity(layer.neurons.len());
+            let mut geometry = (0, 0);
+            for (n, neuron_arc) in layer.neurons.iter().enumerate() {
+                let neuron = neuron_arc.read()
+                    .map_err(|_| format!("Neuron Lock Poisoned (layer {}, neuron {})", idx, n))?;
+                weight_bits.push(neuron.p_weight.significant_bits());
+                if n == 0 {
+                    let memory = neuron.memory.read()
+                        .map_err(|_| format!("Memory Lock poisoned (layer {}, neuron {})", idx, n))?;
+                    geometry = (memory.dimensions, memory.side_length);
+                }
+            }
+            layers.push(LayerSummary {
+                width: layer.neurons.len(),
+                weight_bits,
+                memory_dimensions: geometry.0,
+                memory_side_length: geometry.1,
+            });
+        }
+
+        Ok(ModelSummary {
+            total_neurons: layers.iter().map(|l| l.width).sum(),
+            discriminant_bits: self.discriminant.significant_bits(),
+            layers,
+        })
+    }
+
+    /// 🧭 一致性检查：每个神经元必须与模型共用同一判别式
+    /// `discriminant` 是公开字段，漂移后的神经元会在最终折叠时才于 `reduce_form` 深处报错；
+    /// 这里提前给出指明层与神经元的错误信息。
+    pub fn assert_consistent(&self) -> Result<(), String> {
+        for (idx, layer) in self.layers.iter().enumerate() {
+            for (n, neuron_arc) in layer.neurons.iter().enumerate() {
+                let neuron = neuron_arc.read()
+                    .map_err(|_| format!("Neuron Lock Poisoned (layer {}, neuron {})", idx, n))?;
+                if neuron.discriminant != self.discriminant {
+                    return Err(format!(
+                        "Inconsistent model: neuron {} of layer {} uses discriminant {}, expected {}",
+                        n, idx, neuron.discriminant, self.discriminant
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 🔏 Model Fingerprint: 按层 / 神经元顺序对判别式与全部权重素数做 BLAKE3
+    /// 只覆盖结构性参数，推理留下的 `semantic_root` / `memory` 等瞬态状态不参与，
+    /// 分布式 worker 可借此低成本确认加载了同一个模型。
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(b"HTP_MODEL_FINGERPRINT_V1");
+        hash_integer_field(&mut hasher, &self.discriminant);
+        hasher.update(&(self.layers.len() as u64).to_le_bytes());
+        for layer in &self.layers {
+            hasher.update(&(layer.neurons.len() as u64).to_le_bytes());
+            for neuron_arc in &layer.neurons {
+                // 权重在毒化的锁里依然完整，指纹不因此失败
+                let neuron = neuron_arc.read().unwrap_or_else(|e| e.into_inner());
+                hash_integer_field(&mut hasher, &neuron.p_weight);
+            }
+        }
+        *hasher.finalize().as_bytes()
+    }
+
+    /// 可学习参数个数 (见 `ModelSummary::num_parameters`)
+    pub fn num_parameters(&self) -> Result<usize, String> {
+        Ok(self.summary()?.num_parameters())
     }
 
     pub fn embed(&self, token_ids: &[u32]) -> Result<Vec<AffineTuple>, String> {
-        let mut stream = Vec::new();
         let generator = ClassGroupElement::generator(&self.discriminant);
+        Self::embed_with(token_ids, &generator)
+    }
+
+    fn embed_with(token_ids: &[u32], generator: &ClassGroupElement) -> Result<Vec<AffineTuple>, String> {
+        let mut stream = Vec::new();
 
         for &tid in token_ids {
             let token_str = format!("tok_{}", tid);
@@ -77,17 +250,217 @@ impl HTPModel {
     }
 
     pub fn forward(&self, token_ids: &[u32]) -> Result<AffineTuple, String> {
+        self.assert_consistent()?;
+        let mut current_stream = self.embed(token_ids)?;
+
+        for (idx, layer) in self.layers.iter().enumerate() {
+            current_stream = layer.forward(&current_stream, idx)?;
+        }
+
+        let final_root = AffineTuple::compose_many(&current_stream, &self.discriminant)?;
+
+        Ok(final_root)
+    }
+
+    /// 🔬 Forward Trace: 返回最终根以及每层的输出流快照 (用于可解释性调试)
+    /// 额外的克隆开销仅存在于此变体，`forward` 不受影响。
+    pub fn forward_trace(&self, token_ids: &[u32]) -> Result<(AffineTuple, Vec<Vec<AffineTuple>>), String> {
         let mut current_stream = self.embed(token_ids)?;
+        let mut trace = Vec::with_capacity(self.layers.len());
 
         for (idx, layer) in self.layers.iter().enumerate() {
             current_stream = layer.forward(&current_stream, idx)?;
+            trace.push(current_stream.clone());
+        }
+
+        let final_root = AffineTuple::compose_many(&current_stream, &self.discriminant)?;
+
+        Ok((final_root, trace))
+    }
+
+    /// ⚡ Forward with Cache: 前缀未变时复用嵌入，序列完全相同时直接返回缓存的根
+    /// 结果与 `forward` 完全一致。
+    pub fn forward_with_cache(&self, token_ids: &[u32], cache: &mut ForwardCache) -> Result<AffineTuple, String> {
+        if !cache.is_prefix_of(token_ids) {
+            cache.clear();
         }
 
-        let mut final_root = AffineTuple::identity(&self.discriminant);
-        for tuple in current_stream {
-            final_root = final_root.compose(&tuple, &self.discriminant)?;
+        if cache.prefix_len == token_ids.len() {
+            if let Some(root) = &cache.root {
+                return Ok(root.clone());
+            }
         }
 
+        // 只嵌入新增的 token
+        if cache.embedded.len() < token_ids.len() {
+            let generator = match cache.embedded.first() {
+                Some(tuple) => tuple.q_shift.clone(),
+                None => ClassGroupElement::generator(&self.discriminant),
+            };
+            let suffix = Self::embed_with(&token_ids[cache.embedded.len()..], &generator)?;
+            cache.embedded.extend(suffix);
+        }
+
+        // 每层输出依赖完整输入流，必须重算
+        let mut layer_streams = Vec::with_capacity(self.layers.len());
+        let mut current_stream = cache.embedded.clone();
+        for (idx, layer) in self.layers.iter().enumerate() {
+            current_stream = layer.forward(&current_stream, idx)?;
+            layer_streams.push(current_stream.clone());
+        }
+
+        let final_root = AffineTuple::compose_many(&current_stream, &self.discriminant)?;
+
+        cache.prefix_hash = Some(ForwardCache::hash_prefix(token_ids));
+        cache.prefix_len = token_ids.len();
+        cache.layer_streams = layer_streams;
+        cache.root = Some(final_root.clone());
+
         Ok(final_root)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiny_model() -> HTPModel {
+        // Delta = -(2^61 - 1)
+        let discriminant = -((Integer::from(1) << 61) - 1u32);
+        HTPModel::new(vec![(2, 2, 4), (1, 2, 4)], discriminant)
+    }
+
+    #[test]
+    fn test_layer_forward_reports_failing_neuron() {
+        let discriminant = -((Integer::from(1) << 61) - 1u32);
+        let layer = EvolutionaryLayer::new(3, 2, 4, discriminant.clone());
+        let input = vec![AffineTuple::identity(&discriminant)];
+
+        // 桩神经元：第 1 个神经元返回错误响应，其余正常
+        let calls = std::cell::Cell::new(0);
+        let stub = |_: &HTPNeuron, _: Vec<AffineTuple>, _: usize| {
+            let idx = calls.get();
+            calls.set(idx + 1);
+            let response = if idx == 1 { HtpResponse::Error("checkpoint missing".to_string()) } else { HtpResponse::Ack };
+            Ok((AffineTuple::identity(&discriminant), response))
+        };
+
+        let err = layer.forward_with(&input, 0, stub).unwrap_err();
+        assert_eq!(err, "Neuron 1 failed: checkpoint missing");
+        // 出错后不再激活后续神经元
+        assert_eq!(calls.get(), 2);
+
+        let ok = layer.forward_with(&input, 0, |_, _, _| Ok((AffineTuple::identity(&discriminant), HtpResponse::Ack)));
+        assert_eq!(ok.unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_try_new_rejects_bad_discriminants() {
+        for bad in [Integer::from(5), Integer::from(0), Integer::from(-8), Integer::from(-6)] {
+            assert!(HTPModel::try_new(vec![(1, 2, 4)], bad).is_err());
+        }
+    }
+
+    #[test]
+    fn test_summary_reports_layer_geometry() {
+        let discriminant = -((Integer::from(1) << 61) - 1u32);
+        let model = HTPModel::new(vec![(3, 2, 4), (2, 3, 5)], discriminant);
+        let summary = model.summary().unwrap();
+
+        assert_eq!(summary.total_neurons, 5);
+        assert_eq!(model.num_parameters().unwrap(), 5);
+        assert_eq!(summary.discriminant_bits, 61);
+        assert_eq!(summary.layers.len(), 2);
+        assert_eq!(summary.layers[0].width, 3);
+        assert_eq!(summary.layers[1].width, 2);
+        assert_eq!((summary.layers[0].memory_dimensions, summary.layers[0].memory_side_length), (2, 4));
+        assert_eq!((summary.layers[1].memory_dimensions, summary.layers[1].memory_side_length), (3, 5));
+        for layer in &summary.layers {
+            assert_eq!(layer.weight_bits.len(), layer.width);
+            assert!(layer.weight_bits.iter().all(|&b| b == 128));
+        }
+    }
+
+    #[test]
+    fn test_summary_reports_poisoned_lock() {
+        let model = tiny_model();
+        let neuron = model.layers[1].neurons[0].clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = neuron.write().unwrap();
+            panic!("poison the neuron lock");
+        }).join();
+
+        let err = model.summary().unwrap_err();
+        assert!(err.contains("layer 1"), "{}", err);
+    }
+
+    #[test]
+    fn test_forward_rejects_drifted_neuron_discriminant() {
+        let model = tiny_model();
+        assert!(model.assert_consistent().is_ok());
+
+        model.layers[0].neurons[1].write().unwrap().discriminant = Integer::from(-23);
+        let err = model.forward(&[1, 2]).unwrap_err();
+        assert!(err.contains("neuron 1 of layer 0"), "{}", err);
+        assert_eq!(model.assert_consistent().unwrap_err(), err);
+    }
+
+    #[test]
+    fn test_fingerprint_tracks_weights_only() {
+        let model = tiny_model();
+        let twin = tiny_model();
+        assert_eq!(model.fingerprint(), twin.fingerprint());
+
+        // 推理只改变瞬态状态
+        model.forward(&[1, 2, 3]).unwrap();
+        assert_eq!(model.fingerprint(), twin.fingerprint());
+
+        // 相同神经元数、不同层划分
+        let discriminant = -((Integer::from(1) << 61) - 1u32);
+        let regrouped = HTPModel::new(vec![(1, 2, 4), (2, 2, 4)], discriminant);
+        assert_ne!(regrouped.fingerprint(), model.fingerprint());
+
+        twin.layers[1].neurons[0].write().unwrap().p_weight += 2;
+        assert_ne!(model.fingerprint(), twin.fingerprint());
+    }
+
+    #[test]
+    fn test_forward_trace_matches_forward() {
+        let model = tiny_model();
+        let tokens = [7u32, 8, 9];
+        let (root, trace) = model.forward_trace(&tokens).unwrap();
+
+        assert_eq!(trace.len(), model.layers.len());
+        for (layer, stream) in model.layers.iter().zip(&trace) {
+            assert_eq!(stream.len(), layer.width);
+        }
+
+        let mut folded = AffineTuple::identity(&model.discriminant);
+        for tuple in trace.last().unwrap() {
+            folded = folded.compose(tuple, &model.discriminant).unwrap();
+        }
+        assert_eq!(folded, root);
+        assert_eq!(root, model.forward(&tokens).unwrap());
+    }
+
+    #[test]
+    fn test_forward_with_cache_matches_forward() {
+        let model = tiny_model();
+        let mut cache = ForwardCache::new();
+
+        let mut tokens = Vec::new();
+        for tid in [3u32, 1, 4, 1] {
+            tokens.push(tid);
+            let cached = model.forward_with_cache(&tokens, &mut cache).unwrap();
+            assert_eq!(cached, model.forward(&tokens).unwrap());
+            assert_eq!(cache.layer_streams().len(), model.layers.len());
+        }
+
+        // 重复调用命中缓存
+        assert_eq!(model.forward_with_cache(&tokens, &mut cache).unwrap(), model.forward(&tokens).unwrap());
+
+        // 前缀变化必须使缓存失效
+        let changed = vec![2u32, 1, 4, 1, 5];
+        assert_eq!(model.forward_with_cache(&changed, &mut cache).unwrap(), model.forward(&changed).unwrap());
+    }
+}
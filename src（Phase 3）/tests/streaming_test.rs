@@ -65,7 +65,8 @@ mod tests {
         println!("💥 [Test] Testing Legacy Accumulation Fuse...");
 
         // 模拟恶意攻击者试图构造一个巨大的 P 因子
-        // 每次 P 增加 ~10 bits，循环 1000 次将达到 10000 bits > 8192
+        // 使用较低的上限 (256 bits) 让熔断尽快触发：每次 P 增加 ~10 bits
+        const TEST_LIMIT_BITS: u32 = 256;
         for _ in 0..1000 {
             let p = Integer::from(1009); 
             let q = ClassGroupElement::identity(&discriminant);
@@ -73,7 +74,7 @@ mod tests {
             
             // 这里应当在某一次循环中触发 Err/Panic
             // 因为 compose 内部有硬性的位宽检查
-            accumulator = accumulator.compose(&op, &discriminant).unwrap();
+            accumulator = accumulator.compose_with_limit(&op, &discriminant, TEST_LIMIT_BITS).unwrap();
         }
     }
 }
@@ -33,6 +33,31 @@ impl HyperTensor {
         Ok(root)
     }
 
+    /// 🔭 单轴投影：沿 `axis` 折叠张量，得到低一维的折叠根
+    /// 按“去掉 `axis` 分量后的坐标”分组，组内用 `commutative_merge` 聚合。
+    /// 这是正交锚点 (orthogonal anchors) 与逐轴结构分析的基础构件。
+    pub fn project_axis(&self, axis: usize) -> Result<HashMap<Vec<usize>, AffineTuple>, String> {
+        if axis >= self.dimensions {
+            return Err(format!("❌ Projection axis {} out of range (dimensions: {}).", axis, self.dimensions));
+        }
+
+        let flat_data = self.reconstruct_spatial_snapshot()?;
+        let mut projection: HashMap<Vec<usize>, AffineTuple> = HashMap::new();
+
+        for (coord, tuple) in &flat_data {
+            let mut reduced = coord.clone();
+            reduced.remove(axis);
+
+            let merged = match projection.get(&reduced) {
+                Some(acc) => acc.commutative_merge(tuple, &self.discriminant)?,
+                None => tuple.clone(),
+            };
+            projection.insert(reduced, merged);
+        }
+
+        Ok(projection)
+    }
+
     /// 🛠️ 从时间线重建空间快照
     fn reconstruct_spatial_snapshot(&self) -> Result<HashMap<Vec<usize>, AffineTuple>, String> {
         let mut snapshot = HashMap::new();
@@ -66,8 +91,13 @@ impl HyperTensor {
              return Ok(AffineTuple::identity(&self.discriminant));
         }
 
+        // 所有维度都已折叠：剩下的是同一坐标上的单元根，直接聚合返回
         if depth == self.dimensions {
-             return Ok(AffineTuple::identity(&self.discriminant));
+            let mut cell_agg = AffineTuple::identity(&self.discriminant);
+            for tuple in relevant_data.values() {
+                cell_agg = cell_agg.commutative_merge(tuple, &self.discriminant)?;
+            }
+            return Ok(cell_agg);
         }
 
         // [CRITICAL CHANGE]: 获取当前层需要折叠的“物理维度”
@@ -102,3 +132,73 @@ impl HyperTensor {
         Ok(layer_agg)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_root_merges_cell_roots() {
+        let d = -((Integer::from(1) << 61) - 1u32);
+        let g = ClassGroupElement::generator(&d);
+
+        // 空张量仍折叠为单位元
+        let mut tensor = HyperTensor::new(2, 4, d.clone());
+        assert_eq!(tensor.calculate_global_root().unwrap(), AffineTuple::identity(&d));
+
+        // 单个事件：全局根就是该事件本身
+        let single = AffineTuple { p_factor: Integer::from(3), q_shift: g.clone() };
+        tensor.insert("user_0", single.clone()).unwrap();
+        assert_eq!(tensor.calculate_global_root().unwrap(), single);
+
+        // 多个事件：全局根等于所有单元时间根的交换聚合
+        let mut p = Integer::from(5);
+        for i in 1..8u32 {
+            tensor.insert(&format!("user_{}", i), AffineTuple {
+                p_factor: p.clone(),
+                q_shift: g.pow(&Integer::from(i), &d).unwrap(),
+            }).unwrap();
+            p.next_prime_mut();
+        }
+
+        let mut expected = AffineTuple::identity(&d);
+        for cell_root in tensor.reconstruct_spatial_snapshot().unwrap().values() {
+            expected = expected.commutative_merge(cell_root, &d).unwrap();
+        }
+        let root = tensor.calculate_global_root().unwrap();
+        assert_eq!(root, expected);
+        assert_ne!(root, AffineTuple::identity(&d));
+    }
+
+    #[test]
+    fn test_project_axis_then_merge_matches_global_root() {
+        let d = -((Integer::from(1) << 61) - 1u32);
+        let mut tensor = HyperTensor::new(2, 4, d.clone());
+        let mut rng = rand::thread_rng();
+
+        let mut p = Integer::from(2);
+        for i in 0..12 {
+            tensor.insert(&format!("user_{}", i), AffineTuple {
+                p_factor: p.clone(),
+                q_shift: ClassGroupElement::random(&d, &mut rng).unwrap(),
+            }).unwrap();
+            p.next_prime_mut();
+        }
+
+        let global = tensor.calculate_global_root().unwrap();
+
+        for axis in 0..2 {
+            let projection = tensor.project_axis(axis).unwrap();
+            assert!(projection.keys().all(|k| k.len() == 1));
+
+            // 再沿剩余的一维折叠
+            let mut folded = AffineTuple::identity(&d);
+            for tuple in projection.values() {
+                folded = folded.commutative_merge(tuple, &d).unwrap();
+            }
+            assert_eq!(folded, global);
+        }
+
+        assert!(tensor.project_axis(2).is_err());
+    }
+}
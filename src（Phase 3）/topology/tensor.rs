@@ -8,6 +8,7 @@ use std::fs::File;
 use std::io::{BufReader, BufWriter};
 use rand::seq::SliceRandom; // 用于维度打乱测试
 use rand::thread_rng;
+use std::sync::OnceLock;
 
 pub type Coordinate = Vec<usize>;
 
@@ -15,23 +16,62 @@ pub type Coordinate = Vec<usize>;
 /// 负责单个张量单元内的时序聚合。
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct TimeSegmentTree {
+    /// 只追加 (append-only)；直接修改 `leaves` 会绕过根缓存，请使用 `append`
     pub leaves: Vec<AffineTuple>,
+
+    /// 根缓存: (判别式, 根)。`leaves` 只追加，缓存在下一次 `append` 前一直有效。
+    #[serde(skip)]
+    cached_root: OnceLock<(Integer, AffineTuple)>,
+}
+
+#[cfg(test)]
+thread_local! {
+    /// 测试用: 统计完整重建时间树的次数
+    static ROOT_BUILDS: std::cell::Cell<usize> = std::cell::Cell::new(0);
 }
 
 impl TimeSegmentTree {
     pub fn new() -> Self {
-        TimeSegmentTree { leaves: Vec::new() }
+        TimeSegmentTree { leaves: Vec::new(), cached_root: OnceLock::new() }
     }
 
     pub fn append(&mut self, tuple: AffineTuple) {
         self.leaves.push(tuple);
+        self.cached_root = OnceLock::new();
+    }
+
+    /// 已记录的事件数
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// 按时间顺序取第 `index` 个事件
+    pub fn get(&self, index: usize) -> Option<&AffineTuple> {
+        self.leaves.get(index)
     }
 
     pub fn root(&self, discriminant: &Integer) -> Result<AffineTuple, String> {
-        if self.leaves.is_empty() {
+        if self.is_empty() {
             return Ok(AffineTuple::identity(discriminant));
         }
-        self.build_tree_recursive(&self.leaves, discriminant)
+
+        if let Some((d, root)) = self.cached_root.get() {
+            if d == discriminant {
+                return Ok(root.clone());
+            }
+        }
+
+        #[cfg(test)]
+        ROOT_BUILDS.with(|c| c.set(c.get() + 1));
+
+        let root = self.build_tree_recursive(&self.leaves, discriminant)?;
+        // 若已被其他判别式占用则不覆盖，仅返回本次结果
+        let _ = self.cached_root.set((discriminant.clone(), root.clone()));
+        Ok(root)
     }
 
     fn build_tree_recursive(&self, nodes: &[AffineTuple], discriminant: &Integer) -> Result<AffineTuple, String> {
@@ -48,7 +88,7 @@ impl TimeSegmentTree {
 
         // [Non-Commutative]: Left ⊕ Right
         // 时间演化必须严格遵守顺序：先左后右
-        left.compose(&right, discriminant)
+        Ok(left.compose(&right, discriminant)?)
     }
 
     /// 🛡️ [FALSIFIABILITY BOUNDARY A]: Witness Index Validation
@@ -57,8 +97,8 @@ impl TimeSegmentTree {
         // [CRITICAL CHECK]: 索引越界即“伪证”
         // 如果请求的索引超出了当前记录的历史长度，说明该事件在物理时间上根本未发生。
         // 系统必须直接返回 Error，拒绝生成任何虚构的见证路径。
-        if index >= self.leaves.len() {
-            return Err(format!("❌ Security Halt: Witness index {} out of bounds (History Length: {}). Evolution cannot be extrapolated.", index, self.leaves.len()));
+        if self.get(index).is_none() {
+            return Err(format!("❌ Security Halt: Witness index {} out of bounds (History Length: {}). Evolution cannot be extrapolated.", index, self.len()));
         }
 
         let mut witness = Vec::new();
@@ -66,6 +106,31 @@ impl TimeSegmentTree {
         Ok(witness)
     }
 
+    /// ✅ Witness Verification: `generate_witness` 的对偶
+    /// 见证路径自根向叶记录兄弟节点，因此从最深一层开始逆序折叠：
+    /// `true` 表示兄弟在左 (`sibling ⊕ acc`)，`false` 表示兄弟在右 (`acc ⊕ sibling`)。
+    /// 任何合成错误都视为验证失败。
+    pub fn verify_witness(
+        leaf: &AffineTuple,
+        witness: &[(AffineTuple, bool)],
+        claimed_root: &AffineTuple,
+        discriminant: &Integer,
+    ) -> bool {
+        let mut acc = leaf.clone();
+        for (sibling, sibling_is_left) in witness.iter().rev() {
+            let next = if *sibling_is_left {
+                sibling.compose(&acc, discriminant)
+            } else {
+                acc.compose(sibling, discriminant)
+            };
+            acc = match next {
+                Ok(node) => node,
+                Err(_) => return false,
+            };
+        }
+        acc == *claimed_root
+    }
+
     fn generate_witness_recursive(
         &self, 
         nodes: &[AffineTuple], 
@@ -88,18 +153,29 @@ impl TimeSegmentTree {
             // Witness is Right Sibling (false flag for direction)
             witness.push((right_agg, false)); 
             let left_agg = self.generate_witness_recursive(left_slice, target_abs_index, current_offset, discriminant, witness)?;
-            return left_agg.compose(&self.build_tree_recursive(right_slice, discriminant)?, discriminant);
+            return Ok(left_agg.compose(&self.build_tree_recursive(right_slice, discriminant)?, discriminant)?);
         } else {
             // Target is in Right Subtree
             let left_agg = self.build_tree_recursive(left_slice, discriminant)?;
             // Witness is Left Sibling (true flag for direction)
             witness.push((left_agg, true));
             let right_agg = self.generate_witness_recursive(right_slice, target_abs_index, current_offset + mid, discriminant, witness)?;
-            return left_agg.compose(&right_agg, discriminant);
+            return Ok(left_agg.compose(&right_agg, discriminant)?);
         }
     }
 }
 
+/// 📜 HolographicReport: 全息对称性的机器可读证据
+/// 无论两种折叠顺序是否一致，都给出具体的根，供外部审计方序列化并独立重算。
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HolographicReport {
+    pub symmetric: bool,
+    pub order_a: Vec<usize>,
+    pub root_a: AffineTuple,
+    pub order_b: Vec<usize>,
+    pub root_b: AffineTuple,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct HyperTensor {
     pub dimensions: usize,
@@ -111,6 +187,11 @@ pub struct HyperTensor {
     
     #[serde(skip)]
     pub cached_root: Option<AffineTuple>, 
+
+    /// [Collision Audit]: 可选的反向索引 coord -> 落在该坐标的 user_id 列表
+    /// 默认关闭；通过 `enable_collision_tracking` 开启后由 `insert` 维护。
+    #[serde(default)]
+    pub id_index: Option<HashMap<Coordinate, Vec<String>>>,
 }
 
 impl HyperTensor {
@@ -121,6 +202,27 @@ impl HyperTensor {
             discriminant,
             data: HashMap::new(),
             cached_root: None,
+            id_index: None,
+        }
+    }
+
+    /// 🔍 开启坐标碰撞追踪 (仅记录开启之后插入的 id)
+    pub fn enable_collision_tracking(&mut self) {
+        if self.id_index.is_none() {
+            self.id_index = Some(HashMap::new());
+        }
+    }
+
+    /// 🔍 碰撞报告：列出有多于一个 id 落入的坐标
+    /// 这些坐标上的 `TimeSegmentTree` 混合了不同 id 的历史；
+    /// 出现碰撞时应考虑增大 `side_length` 或 `dimensions`。未开启追踪时返回空表。
+    pub fn collision_report(&self) -> HashMap<Coordinate, Vec<String>> {
+        match &self.id_index {
+            Some(index) => index.iter()
+                .filter(|(_, ids)| ids.len() > 1)
+                .map(|(coord, ids)| (coord.clone(), ids.clone()))
+                .collect(),
+            None => HashMap::new(),
         }
     }
 
@@ -156,6 +258,13 @@ impl HyperTensor {
 
     pub fn insert(&mut self, user_id: &str, new_tuple: AffineTuple) -> Result<(), String> {
         let coord = self.map_id_to_coord_hash(user_id);
+
+        if let Some(index) = self.id_index.as_mut() {
+            let ids = index.entry(coord.clone()).or_insert_with(Vec::new);
+            if !ids.iter().any(|id| id == user_id) {
+                ids.push(user_id.to_string());
+            }
+        }
         
         self.data.entry(coord)
             .or_insert_with(TimeSegmentTree::new)
@@ -164,9 +273,69 @@ impl HyperTensor {
         self.cached_root = None;
         Ok(())
     }
+
+    /// 🔗 合并另一个张量的历史 (例如分布式训练中两个 worker 的全息记忆)
+    /// 要求 `dimensions`、`side_length` 与 `discriminant` 完全一致。
+    /// 对每个坐标，`other` 的时间线事件按其原有顺序追加在 `self` 的事件之后。
+    ///
+    /// ⚠️ 时间折叠是非交换的：`a.merge(&b)` 与 `b.merge(&a)` 得到的单元根一般不同，
+    /// 调用方必须按真实的时间先后决定合并顺序。
+    pub fn merge(&mut self, other: &HyperTensor) -> Result<(), String> {
+        if self.dimensions != other.dimensions || self.side_length != other.side_length {
+            return Err(format!(
+                "❌ Merge mismatch: geometry {}x{} vs {}x{}.",
+                self.dimensions, self.side_length, other.dimensions, other.side_length
+            ));
+        }
+        if self.discriminant != other.discriminant {
+            return Err("❌ Merge mismatch: tensors use different discriminants.".to_string());
+        }
+
+        for (coord, other_tree) in &other.data {
+            let tree = self.data.entry(coord.clone()).or_insert_with(TimeSegmentTree::new);
+            for event in &other_tree.leaves {
+                tree.append(event.clone());
+            }
+        }
+
+        // 两边都开启了碰撞追踪时，合并 id 索引
+        if let (Some(index), Some(other_index)) = (self.id_index.as_mut(), other.id_index.as_ref()) {
+            for (coord, other_ids) in other_index {
+                let ids = index.entry(coord.clone()).or_insert_with(Vec::new);
+                for id in other_ids {
+                    if !ids.contains(id) {
+                        ids.push(id.clone());
+                    }
+                }
+            }
+        }
+
+        self.cached_root = None;
+        Ok(())
+    }
     
     // ... [save_to_disk / load_from_disk Omitted for brevity] ...
 
+    /// 📊 活跃单元数 (至少有一条时间线事件的坐标)
+    pub fn active_cell_count(&self) -> usize {
+        self.data.len()
+    }
+
+    /// 📊 全部时间线上的事件 (叶子) 总数
+    pub fn total_events(&self) -> usize {
+        self.data.values().map(|tree| tree.len()).sum()
+    }
+
+    /// 📊 负载因子 = 活跃单元数 / side_length^dimensions
+    /// 接近 1 时 `map_id_to_coord_hash` 的坐标碰撞会变得频繁。
+    pub fn load_factor(&self) -> f64 {
+        let capacity = (self.side_length as f64).powi(self.dimensions as i32);
+        if capacity == 0.0 {
+            return 0.0;
+        }
+        self.active_cell_count() as f64 / capacity
+    }
+
     pub fn get_segment_tree_path(&self, coord: &Coordinate, _axis: usize) -> Vec<AffineTuple> {
         if let Some(tree) = self.data.get(coord) {
             if let Ok(root) = tree.root(&self.discriminant) {
@@ -182,6 +351,23 @@ impl HyperTensor {
     /// 如果 Fold(Axis_A -> Axis_B) != Fold(Axis_B -> Axis_A)，
     /// 意味着空间算子混入了因果性（时间毒素），必须立即 Panic。
     pub fn verify_holographic_symmetry(&self) -> Result<bool, String> {
+        let report = self.holographic_witness()?;
+
+        if !report.symmetric {
+            // [FALSIFIED]: 证伪成功，系统存在严重逻辑漏洞
+            eprintln!("❌ HOLOGRAPHIC VIOLATION DETECTED!");
+            eprintln!("   Order A {:?} -> Root: {:?}", report.order_a, report.root_a);
+            eprintln!("   Order B {:?} -> Root: {:?}", report.order_b, report.root_b);
+            return Ok(false);
+        }
+
+        // [VERIFIED]: 全息一致性通过
+        Ok(true)
+    }
+
+    /// 🧾 全息对称性见证：返回两种折叠顺序及其对应的根
+    /// 维度 < 2 时没有可置换的轴，两条路径相同。
+    pub fn holographic_witness(&self) -> Result<HolographicReport, String> {
         // 1. Path A: 自然序 (Canonical Order)
         let order_a: Vec<usize> = (0..self.dimensions).collect();
         let root_a = self.compute_root_internal(&order_a)?;
@@ -191,26 +377,229 @@ impl HyperTensor {
         if self.dimensions >= 2 {
             // 交换前两个维度做最严格的测试
             order_b.swap(0, 1); 
-        } else {
-            return Ok(true);
         }
 
-        let root_b = self.compute_root_internal(&order_b)?;
+        let root_b = if order_b == order_a {
+            root_a.clone()
+        } else {
+            self.compute_root_internal(&order_b)?
+        };
 
         // 3. The Judgment (最终审判)
         // 比较 P 因子和 Q 移位是否完全一致
-        let p_match = root_a.p_factor == root_b.p_factor;
-        let q_match = root_a.q_shift == root_b.q_shift;
+        let symmetric = root_a.p_factor == root_b.p_factor && root_a.q_shift == root_b.q_shift;
 
-        if !p_match || !q_match {
-            // [FALSIFIED]: 证伪成功，系统存在严重逻辑漏洞
-            eprintln!("❌ HOLOGRAPHIC VIOLATION DETECTED!");
-            eprintln!("   Order A {:?} -> Root: {:?}", order_a, root_a);
-            eprintln!("   Order B {:?} -> Root: {:?}", order_b, root_b);
-            return Ok(false);
+        Ok(HolographicReport { symmetric, order_a, root_a, order_b, root_b })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phase3::core::algebra::ClassGroupElement;
+
+    fn root_builds() -> usize {
+        ROOT_BUILDS.with(|c| c.get())
+    }
+
+    #[test]
+    fn test_collision_report_lists_conflated_ids() {
+        let d = -((Integer::from(1) << 61) - 1u32);
+        // 1 维、边长 1：所有 id 必然落在同一坐标
+        let mut tensor = HyperTensor::new(1, 1, d.clone());
+        tensor.insert("before_tracking", AffineTuple::identity(&d)).unwrap();
+        assert!(tensor.collision_report().is_empty());
+
+        tensor.enable_collision_tracking();
+        tensor.insert("alice", AffineTuple::identity(&d)).unwrap();
+        assert!(tensor.collision_report().is_empty());
+
+        tensor.insert("bob", AffineTuple::identity(&d)).unwrap();
+        tensor.insert("alice", AffineTuple::identity(&d)).unwrap();
+
+        let report = tensor.collision_report();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[&vec![0]], vec!["alice".to_string(), "bob".to_string()]);
+    }
+
+    #[test]
+    fn test_density_metrics() {
+        let d = -((Integer::from(1) << 61) - 1u32);
+        let mut tensor = HyperTensor::new(2, 4, d.clone());
+        assert_eq!(tensor.active_cell_count(), 0);
+        assert_eq!(tensor.total_events(), 0);
+        assert_eq!(tensor.load_factor(), 0.0);
+
+        // 同一用户重复写入：单元数不变，事件数累加
+        for _ in 0..3 {
+            tensor.insert("alice", AffineTuple::identity(&d)).unwrap();
         }
+        assert_eq!(tensor.active_cell_count(), 1);
+        assert_eq!(tensor.total_events(), 3);
+        assert!((tensor.load_factor() - 1.0 / 16.0).abs() < 1e-12);
 
-        // [VERIFIED]: 全息一致性通过
-        Ok(true)
+        for i in 0..10 {
+            tensor.insert(&format!("user_{}", i), AffineTuple::identity(&d)).unwrap();
+        }
+        let cells = tensor.active_cell_count();
+        assert!(cells >= 1 && cells <= 16);
+        assert_eq!(tensor.total_events(), 13);
+        assert!((tensor.load_factor() - cells as f64 / 16.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_segment_tree_accessors() {
+        let d = -((Integer::from(1) << 61) - 1u32);
+        let mut tree = TimeSegmentTree::new();
+        assert!(tree.is_empty());
+        assert_eq!(tree.len(), 0);
+        assert!(tree.get(0).is_none());
+        assert!(tree.generate_witness(0, &d).is_err());
+
+        let events: Vec<AffineTuple> = (2..5u32)
+            .map(|p| AffineTuple { p_factor: Integer::from(p), q_shift: ClassGroupElement::identity(&d) })
+            .collect();
+        for event in &events {
+            tree.append(event.clone());
+        }
+
+        assert!(!tree.is_empty());
+        assert_eq!(tree.len(), 3);
+        for (i, event) in events.iter().enumerate() {
+            assert_eq!(tree.get(i), Some(event));
+        }
+        assert!(tree.get(3).is_none());
+        assert!(tree.generate_witness(2, &d).is_ok());
+        assert!(tree.generate_witness(3, &d).is_err());
+    }
+
+    #[test]
+    fn test_witness_round_trip() {
+        let d = -((Integer::from(1) << 61) - 1u32);
+        let g = ClassGroupElement::generator(&d);
+        let mut tree = TimeSegmentTree::new();
+        // 非 2 的幂，覆盖不平衡的切分
+        for i in 1..=5u32 {
+            tree.append(AffineTuple { p_factor: Integer::from(i + 1), q_shift: g.pow(&Integer::from(i * 7), &d).unwrap() });
+        }
+        let root = tree.root(&d).unwrap();
+
+        for index in 0..tree.len() {
+            let witness = tree.generate_witness(index, &d).unwrap();
+            let leaf = tree.get(index).unwrap();
+            assert!(TimeSegmentTree::verify_witness(leaf, &witness, &root, &d), "index {}", index);
+
+            // 换一片叶子、或翻转方向 (非交换) 都必须失败
+            let other = tree.get((index + 1) % tree.len()).unwrap();
+            assert!(!TimeSegmentTree::verify_witness(other, &witness, &root, &d));
+            let mut flipped = witness.clone();
+            flipped[0].1 = !flipped[0].1;
+            assert!(!TimeSegmentTree::verify_witness(leaf, &flipped, &root, &d));
+        }
+    }
+
+    #[test]
+    fn test_segment_tree_root_is_cached_until_append() {
+        let d = -((Integer::from(1) << 61) - 1u32);
+        let g = ClassGroupElement::generator(&d);
+        let mut tree = TimeSegmentTree::new();
+        for i in 1..4u32 {
+            tree.append(AffineTuple { p_factor: Integer::from(i + 2), q_shift: g.pow(&Integer::from(i), &d).unwrap() });
+        }
+
+        let before = root_builds();
+        let first = tree.root(&d).unwrap();
+        let second = tree.root(&d).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(root_builds() - before, 1);
+
+        // append 使缓存失效
+        tree.append(AffineTuple { p_factor: Integer::from(11), q_shift: g.clone() });
+        let third = tree.root(&d).unwrap();
+        assert_eq!(root_builds() - before, 2);
+        assert_ne!(third, first);
+        assert_eq!(third, first.compose(&tree.leaves[3], &d).unwrap());
+    }
+
+    #[test]
+    fn test_merge_appends_other_history_in_order() {
+        let d = -((Integer::from(1) << 61) - 1u32);
+        let g = ClassGroupElement::generator(&d);
+        let event = |p: u32, k: u32| AffineTuple { p_factor: Integer::from(p), q_shift: g.pow(&Integer::from(k), &d).unwrap() };
+
+        // 1 维、边长 1：两个 worker 的事件都落在同一单元
+        let mut worker_a = HyperTensor::new(1, 1, d.clone());
+        let mut worker_b = HyperTensor::new(1, 1, d.clone());
+        for i in 0..3u32 {
+            worker_a.insert(&format!("a_{}", i), event(2 * i + 3, i + 1)).unwrap();
+            worker_b.insert(&format!("b_{}", i), event(2 * i + 5, 3 * i + 2)).unwrap();
+        }
+        let root_a = worker_a.calculate_global_root().unwrap();
+        let root_b = worker_b.calculate_global_root().unwrap();
+
+        let mut merged = HyperTensor::new(1, 1, d.clone());
+        merged.merge(&worker_a).unwrap();
+        merged.cached_root = Some(root_a.clone());
+        merged.merge(&worker_b).unwrap();
+        assert!(merged.cached_root.is_none());
+        assert_eq!(merged.total_events(), 6);
+        assert_eq!(merged.calculate_global_root().unwrap(), root_a.compose(&root_b, &d).unwrap());
+
+        // 合并顺序很重要
+        let mut reversed = HyperTensor::new(1, 1, d.clone());
+        reversed.merge(&worker_b).unwrap();
+        reversed.merge(&worker_a).unwrap();
+        assert_ne!(reversed.calculate_global_root().unwrap(), merged.calculate_global_root().unwrap());
+
+        // 多单元：每个坐标的时间线独立拼接
+        let mut left = HyperTensor::new(2, 4, d.clone());
+        let mut right = HyperTensor::new(2, 4, d.clone());
+        for i in 0..6u32 {
+            left.insert(&format!("user_{}", i), event(i + 2, i + 1)).unwrap();
+            right.insert(&format!("user_{}", i + 3), event(i + 7, 2 * i + 1)).unwrap();
+        }
+        let mut combined = HyperTensor::new(2, 4, d.clone());
+        combined.merge(&left).unwrap();
+        combined.merge(&right).unwrap();
+        for (coord, tree) in &combined.data {
+            let expected = match (left.data.get(coord), right.data.get(coord)) {
+                (Some(l), Some(r)) => l.root(&d).unwrap().compose(&r.root(&d).unwrap(), &d).unwrap(),
+                (Some(l), None) => l.root(&d).unwrap(),
+                (None, Some(r)) => r.root(&d).unwrap(),
+                (None, None) => unreachable!(),
+            };
+            assert_eq!(tree.root(&d).unwrap(), expected);
+        }
+
+        assert!(combined.merge(&HyperTensor::new(2, 5, d.clone())).is_err());
+        assert!(combined.merge(&HyperTensor::new(2, 4, Integer::from(-23))).is_err());
+    }
+
+    #[test]
+    fn test_holographic_witness_reports_both_roots() {
+        // Delta = -(2^61 - 1)
+        let d = -((Integer::from(1) << 61) - 1u32);
+        let g = ClassGroupElement::generator(&d);
+        let mut tensor = HyperTensor::new(3, 4, d.clone());
+        for i in 0..6u32 {
+            let tuple = AffineTuple {
+                p_factor: Integer::from(2 * i + 3),
+                q_shift: g.pow(&Integer::from(i + 1), &d).unwrap(),
+            };
+            tensor.insert(&format!("user_{}", i), tuple).unwrap();
+        }
+
+        let report = tensor.holographic_witness().unwrap();
+        assert!(report.symmetric);
+        assert_eq!(report.order_a, vec![0, 1, 2]);
+        assert_eq!(report.order_b, vec![1, 0, 2]);
+        assert_eq!(report.root_a, report.root_b);
+        assert_eq!(report.root_a, tensor.compute_root_internal(&report.order_a).unwrap());
+        assert!(tensor.verify_holographic_symmetry().unwrap());
+
+        // 可序列化，供外部审计
+        let json = serde_json::to_string(&report).unwrap();
+        let back: HolographicReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, report);
     }
 }
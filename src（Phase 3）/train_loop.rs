@@ -5,11 +5,22 @@ use crate::phase3::decoder::InverseDecoder;
 use crate::phase3::core::primes::hash_to_prime;
 use crate::phase3::core::affine::AffineTuple;
 use crate::phase3::core::algebra::ClassGroupElement;
+use crate::phase3::topology::tensor::Coordinate;
 
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
 use std::sync::{Arc, RwLock};
 use rand::{Rng, RngCore}; 
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
 use rand::rngs::OsRng;     
 use rug::Integer;
+use serde::{Serialize, Deserialize};
+
+/// 投影坐标缓存上限，超过后整体清空
+const COORD_CACHE_CAPACITY: usize = 4096;
 
 /// 突变策略枚举
 enum MutationStrategy {
@@ -17,80 +28,247 @@ enum MutationStrategy {
     LocalShift,
 }
 
+/// 单个样本的解码结局
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExampleOutcome {
+    Miss,
+    Drift(usize),
+    Exact,
+}
+
+impl ExampleOutcome {
+    fn loss(&self) -> f32 {
+        match self {
+            ExampleOutcome::Miss => 1.0,
+            ExampleOutcome::Drift(drift) => 0.1 * (*drift as f32),
+            ExampleOutcome::Exact => 0.0,
+        }
+    }
+}
+
+/// 基因池持久化格式: P 权重以十进制字符串存储
+#[derive(Serialize, Deserialize)]
+struct GeneRecord {
+    gene: String,
+    fitness: f64,
+}
+
 /// 🧬 EvolutionaryTrainer: 进化训练器 (Enhanced with Memetic Search)
 pub struct EvolutionaryTrainer {
     pub model: Arc<RwLock<HTPModel>>,
     pub decoder: InverseDecoder,
     pub learning_rate: f64, 
-    pub gene_pool: Vec<Integer>,
+    /// 精英基因环形缓冲: (P 权重, 适应度)
+    pub gene_pool: VecDeque<(Integer, f64)>,
     pub max_pool_size: usize,
+    /// 每次命中后收割精英基因的概率
+    pub harvest_probability: f64,
+    /// LocalShift 的连续性阈值 = `search_radius * lipschitz_factor`
+    pub lipschitz_factor: usize,
+    /// 通过 Lipschitz 过滤器的 LocalShift 次数
+    pub accepted_mutations: u64,
+    /// 被 Continuity Trap 拒绝的 LocalShift 次数
+    pub rejected_mutations: u64,
+    /// 每个判别式的生成元缓存 (generator() 含素数搜索，不能放在热循环里)
+    generator_cache: HashMap<Integer, ClassGroupElement>,
+    /// generator() 实际被调用的次数
+    pub generator_computations: u64,
+    /// (P, Δ) -> 投影坐标，避免重复计算当前权重的基准坐标
+    coord_cache: HashMap<(Integer, Integer), Coordinate>,
 }
 
 impl EvolutionaryTrainer {
     pub fn new(model: Arc<RwLock<HTPModel>>, vocab_size: u32) -> Self {
+        let mut generator_cache = HashMap::new();
+        if let Ok(model_guard) = model.read() {
+            let d = model_guard.discriminant.clone();
+            generator_cache.insert(d.clone(), ClassGroupElement::generator(&d));
+        }
+        let generator_computations = generator_cache.len() as u64;
+
         EvolutionaryTrainer {
             model,
             decoder: InverseDecoder::new(vocab_size),
             learning_rate: 0.05, 
-            gene_pool: Vec::new(),
+            gene_pool: VecDeque::new(),
             max_pool_size: 200, 
+            harvest_probability: 0.1,
+            lipschitz_factor: 2,
+            accepted_mutations: 0,
+            rejected_mutations: 0,
+            generator_cache,
+            generator_computations,
+            coord_cache: HashMap::new(),
         }
     }
 
     /// 🏋️ Train Step: 单步进化循环
     pub fn train_step(&mut self, input_ids: &[u32], target_id: u32) -> Result<f32, String> {
+        let (outcome, confidence) = self.evaluate_example(input_ids, target_id)?;
+
+        match outcome {
+            ExampleOutcome::Miss => self.punish_path_mutation(),
+            ExampleOutcome::Drift(drift) => {
+                let drift_risk = (drift as f64) * 0.05; 
+                
+                let mut rng = rand::thread_rng();
+                if rng.gen_bool(drift_risk.min(0.5)) { 
+                    self.apply_micro_mutation();
+                }
+            },
+            ExampleOutcome::Exact => self.reward_and_harvest(confidence),
+        }
+
+        Ok(outcome.loss())
+    }
+
+    /// 📜 Train Sequence: 对整段续写做逐位置训练 (Teacher Forcing)
+    /// 第 i 步以 `input_ids ++ targets[..i]` 为前缀预测 `targets[i]`，
+    /// 每一步都按 `train_step` 的规则立即奖励 / 惩罚。返回逐位置的 Loss。
+    pub fn train_sequence(&mut self, input_ids: &[u32], targets: &[u32]) -> Result<Vec<f32>, String> {
+        if targets.is_empty() {
+            return Err("Empty target sequence".to_string());
+        }
+
+        let mut prefix = input_ids.to_vec();
+        let mut losses = Vec::with_capacity(targets.len());
+        for &target_id in targets {
+            losses.push(self.train_step(&prefix, target_id)?);
+            prefix.push(target_id);
+        }
+        Ok(losses)
+    }
+
+    /// 📦 Train Batch: 聚合整批样本后再决定突变
+    /// 突变强度由整批的 Miss / Drift 比例决定，而不是逐样本掷骰子，从而降低方差。
+    /// 只要有样本精确命中，就以精确命中的置信度之和除以批大小 (Drift / Miss 计 0) 作为适应度收割当前权重。
+    /// 返回平均 Loss。
+    pub fn train_batch(&mut self, examples: &[(Vec<u32>, u32)]) -> Result<f32, String> {
+        if examples.is_empty() {
+            return Err("Empty training batch".to_string());
+        }
+
+        let mut total_loss = 0.0f32;
+        let mut exact_confidence = 0.0f64;
+        let mut misses = 0usize;
+        let mut drifts = 0usize;
+
+        for (input_ids, target_id) in examples {
+            let (outcome, confidence) = self.evaluate_example(input_ids, *target_id)?;
+            total_loss += outcome.loss();
+            match outcome {
+                ExampleOutcome::Miss => misses += 1,
+                ExampleOutcome::Drift(_) => drifts += 1,
+                ExampleOutcome::Exact => exact_confidence += confidence,
+            }
+        }
+
+        let n = examples.len() as f64;
+        // 收割在突变之前进行，记录的是产生这批结果的权重
+        let fitness = exact_confidence / n;
+        if fitness > 0.0 {
+            self.reward_and_harvest(fitness);
+        }
+
+        if misses > 0 {
+            // 按 Miss 比例缩放探索强度
+            self.mutate_network_scaled(MutationStrategy::HardReset, misses as f64 / n);
+        } else if drifts > 0 {
+            self.mutate_network_scaled(MutationStrategy::LocalShift, drifts as f64 / n);
+        }
+
+        Ok(total_loss / examples.len() as f32)
+    }
+
+    /// 前向 + 解码，判定单个样本的结局及其解码置信度 (Miss 为 0)，不产生副作用
+    fn evaluate_example(&self, input_ids: &[u32], target_id: u32) -> Result<(ExampleOutcome, f64), String> {
         let prediction_root = {
             let model_guard = self.model.read().map_err(|_| "Model Lock Poisoned")?;
             model_guard.forward(input_ids)?
         };
 
         let decode_result = self.decoder.decode(&prediction_root)
-            .unwrap_or(crate::phase3::decoder::DecodeResult { token_id: u32::MAX, drift: usize::MAX });
-
-        let is_target_hit = decode_result.token_id == target_id;
-        let mut loss = 0.0;
-
-        if !is_target_hit {
-            loss = 1.0;
-            self.punish_path_mutation();
-        } 
-        else if decode_result.drift > 0 {
-            loss = 0.1 * (decode_result.drift as f32);
-            let drift_risk = (decode_result.drift as f64) * 0.05; 
-            
-            let mut rng = rand::thread_rng();
-            if rng.gen_bool(drift_risk.min(0.5)) { 
-                self.apply_micro_mutation();
-            }
-        }
-        else {
-            loss = 0.0;
-            self.reward_and_harvest();
-        }
+            .unwrap_or(crate::phase3::decoder::DecodeResult { token_id: u32::MAX, drift: usize::MAX, confidence: 0.0 });
 
-        Ok(loss)
+        Ok(if decode_result.token_id != target_id {
+            (ExampleOutcome::Miss, 0.0)
+        } else if decode_result.is_exact() {
+            (ExampleOutcome::Exact, decode_result.confidence)
+        } else {
+            (ExampleOutcome::Drift(decode_result.drift), decode_result.confidence)
+        })
     }
 
-    fn reward_and_harvest(&mut self) {
+    /// 以 `harvest_probability` 的概率收割精英基因
+    /// 只在精确命中时调用；`fitness` 取自精确命中的解码置信度，批次中精确命中率越低，适应度越低。
+    fn reward_and_harvest(&mut self, fitness: f64) {
         let mut rng = rand::thread_rng();
-        if rng.gen_bool(0.1) { 
+        if rng.gen_bool(self.harvest_probability.clamp(0.0, 1.0)) { 
+             let mut harvested = Vec::new();
              if let Ok(model_guard) = self.model.read() {
                  for layer in &model_guard.layers {
                      if let Some(neuron) = layer.neurons.choose(&mut rng) {
                          if let Ok(guard) = neuron.read() {
-                             self.add_to_gene_pool(guard.p_weight.clone());
+                             harvested.push(guard.p_weight.clone());
                          }
                      }
                  }
              }
+             for gene in harvested {
+                 self.add_to_gene_pool(gene, fitness);
+             }
         }
     }
 
-    fn add_to_gene_pool(&mut self, gene: Integer) {
+    fn add_to_gene_pool(&mut self, gene: Integer, fitness: f64) {
+        if self.max_pool_size == 0 {
+            return;
+        }
+        // O(1) 淘汰最旧的基因
         if self.gene_pool.len() >= self.max_pool_size {
-            self.gene_pool.remove(0); 
+            self.gene_pool.pop_front(); 
+        }
+        self.gene_pool.push_back((gene, fitness));
+    }
+
+    /// 💾 将基因池写入磁盘，用于长时间训练的 Checkpoint
+    pub fn save_gene_pool<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let records: Vec<GeneRecord> = self.gene_pool.iter()
+            .map(|(gene, fitness)| GeneRecord { gene: gene.to_string_radix(10), fitness: *fitness })
+            .collect();
+
+        let file = File::create(path).map_err(|e| format!("Gene pool save failed: {}", e))?;
+        serde_json::to_writer(BufWriter::new(file), &records)
+            .map_err(|e| format!("Gene pool serialization failed: {}", e))
+    }
+
+    /// 📂 从磁盘恢复基因池 (追加到当前池)
+    /// 只接纳通过素性测试的基因，防止损坏的文件污染种群。返回被接纳的数量。
+    pub fn load_gene_pool<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, String> {
+        let file = File::open(path).map_err(|e| format!("Gene pool load failed: {}", e))?;
+        let records: Vec<GeneRecord> = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Gene pool deserialization failed: {}", e))?;
+
+        let mut admitted = 0;
+        for record in records {
+            let gene = match Integer::from_str_radix(&record.gene, 10) {
+                Ok(gene) => gene,
+                Err(_) => continue,
+            };
+            if gene.is_probably_prime(25) == rug::integer::IsPrime::No || !record.fitness.is_finite() {
+                continue;
+            }
+            self.add_to_gene_pool(gene, record.fitness);
+            admitted += 1;
         }
-        self.gene_pool.push(gene);
+        Ok(admitted)
+    }
+
+    /// 按适应度比例 (Roulette Wheel) 抽取精英基因
+    fn select_elite<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Integer> {
+        let weights = self.gene_pool.iter().map(|(_, fitness)| fitness.max(0.0));
+        let dist = WeightedIndex::new(weights).ok()?;
+        self.gene_pool.get(dist.sample(rng)).map(|(gene, _)| gene.clone())
     }
 
     fn punish_path_mutation(&mut self) {
@@ -103,22 +281,28 @@ impl EvolutionaryTrainer {
 
     /// 通用突变逻辑 (Memetic Algorithm + Lipschitz Filter)
     fn mutate_network(&mut self, strategy: MutationStrategy) {
+        self.mutate_network_scaled(strategy, 1.0);
+    }
+
+    /// 按 `scale` 缩放每个神经元的突变概率 (`learning_rate * scale`)
+    fn mutate_network_scaled(&mut self, strategy: MutationStrategy, scale: f64) {
         let mut rng = rand::thread_rng(); 
+        let mutation_rate = (self.learning_rate * scale).clamp(0.0, 1.0);
         
         let mut model_guard = self.model.write().expect("Model Lock Poisoned during mutation");
 
         for layer in &mut model_guard.layers {
             for neuron_lock in &layer.neurons {
-                if rng.gen_bool(self.learning_rate) {
+                if rng.gen_bool(mutation_rate) {
                     
                     let mut neuron_mut = neuron_lock.write().expect("Neuron Lock Poisoned");
 
                     match strategy {
                         // [Strategy 1]: Hard Reset (Exploration)
                         MutationStrategy::HardReset => {
-                            if !self.gene_pool.is_empty() && rng.gen_bool(0.3) {
-                                let elite_gene = self.gene_pool.choose(&mut rng).unwrap();
-                                neuron_mut.p_weight = elite_gene.clone(); 
+                            let elite_gene = if rng.gen_bool(0.3) { self.select_elite(&mut rng) } else { None };
+                            if let Some(elite_gene) = elite_gene {
+                                neuron_mut.p_weight = elite_gene; 
                                 if let Ok(mut memory_guard) = neuron_mut.memory.write() {
                                     memory_guard.data.clear();
                                     memory_guard.cached_root = None;
@@ -143,15 +327,33 @@ impl EvolutionaryTrainer {
                         
                         // [Strategy 2]: Local Shift (Exploitation) with Lipschitz Filter
                         MutationStrategy::LocalShift => {
-                            let current_p = &neuron_mut.p_weight;
+                            let current_p = neuron_mut.p_weight.clone();
+                            let discriminant = neuron_mut.discriminant.clone();
+
+                            // 生成元按判别式缓存，只计算一次
+                            let generator = match self.generator_cache.get(&discriminant) {
+                                Some(g) => g.clone(),
+                                None => {
+                                    let g = ClassGroupElement::generator(&discriminant);
+                                    self.generator_computations += 1;
+                                    self.generator_cache.insert(discriminant.clone(), g.clone());
+                                    g
+                                }
+                            };
                             
                             // 1. 捕获当前坐标 (基准点)
                             // 我们使用 Generator 作为标准输入来测量 P 的投影特性
-                            let dummy_tuple_old = AffineTuple { 
-                                p_factor: current_p.clone(), 
-                                q_shift: ClassGroupElement::generator(&neuron_mut.discriminant).pow(current_p, &neuron_mut.discriminant).unwrap() 
+                            let coord_key = (current_p.clone(), discriminant.clone());
+                            let old_coord = match self.coord_cache.get(&coord_key) {
+                                Some(coord) => coord.clone(),
+                                None => {
+                                    let dummy_tuple_old = AffineTuple { 
+                                        p_factor: current_p.clone(), 
+                                        q_shift: generator.pow(&current_p, &discriminant).unwrap() 
+                                    };
+                                    self.decoder.extract_coordinate(&dummy_tuple_old)
+                                }
                             };
-                            let old_coord = self.decoder.extract_coordinate(&dummy_tuple_old);
 
                             // 2. 生成候选突变 (Trial)
                             let direction = if rng.gen_bool(0.5) { 1 } else { -1 };
@@ -168,18 +370,25 @@ impl EvolutionaryTrainer {
                             // 3. 预计算新坐标 (Simulation)
                             let dummy_tuple_new = AffineTuple { 
                                 p_factor: new_prime.clone(), 
-                                q_shift: ClassGroupElement::generator(&neuron_mut.discriminant).pow(&new_prime, &neuron_mut.discriminant).unwrap() 
+                                q_shift: generator.pow(&new_prime, &discriminant).unwrap() 
                             };
                             let new_coord = self.decoder.extract_coordinate(&dummy_tuple_new);
 
                             // 4. Lipschitz 过滤器 (The Filter)
                             // 防止坐标瞬移。如果跳得太远，说明这个素数导致了投影空间的“断裂”。
-                            // 阈值设为搜索半径的 2 倍。
+                            // 阈值设为搜索半径的 `lipschitz_factor` 倍 (默认 2)。
                             let jump_distance = self.decoder.calculate_distance(&old_coord, &new_coord);
-                            let continuity_threshold = self.decoder.search_radius * 2;
+
+                            if self.coord_cache.len() >= COORD_CACHE_CAPACITY {
+                                self.coord_cache.clear();
+                            }
+                            self.coord_cache.insert(coord_key, old_coord);
+                            self.coord_cache.insert((new_prime.clone(), discriminant), new_coord);
+                            let continuity_threshold = self.decoder.search_radius * self.lipschitz_factor;
 
                             if jump_distance <= continuity_threshold {
                                 // ✅ 接受：这是一个平滑的移动
+                                self.accepted_mutations += 1;
                                 neuron_mut.p_weight = new_prime;
                                 if let Ok(mut memory_guard) = neuron_mut.memory.write() {
                                     memory_guard.data.clear();
@@ -188,6 +397,7 @@ impl EvolutionaryTrainer {
                             } else {
                                 // ❌ 拒绝：这是不连续的跳变 (Continuity Trap)
                                 // 保持原权重不变，等待下一次随机游走
+                                self.rejected_mutations += 1;
                             }
                         }
                     }
@@ -196,3 +406,174 @@ impl EvolutionaryTrainer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiny_trainer() -> EvolutionaryTrainer {
+        // Delta = -(2^61 - 1)
+        let discriminant = -((Integer::from(1) << 61) - 1u32);
+        let model = HTPModel::new(vec![(1, 2, 4)], discriminant);
+        let mut trainer = EvolutionaryTrainer::new(Arc::new(RwLock::new(model)), 8);
+        // 冻结权重，使 Loss 可复现
+        trainer.learning_rate = 0.0;
+        trainer
+    }
+
+    /// 将模型当前对 `input_ids` 的预测点登记为 `target_id` 的精确坐标
+    fn pin_target(trainer: &mut EvolutionaryTrainer, input_ids: &[u32], target_id: u32) {
+        let root = trainer.model.read().unwrap().forward(input_ids).unwrap();
+        let coord = trainer.decoder.extract_coordinate(&root);
+        let prime = Integer::from(1_000_003u32 + target_id);
+        trainer.decoder.vocab_tensor.star_map.insert(coord, prime.clone());
+        trainer.decoder.vocab_tensor.prime_to_id.insert(prime, target_id);
+    }
+
+    #[test]
+    fn test_train_batch_mean_loss() {
+        let mut trainer = tiny_trainer();
+        let input = vec![1u32, 2, 3];
+        pin_target(&mut trainer, &input, 5);
+
+        // 反复命中同一目标: 平均 Loss 为 0
+        let hits = vec![(input.clone(), 5u32); 4];
+        for _ in 0..3 {
+            assert_eq!(trainer.train_batch(&hits).unwrap(), 0.0);
+        }
+
+        // 一半命中，一半错失
+        let mixed = vec![(input.clone(), 5u32), (input.clone(), 6u32)];
+        assert!((trainer.train_batch(&mixed).unwrap() - 0.5).abs() < 1e-6);
+
+        assert!(trainer.train_batch(&[]).is_err());
+    }
+
+    #[test]
+    fn test_train_sequence_reports_per_position_loss() {
+        let mut trainer = tiny_trainer();
+        let input = vec![1u32, 2];
+        pin_target(&mut trainer, &[1, 2], 4);
+        pin_target(&mut trainer, &[1, 2, 4], 6);
+
+        // 前两个位置命中；第三个目标不在词表中 (vocab = 8)，必然错失
+        let losses = trainer.train_sequence(&input, &[4, 6, 99]).unwrap();
+        assert_eq!(losses.len(), 3);
+        assert_eq!(&losses[..2], &[0.0, 0.0]);
+        assert_eq!(losses[2], 1.0);
+
+        assert!(trainer.train_sequence(&input, &[]).is_err());
+    }
+
+    #[test]
+    fn test_elite_selection_is_fitness_weighted() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let mut trainer = tiny_trainer();
+        trainer.max_pool_size = 2;
+        trainer.add_to_gene_pool(Integer::from(11), 0.1);
+        trainer.add_to_gene_pool(Integer::from(13), 0.9);
+        trainer.add_to_gene_pool(Integer::from(17), 0.9);
+        // 环形缓冲淘汰最旧的基因
+        assert_eq!(trainer.gene_pool.len(), 2);
+        assert_eq!(trainer.gene_pool[0].0, 13);
+
+        trainer.gene_pool[0].1 = 0.1;
+        let mut rng = StdRng::seed_from_u64(1);
+        let draws = 4000;
+        let strong = (0..draws)
+            .filter(|_| trainer.select_elite(&mut rng).unwrap() == 17)
+            .count();
+        // 期望约 90%
+        assert!(strong > draws * 8 / 10, "strong gene drawn {} / {}", strong, draws);
+    }
+
+    #[test]
+    fn test_harvested_fitness_tracks_batch_hit_rate() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let mut trainer = tiny_trainer();
+        trainer.harvest_probability = 1.0;
+        let input = vec![1u32, 2, 3];
+        let neuron = trainer.model.read().unwrap().layers[0].neurons[0].clone();
+        let gene_a = neuron.read().unwrap().p_weight.clone();
+        pin_target(&mut trainer, &input, 5);
+
+        // 全部命中
+        trainer.train_batch(&[(input.clone(), 5), (input.clone(), 5)]).unwrap();
+
+        // 换一个权重，只命中一半
+        let gene_b = hash_to_prime("harvest_gene_b", 128).unwrap();
+        neuron.write().unwrap().p_weight = gene_b.clone();
+        pin_target(&mut trainer, &input, 5);
+        trainer.train_batch(&[(input.clone(), 5), (input.clone(), 6)]).unwrap();
+
+        // 全部错失的批次不收割
+        trainer.train_batch(&[(input.clone(), 6)]).unwrap();
+
+        assert_eq!(trainer.gene_pool.len(), 2);
+        let (fitness_a, fitness_b) = (trainer.gene_pool[0].1, trainer.gene_pool[1].1);
+        assert_eq!((&trainer.gene_pool[0].0, &trainer.gene_pool[1].0), (&gene_a, &gene_b));
+        assert!((fitness_a - 2.0 * fitness_b).abs() < 1e-9, "fitness {} vs {}", fitness_a, fitness_b);
+
+        // 轮盘赌的选择概率随之不同: 约 2/3 vs 1/3
+        let mut rng = StdRng::seed_from_u64(5);
+        let draws = 3000;
+        let picked_a = (0..draws)
+            .filter(|_| trainer.select_elite(&mut rng).unwrap() == gene_a)
+            .count();
+        assert!(picked_a > draws * 3 / 5 && picked_a < draws * 3 / 4, "gene a drawn {} / {}", picked_a, draws);
+    }
+
+    #[test]
+    fn test_gene_pool_round_trip() {
+        let mut trainer = tiny_trainer();
+        let big_prime = hash_to_prime("gene_pool_round_trip", 128).unwrap();
+        trainer.add_to_gene_pool(big_prime.clone(), 0.75);
+        trainer.add_to_gene_pool(Integer::from(13), 1.0);
+
+        let path = std::env::temp_dir().join(format!("htp_gene_pool_{}.json", std::process::id()));
+        trainer.save_gene_pool(&path).unwrap();
+
+        let mut restored = tiny_trainer();
+        assert_eq!(restored.load_gene_pool(&path).unwrap(), 2);
+        assert_eq!(restored.gene_pool, trainer.gene_pool);
+
+        // 合数不会被接纳
+        std::fs::write(&path, r#"[{"gene":"15","fitness":1.0},{"gene":"17","fitness":0.5}]"#).unwrap();
+        let mut filtered = tiny_trainer();
+        assert_eq!(filtered.load_gene_pool(&path).unwrap(), 1);
+        assert_eq!(filtered.gene_pool[0].0, 17);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tiny_lipschitz_threshold_rejects_shifts() {
+        let mut trainer = tiny_trainer();
+        trainer.learning_rate = 1.0;
+        trainer.lipschitz_factor = 0;
+
+        for _ in 0..10 {
+            trainer.apply_micro_mutation();
+        }
+
+        let total = trainer.accepted_mutations + trainer.rejected_mutations;
+        assert_eq!(total, 10);
+        // 阈值为 0 时只有零位移的突变能通过
+        assert!(trainer.rejected_mutations >= 8, "rejected {} / {}", trainer.rejected_mutations, total);
+    }
+
+    #[test]
+    fn test_generator_is_computed_once() {
+        let mut trainer = tiny_trainer();
+        trainer.learning_rate = 1.0;
+        assert_eq!(trainer.generator_computations, 1);
+
+        for _ in 0..5 {
+            trainer.apply_micro_mutation();
+        }
+        assert_eq!(trainer.generator_computations, 1);
+        assert!(!trainer.coord_cache.is_empty());
+    }
+}